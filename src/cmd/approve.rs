@@ -0,0 +1,81 @@
+//! record who approved an artifact and when
+
+use time;
+
+use super::types::*;
+use core::{review, save};
+use core::types::{Approval, Approvals};
+
+/// the inputs of the approve command
+#[derive(Debug)]
+pub struct ApproveCmd {
+    pub name: ArtName,
+    pub by: String,
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("approve")
+        .about("record the approval of the current version of an artifact. The approval \
+                is stale once its text, partof or status changes")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("name")
+                 .help("the artifact to approve")
+                 .required(true))
+        .arg(Arg::with_name("by")
+                 .long("by")
+                 .value_name("NAME")
+                 .help("the name of the approver")
+                 .takes_value(true)
+                 .required(true))
+}
+
+/// get all the information from the user input
+pub fn get_approve_cmd(matches: &ArgMatches) -> Result<ApproveCmd, String> {
+    let name = try!(ArtName::from_str(matches.value_of("name").unwrap())
+        .map_err(|e| e.to_string()));
+    let by = matches.value_of("by").unwrap().trim();
+    if by.is_empty() {
+        return Err("the name of the approver cannot be empty".to_string());
+    }
+    Ok(ApproveCmd {
+        name: name,
+        by: by.to_string(),
+    })
+}
+
+/// record the approval at the time `at` in `approvals`, returning the
+/// approved name as it is defined and the approval
+pub fn approve_name(artifacts: &Artifacts, approvals: &mut Approvals, cmd: &ApproveCmd,
+                    at: &str)
+                    -> Result<(ArtNameRc, Approval), String> {
+    let name = match artifacts.get_key_value(&cmd.name) {
+        Some((n, _)) => n.clone(),
+        None => {
+            return Err(format!("{} does not exist{}",
+                               cmd.name, utils::did_you_mean(&cmd.name, artifacts.keys())))
+        }
+    };
+    let approval = try!(review::approve(artifacts, approvals, &name, &cmd.by, at)
+        .map_err(|e| e.desc));
+    review::prune_approvals(artifacts, approvals);
+    Ok((name, approval))
+}
+
+/// write the approval to the file in the settings directory
+/// partof: #SPC-approve
+pub fn do_approve<W: Write>(w: &mut W, cfg: &Path, artifacts: &Artifacts, cmd: &ApproveCmd)
+                            -> Result<(), String> {
+    let mut approvals = try!(review::load_approvals(cfg).map_err(|e| e.to_string()));
+    let at = time::now_utc().rfc3339().to_string();
+    let (name, approval) = try!(approve_name(artifacts, &mut approvals, cmd, &at));
+    let path = cfg.join(review::APPROVALS_FILE);
+    let original = if path.exists() {
+        try!(save::read_file(&path).map_err(|e| e.to_string()))
+    } else {
+        String::new()
+    };
+    let change = (path, original, review::fmt_approvals(&approvals));
+    try!(save::write_files(&[change]).map_err(|e| e.to_string()));
+    write!(w, "{}: approved by {} at {}\n", name, approval.by, approval.at).unwrap();
+    Ok(())
+}
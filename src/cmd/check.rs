@@ -0,0 +1,267 @@
+//! check the project for errors and thresholds, for use in CI
+
+use super::types::*;
+use super::status;
+use core::{link, review};
+use core::types::ApprovalState;
+
+lazy_static!{
+    static ref THRESHOLD_RE: Regex = Regex::new(
+        r"^\s*(\S+)\s+(?:(avg)\s+)?(completed|tested)\s*(>=|<=|>|<)\s*(\d+)%?\s*$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Completed,
+    Tested,
+}
+
+/// a threshold that the project must meet, given in the form
+/// `PATTERN [avg] FIELD OP PERCENT`, i.e. `REQ avg completed >= 80`
+///
+/// PATTERN is a regexp matched against the start of artifact names.
+/// If `avg` is given the average of the matching artifacts must meet the
+/// threshold, otherwise every matching artifact must meet it.
+#[derive(Debug)]
+pub struct Threshold {
+    pub raw: String,
+    pub pattern: Regex,
+    pub avg: bool,
+    pub field: Field,
+    pub op: String,
+    pub perc: u8,
+}
+
+impl Threshold {
+    pub fn from_str(s: &str) -> Result<Threshold, String> {
+        let caps = match THRESHOLD_RE.captures(s) {
+            Some(c) => c,
+            None => {
+                return Err(format!("invalid threshold `{}`, must be of the form: \
+                                    PATTERN [avg] completed|tested OP PERCENT", s))
+            }
+        };
+        let pattern = match RegexBuilder::new(&format!("^(?:{})", caps.at(1).unwrap()))
+                                .case_insensitive(true)
+                                .compile() {
+            Ok(p) => p,
+            Err(e) => return Err(format!("invalid threshold `{}`: {}", s, e)),
+        };
+        let perc = match caps.at(5).unwrap().parse::<u8>() {
+            Ok(p) if p <= 100 => p,
+            _ => return Err(format!("invalid threshold `{}`: PERCENT must be 0 to 100", s)),
+        };
+        Ok(Threshold {
+            raw: s.trim().to_string(),
+            pattern: pattern,
+            avg: caps.at(2).is_some(),
+            field: match caps.at(3).unwrap() {
+                "completed" => Field::Completed,
+                "tested" => Field::Tested,
+                _ => unreachable!(),
+            },
+            op: caps.at(4).unwrap().to_string(),
+            perc: perc,
+        })
+    }
+
+    fn passes(&self, value: f32) -> bool {
+        let value = percent(value);
+        match self.op.as_str() {
+            ">=" => value >= self.perc,
+            "<=" => value <= self.perc,
+            ">" => value > self.perc,
+            "<" => value < self.perc,
+            _ => unreachable!(),
+        }
+    }
+
+    /// check the threshold against the artifacts, returning a message
+    /// for every failure
+    pub fn check(&self, artifacts: &Artifacts) -> Vec<String> {
+        let mut names: Vec<&ArtNameRc> = artifacts.keys()
+            .filter(|n| self.pattern.is_match(&n.raw))
+            .collect();
+        names.sort();
+        let values = names.iter().map(|n| {
+            let artifact = artifacts.get(*n).unwrap();
+            match self.field {
+                Field::Completed => artifact.completed,
+                Field::Tested => artifact.tested,
+            }
+        });
+        let mut failures = Vec::new();
+        if self.avg {
+            if names.len() == 0 {
+                return failures;
+            }
+            let avg = values.fold(0., |sum, v| sum + v) / names.len() as f32;
+            if !self.passes(avg) {
+                failures.push(format!("`{}`: average is {}%", self.raw, percent(avg)));
+            }
+        } else {
+            for (name, value) in names.iter().zip(values) {
+                if !self.passes(value) {
+                    failures.push(format!("`{}`: {} is {}%", self.raw, name, percent(value)));
+                }
+            }
+        }
+        failures
+    }
+}
+
+/// the ratio as a rounded percentage, which is what the thresholds are
+/// checked against and what the failures show
+fn percent(value: f32) -> u8 {
+    (value * 100.).round() as u8
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("check")
+        .about("check for errors and thresholds, exiting with 1 if any fail. \
+                Thresholds can also be set with `thresholds` in the settings")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("threshold")
+                 .short("T")
+                 .long("threshold")
+                 .value_name("THRESHOLD")
+                 .help("threshold of the form `PATTERN [avg] completed|tested OP PERCENT`, \
+                        i.e. `REQ avg completed >= 80` or `TST tested >= 100`")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1))
+        .arg(Arg::with_name("require-approved")
+                 .long("require-approved")
+                 .value_name("TYPE")
+                 .help("fail if an artifact of the type is not approved or was changed \
+                        since it was approved, see `rst approve`")
+                 .possible_values(&["REQ", "SPC", "RSK", "TST"])
+                 .case_insensitive(true)
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1))
+}
+
+/// get the thresholds from the user input, combined with the ones
+/// in the settings, and the types which must be approved
+pub fn get_check_cmd(matches: &ArgMatches, settings: &Settings)
+                     -> Result<(Vec<Threshold>, Vec<ArtType>), String> {
+    let mut thresholds = Vec::new();
+    for t in settings.thresholds.iter() {
+        thresholds.push(try!(Threshold::from_str(t)));
+    }
+    if let Some(values) = matches.values_of("threshold") {
+        for t in values {
+            thresholds.push(try!(Threshold::from_str(t)));
+        }
+    }
+    let mut require_approved = Vec::new();
+    for t in matches.values_of("require-approved").map_or(Vec::new(), |v| v.collect()) {
+        let ty = try!(ArtName::from_str(t).map_err(|e| e.to_string())).get_type();
+        if !require_approved.contains(&ty) {
+            require_approved.push(ty);
+        }
+    }
+    Ok((thresholds, require_approved))
+}
+
+/// check that the artifacts of the types are approved, returning a message
+/// for every one which is not. The artifacts created automatically and the
+/// obsolete ones do not need to be approved
+/// partof: #SPC-approve-check
+pub fn check_approvals(artifacts: &Artifacts, types: &[ArtType]) -> Vec<String> {
+    let mut names: Vec<&ArtNameRc> = artifacts.iter()
+        .filter(|&(_, a)| types.contains(&a.ty))
+        .filter(|&(_, a)| a.path != PathBuf::from("PARENT") && !link::is_obsolete(a))
+        .map(|(n, _)| n)
+        .collect();
+    names.sort();
+    let mut failures = Vec::new();
+    for name in names {
+        let artifact = &artifacts[name];
+        match (review::approval_state(artifact), artifact.approval.as_ref()) {
+            (ApprovalState::Missing, _) => failures.push(format!("{} is not approved", name)),
+            (ApprovalState::Stale, Some(a)) => {
+                failures.push(format!("{} changed since it was approved by {} at {}",
+                                      name, a.by, a.at))
+            }
+            _ => {}
+        }
+    }
+    failures
+}
+
+/// check the artifacts, writing a summary of the failures.
+/// Returns whether the check passed.
+/// partof: #SPC-check
+pub fn do_check<W: Write>(w: &mut W, cwd: &Path, artifacts: &Artifacts, settings: &Settings,
+                          thresholds: &[Threshold], require_approved: &[ArtType])
+                          -> io::Result<bool> {
+    let mut status = status::get_status(artifacts);
+    status.dangling_locs = status::find_dangling_locs(artifacts, settings);
+    let mut failures = Vec::new();
+    for t in thresholds {
+        failures.extend(t.check(artifacts));
+    }
+    let unapproved = check_approvals(artifacts, require_approved);
+    let passed = status.num_errors() == 0 && failures.len() == 0 && unapproved.len() == 0;
+    if passed {
+        try!(write!(w, "check passed: {} artifacts, {} thresholds\n",
+                    artifacts.len(), thresholds.len()));
+    } else {
+        try!(write!(w, "check failed: {} errors, {} threshold failures",
+                    status.num_errors(), failures.len()));
+        if !require_approved.is_empty() {
+            try!(write!(w, ", {} approval failures", unapproved.len()));
+        }
+        try!(w.write_all(b"\n"));
+        try!(status.write_errors(w, cwd, artifacts));
+        for f in failures.iter().chain(unapproved.iter()) {
+            try!(write!(w, " - {}\n", f));
+        }
+    }
+    // warnings do not fail the check
+    if status.num_warnings() > 0 {
+        try!(write!(w, "{} warnings:\n", status.num_warnings()));
+        try!(status.write_warnings(w, cwd, artifacts));
+    }
+    Ok(passed)
+}
+
+#[test]
+/// partof: #TST-check
+fn test_threshold() {
+    let t = Threshold::from_str("REQ avg completed >= 80").unwrap();
+    assert!(t.avg);
+    assert_eq!(t.field, Field::Completed);
+    assert_eq!(t.op, ">=");
+    assert_eq!(t.perc, 80);
+    let t = Threshold::from_str("  tst-foo tested<100%").unwrap();
+    assert!(!t.avg);
+    assert_eq!(t.field, Field::Tested);
+    assert!(t.pattern.is_match("TST-foo-bar"));
+    assert!(!t.pattern.is_match("SPC-tst-foo"));
+
+    assert!(Threshold::from_str("REQ completed").is_err());
+    assert!(Threshold::from_str("REQ done >= 80").is_err());
+    assert!(Threshold::from_str("REQ completed >= 101").is_err());
+    assert!(Threshold::from_str("REQ-[ completed >= 10").is_err());
+
+    let mut artifacts = core::load::load_toml_simple("[REQ-a]\n[REQ-b]\n[SPC-a]\n");
+    artifacts.get_mut(&ArtName::from_str("REQ-a").unwrap()).unwrap().completed = 1.;
+    artifacts.get_mut(&ArtName::from_str("REQ-b").unwrap()).unwrap().completed = 0.5;
+    artifacts.get_mut(&ArtName::from_str("SPC-a").unwrap()).unwrap().completed = 0.;
+    assert!(Threshold::from_str("REQ avg completed >= 75").unwrap().check(&artifacts).is_empty());
+    assert_eq!(Threshold::from_str("REQ avg completed > 75").unwrap().check(&artifacts).len(), 1);
+    assert_eq!(Threshold::from_str("REQ completed >= 75").unwrap().check(&artifacts),
+               vec!["`REQ completed >= 75`: REQ-b is 50%".to_string()]);
+    assert_eq!(Threshold::from_str(".* completed >= 50").unwrap().check(&artifacts).len(), 1);
+
+    // the failures show the percentage the threshold was checked against
+    artifacts.get_mut(&ArtName::from_str("REQ-b").unwrap()).unwrap().completed = 0.799;
+    assert!(Threshold::from_str("REQ completed >= 80").unwrap().check(&artifacts).is_empty());
+    assert_eq!(Threshold::from_str("REQ completed > 80").unwrap().check(&artifacts),
+               vec!["`REQ completed > 80`: REQ-b is 80%".to_string()]);
+    assert_eq!(Threshold::from_str("REQ avg completed > 90").unwrap().check(&artifacts),
+               vec!["`REQ avg completed > 90`: average is 90%".to_string()]);
+}
@@ -0,0 +1,396 @@
+//! shell completions
+//!
+//! the scripts for every shell call back into `rst __complete`, which
+//! completes subcommands, flags, the values of options and artifact names.
+//! The flags of each command are taken from its definition so that they
+//! never get out of sync with the arguments.
+
+use std::env;
+use std::process;
+use std::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
+use std::time::UNIX_EPOCH;
+
+use std::collections::{BTreeMap, VecDeque};
+
+use rustc_serialize::json::{Json, ToJson};
+use clap::ArgSettings;
+
+use super::types::*;
+use super::matches;
+use super::watch;
+
+/// arguments whose values are artifact names
+const NAME_ARGS: [&'static str; 7] = ["search", "root", "name", "names", "old", "new", "NAMES"];
+
+pub const BASH: &'static str = r#"_rst() {
+    local IFS=$'\n'
+    COMPREPLY=($(rst __complete "$COMP_CWORD" "${COMP_WORDS[@]}" 2>/dev/null))
+}
+complete -o default -F _rst rst
+"#;
+
+pub const ZSH: &'static str = r#"#compdef rst
+_rst() {
+    local -a completions
+    completions=(${(f)"$(rst __complete $((CURRENT - 1)) "${words[@]}" 2>/dev/null)"})
+    if (( ${#completions} )); then
+        compadd -U -- $completions
+    else
+        _files
+    fi
+}
+compdef _rst rst
+"#;
+
+pub const FISH: &'static str = r#"function __rst_complete
+    set -l words (commandline -opc) (commandline -ct)
+    rst __complete (math (count $words) - 1) $words 2>/dev/null
+end
+complete -c rst -f -a '(__rst_complete)'
+"#;
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("completions")
+        .about("print the completion script for a shell, i.e. \
+                `source <(rst completions bash)`")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("shell")
+                 .help("the shell to print the completions for")
+                 .possible_values(&["bash", "zsh", "fish"])
+                 .required(true))
+}
+
+/// the subcommand called by the completion scripts
+pub fn get_complete_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("__complete")
+        .about("print the completions for the word at INDEX of the cmdline WORDS")
+        .settings(&[AS::Hidden, AS::TrailingVarArg, AS::AllowLeadingHyphen])
+        .arg(Arg::with_name("index")
+                 .value_name("INDEX")
+                 .required(true))
+        .arg(Arg::with_name("words")
+                 .value_name("WORDS")
+                 .multiple(true))
+}
+
+pub fn get_completions_cmd(matches: &ArgMatches) -> &'static str {
+    match matches.value_of("shell").unwrap() {
+        "bash" => BASH,
+        "zsh" => ZSH,
+        "fish" => FISH,
+        _ => unreachable!(),
+    }
+}
+
+/// get the words and the index of the word being completed
+pub fn get_complete_cmd(matches: &ArgMatches) -> Result<(Vec<String>, usize), String> {
+    let index = try!(matches.value_of("index").unwrap().parse::<usize>()
+                     .map_err(|e| format!("invalid index: {}", e)));
+    let words = match matches.values_of("words") {
+        Some(w) => w.map(|w| w.to_string()).collect(),
+        None => Vec::new(),
+    };
+    Ok((words, index))
+}
+
+/// an argument of a command as it is defined
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CmdArg {
+    /// the flags, i.e. `-q` and `--quiet`, or the name of a positional
+    pub names: Vec<String>,
+    /// the name of the value if the argument takes one
+    pub value_name: Option<String>,
+    pub values: Vec<String>,
+}
+
+/// the arguments of a command
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CmdArgs {
+    pub subcommands: Vec<String>,
+    pub options: Vec<CmdArg>,
+    pub positionals: Vec<CmdArg>,
+}
+
+fn switch_names(short: Option<char>, long: Option<&str>) -> Vec<String> {
+    short.map(|s| format!("-{}", s)).into_iter()
+        .chain(long.map(|l| format!("--{}", l)))
+        .collect()
+}
+
+/// get the arguments of a command from its definition. clap 2 only exposes
+/// them through the fields of its parser, which are hidden from the docs, so
+/// clap is pinned to an exact version in Cargo.toml.
+/// The global arguments of the `parent` are included, and the help and
+/// version flags are added since clap only adds them when parsing
+pub fn get_args(app: &App, parent: Option<&App>) -> CmdArgs {
+    let mut out = CmdArgs::default();
+    for sub in &app.p.subcommands {
+        if !sub.p.is_set(AS::Hidden) {
+            out.subcommands.push(sub.p.meta.name.clone());
+        }
+    }
+    if !out.subcommands.is_empty() {
+        out.subcommands.push("help".to_string());
+    }
+    let apps: Vec<(&App, bool)> = parent.into_iter().map(|p| (p, true))
+        .chain(Some((app, false)))
+        .collect();
+    for (a, only_global) in apps {
+        for f in &a.p.flags {
+            let global = f.b.is_set(ArgSettings::Global);
+            if !f.b.is_set(ArgSettings::Hidden) && (global || !only_global) {
+                out.options.push(CmdArg {
+                    names: switch_names(f.s.short, f.s.long),
+                    ..CmdArg::default()
+                });
+            }
+        }
+        for o in &a.p.opts {
+            let global = o.b.is_set(ArgSettings::Global);
+            if !o.b.is_set(ArgSettings::Hidden) && (global || !only_global) {
+                // clap displays the name of the argument without a value name
+                let value_name = o.v.val_names.as_ref()
+                    .and_then(|v| v.values().next().cloned())
+                    .unwrap_or(o.b.name);
+                out.options.push(CmdArg {
+                    names: switch_names(o.s.short, o.s.long),
+                    value_name: Some(value_name.to_string()),
+                    values: o.v.possible_vals.as_ref()
+                        .map_or(Vec::new(), |v| v.iter().map(|v| v.to_string()).collect()),
+                });
+            }
+        }
+    }
+    out.options.push(CmdArg {
+        names: switch_names(Some('h'), Some("help")),
+        ..CmdArg::default()
+    });
+    if parent.is_none() {
+        out.options.push(CmdArg {
+            names: switch_names(Some('V'), Some("version")),
+            ..CmdArg::default()
+        });
+    }
+    for p in app.p.positionals.values() {
+        if !p.b.is_set(ArgSettings::Hidden) {
+            out.positionals.push(CmdArg {
+                names: vec![p.b.name.to_string()],
+                value_name: p.v.val_names.as_ref()
+                    .and_then(|v| v.values().next())
+                    .map(|v| v.to_string()),
+                values: p.v.possible_vals.as_ref()
+                    .map_or(Vec::new(), |v| v.iter().map(|v| v.to_string()).collect()),
+            });
+        }
+    }
+    out
+}
+
+/// get the arguments of a subcommand, or of rst if it is None
+fn get_cmd_args(subcommand: Option<&str>) -> CmdArgs {
+    let app = matches::get_app();
+    match subcommand {
+        None => get_args(&app, None),
+        Some(name) => match app.p.subcommands.iter().find(|s| s.p.meta.name == name) {
+            Some(sub) => get_args(sub, Some(&app)),
+            None => CmdArgs::default(),
+        },
+    }
+}
+
+fn filter(candidates: Vec<String>, cur: &str) -> Vec<String> {
+    candidates.into_iter().filter(|c| c.starts_with(cur)).collect()
+}
+
+/// complete the value of an argument
+fn complete_value<F>(arg: &CmdArg, cur: &str, get_names: F) -> Vec<String>
+    where F: FnOnce() -> Vec<String> {
+    if !arg.values.is_empty() {
+        return filter(arg.values.clone(), cur);
+    }
+    let name = match arg.value_name {
+        Some(ref n) => n.as_str(),
+        None => arg.names[0].as_str(),
+    };
+    if name == "FIELDS" {
+        // the search fields are single letters that are combined
+        let mut fields: Vec<char> = ui::VALID_SEARCH_FIELDS.iter()
+            .filter(|c| !cur.contains(**c))
+            .cloned()
+            .collect();
+        fields.sort();
+        return fields.iter().map(|c| format!("{}{}", cur, c)).collect();
+    }
+    if !NAME_ARGS.contains(&name) {
+        return Vec::new();
+    }
+    // only the last of a list of names is completed
+    let (before, last) = match cur.rfind(',') {
+        Some(i) => cur.split_at(i + 1),
+        None => ("", cur),
+    };
+    let last_upper = last.trim_left().to_uppercase();
+    get_names().into_iter()
+        .filter(|n| n.to_uppercase().starts_with(&last_upper))
+        .map(|n| format!("{}{}", before, n))
+        .collect()
+}
+
+/// get the completions for the word at `index` of the cmdline `words`,
+/// where the first word is `rst`. Artifact names are only loaded with
+/// `get_names` when they are needed
+/// partof: #SPC-completions
+pub fn complete<F>(words: &[String], index: usize, get_names: F) -> Vec<String>
+    where F: FnOnce() -> Vec<String> {
+    let empty = String::new();
+    let cur = words.get(index).unwrap_or(&empty).as_str();
+    let mut start = 1;
+    // watch takes another command as its arguments
+    if words.get(1).map(|w| w.as_str()) == Some("watch") {
+        start = 2;
+        while start < index && words[start].starts_with('-') {
+            if words[start] == "-i" || words[start] == "--interval" {
+                start += 1;
+            }
+            start += 1;
+        }
+    }
+    if index <= start {
+        let args = get_cmd_args(None);
+        if cur.starts_with('-') {
+            return filter(args.options.iter().flat_map(|o| o.names.clone()).collect(), cur);
+        }
+        return filter(args.subcommands, cur);
+    }
+
+    let args = get_cmd_args(Some(&words[start]));
+    let takes_value = |w: &str| -> Option<&CmdArg> {
+        args.options.iter().find(|o| o.value_name.is_some() && o.names.iter().any(|n| n == w))
+    };
+    if let Some(opt) = takes_value(&words[index - 1]) {
+        return complete_value(opt, cur, get_names);
+    }
+    if cur.starts_with('-') {
+        return filter(args.options.iter().flat_map(|o| o.names.clone()).collect(), cur);
+    }
+    // find which positional is being completed
+    let mut position = 0;
+    let mut i = start + 1;
+    while i < index {
+        if words[i].starts_with('-') {
+            if takes_value(&words[i]).is_some() {
+                i += 1;
+            }
+        } else {
+            position += 1;
+        }
+        i += 1;
+    }
+    match args.positionals.get(position) {
+        Some(arg) => complete_value(arg, cur, get_names),
+        None => Vec::new(),
+    }
+}
+
+/// the file where the names of the artifacts in a project are cached. It is
+/// in the cache directory of the user so that other users cannot write it
+fn cache_path(cfg: &Path) -> Option<PathBuf> {
+    let dir = match (env::var_os("XDG_CACHE_HOME"), env::var_os("HOME")) {
+        (Some(ref d), _) if !d.is_empty() => PathBuf::from(d),
+        (_, Some(ref h)) if !h.is_empty() => PathBuf::from(h).join(".cache"),
+        _ => return None,
+    };
+    let mut hasher = DefaultHasher::new();
+    cfg.hash(&mut hasher);
+    Some(dir.join("rst").join(format!("complete-{:x}.json", hasher.finish())))
+}
+
+/// write the cache to a new file and move it into place, so that a file or
+/// link that is already at the path is replaced instead of written through
+pub fn write_cache(path: &Path, text: &str) -> io::Result<()> {
+    try!(fs::create_dir_all(path.parent().unwrap()));
+    let tmp = path.with_extension(format!("json.{}", process::id()));
+    let written = fs::OpenOptions::new().write(true).create_new(true).open(&tmp)
+        .and_then(|mut f| f.write_all(text.as_bytes()))
+        .and_then(|_| fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+fn snapshot_json(snapshot: &watch::Snapshot) -> Json {
+    let mut files = BTreeMap::new();
+    for (path, time) in snapshot {
+        let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        files.insert(path.to_string_lossy().to_string(),
+                     format!("{}.{:09}", since.as_secs(), since.subsec_nanos()).to_json());
+    }
+    Json::Object(files)
+}
+
+fn paths_json(paths: &VecDeque<PathBuf>) -> Json {
+    paths.iter().map(|p| p.to_string_lossy().to_string()).collect::<Vec<_>>().to_json()
+}
+
+fn json_paths(json: Option<&Json>) -> Option<VecDeque<PathBuf>> {
+    json.and_then(|j| j.as_array())
+        .map(|a| a.iter().filter_map(|p| p.as_string()).map(PathBuf::from).collect())
+}
+
+/// get the names from the cache if none of the watched files (see
+/// SPC-watch-snapshot) have changed since it was written
+fn read_cache(cfg: &Path) -> Option<Vec<String>> {
+    let text = match cache_path(cfg).map(|p| core::save::read_file(&p)) {
+        Some(Ok(t)) => t,
+        _ => return None,
+    };
+    let cache = match Json::from_str(&text) {
+        Ok(c) => c,
+        Err(_) => return None,
+    };
+    let mut settings = Settings::new();
+    match (json_paths(cache.find("paths")),
+           json_paths(cache.find("code_paths")),
+           json_paths(cache.find("exclude_code_paths"))) {
+        (Some(p), Some(c), Some(e)) => {
+            settings.paths = p;
+            settings.code_paths = c;
+            settings.exclude_code_paths = e;
+        }
+        _ => return None,
+    }
+    if cache.find("files") != Some(&snapshot_json(&watch::snapshot(cfg, &settings))) {
+        return None;
+    }
+    cache.find("names")
+        .and_then(|n| n.as_array())
+        .map(|n| n.iter().filter_map(|n| n.as_string()).map(|n| n.to_string()).collect())
+}
+
+/// get the sorted names of the artifacts in the project, using the cache
+/// when it is valid
+/// partof: #SPC-completions-cache
+pub fn load_names(cfg: &Path) -> Vec<String> {
+    if let Some(names) = read_cache(cfg) {
+        return names;
+    }
+    let (artifacts, settings) = match core::load_path(cfg) {
+        Ok(v) => v,
+        Err(_) => return Vec::new(),
+    };
+    let mut names: Vec<String> = artifacts.keys().map(|n| n.raw.clone()).collect();
+    names.sort();
+    let mut cache = BTreeMap::new();
+    cache.insert("paths".to_string(), paths_json(&settings.paths));
+    cache.insert("code_paths".to_string(), paths_json(&settings.code_paths));
+    cache.insert("exclude_code_paths".to_string(), paths_json(&settings.exclude_code_paths));
+    cache.insert("files".to_string(), snapshot_json(&watch::snapshot(cfg, &settings)));
+    cache.insert("names".to_string(), names.to_json());
+    // the cache is only an optimization, so failing to write it is fine
+    if let Some(path) = cache_path(cfg) {
+        let _ = write_cache(&path, &Json::Object(cache).to_string());
+    }
+    names
+}
@@ -0,0 +1,177 @@
+//! export the artifact graph to formats that can be rendered by other tools
+
+use super::types::*;
+use super::ls;
+use super::fmt as cmdfmt;
+
+/// the kinds of links that exist between artifacts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// automatically linked by name, i.e. SPC-foo is a partof REQ-foo
+    Named,
+    /// automatically linked to the by-name parent, i.e. REQ-foo-bar is a partof REQ-foo
+    Parent,
+    /// explicitly linked with the `partof` attribute
+    Explicit,
+}
+
+/// the supported export formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dot,
+    Mermaid,
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("export")
+        .about("export the linked artifact graph to graphviz dot or mermaid")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("format")
+                 .help("the format to export to")
+                 .possible_values(&["dot", "mermaid"])
+                 .required(true))
+        .args(&ls::get_search_args())
+}
+
+/// get all the information from the user input
+pub fn get_export_cmd(matches: &ArgMatches)
+                      -> Result<(ExportFormat, String, SearchSettings), String> {
+    let format = match matches.value_of("format").unwrap() {
+        "dot" => ExportFormat::Dot,
+        "mermaid" => ExportFormat::Mermaid,
+        _ => unreachable!(),
+    };
+    let (search, search_set) = try!(ls::get_search_cmd(matches));
+    Ok((format, search, search_set))
+}
+
+/// determine how `name` came to be a partof `partof`
+///
+/// links that are both automatic and explicit are reported as automatic,
+/// since the explicit partof is redundant
+pub fn get_link_kind(name: &ArtName, partof: &ArtName) -> LinkKind {
+    if name.parent().as_ref() == Some(partof) {
+        LinkKind::Parent
+    } else if name.named_partofs().iter().any(|n| n == partof) {
+        LinkKind::Named
+    } else {
+        LinkKind::Explicit
+    }
+}
+
+/// get the names selected by the search and every link between them,
+/// with the links given as (partof, part, kind)
+fn get_graph(search: &str, artifacts: &Artifacts, search_set: &SearchSettings)
+             -> Result<(Vec<ArtNameRc>, Vec<(ArtNameRc, ArtNameRc, LinkKind)>), String> {
+    let (names, pat_case) = try!(ls::select_names(search, artifacts, search_set));
+    let names: Vec<ArtNameRc> = names.into_iter()
+        .filter(|n| match artifacts.get(n) {
+            Some(a) => ui::show_artifact(n, a, &pat_case, search_set),
+            None => false,
+        })
+        .collect();
+    let shown: ArtNames = ArtNames::from_iter(names.iter().cloned());
+    let mut links = Vec::new();
+    for name in &names {
+        let artifact = artifacts.get(name).unwrap();
+        let mut partofs: Vec<_> = artifact.partof.iter().filter(|p| shown.contains(*p)).collect();
+        partofs.sort();
+        for p in partofs {
+            links.push((p.clone(), name.clone(), get_link_kind(name, p)));
+        }
+    }
+    Ok((names, links))
+}
+
+fn fmt_percents(artifact: &Artifact) -> String {
+    format!("{}% {}%",
+            (artifact.completed * 100.) as u8,
+            (artifact.tested * 100.) as u8)
+}
+
+/// write the artifacts as a graphviz digraph
+/// partof: #SPC-export-dot
+pub fn write_dot<W: Write>(w: &mut W,
+                           artifacts: &Artifacts,
+                           names: &[ArtNameRc],
+                           links: &[(ArtNameRc, ArtNameRc, LinkKind)])
+                           -> io::Result<()> {
+    try!(w.write_all(b"digraph artifacts {\n"));
+    try!(w.write_all(b"    node [shape=box, style=filled];\n"));
+    for name in names {
+        let artifact = artifacts.get(name).unwrap();
+        let fill = match cmdfmt::get_color(artifact) {
+            Green => "palegreen",
+            Blue => "lightskyblue",
+            Yellow => "khaki",
+            _ => "lightcoral",
+        };
+        try!(write!(w, "    \"{:?}\" [label=\"{}\\n{}\", fillcolor={}];\n",
+                    name, name.raw, fmt_percents(artifact), fill));
+    }
+    for &(ref partof, ref part, kind) in links {
+        let style = match kind {
+            LinkKind::Named => "dashed",
+            LinkKind::Parent => "bold",
+            LinkKind::Explicit => "solid",
+        };
+        try!(write!(w, "    \"{:?}\" -> \"{:?}\" [style={}];\n", partof, part, style));
+    }
+    try!(w.write_all(b"}\n"));
+    Ok(())
+}
+
+/// write the artifacts as a mermaid flowchart
+///
+/// mermaid ids cannot contain `-`, so nodes are given ids in the order they
+/// are written and labeled with their name
+/// partof: #SPC-export-mermaid
+pub fn write_mermaid<W: Write>(w: &mut W,
+                               artifacts: &Artifacts,
+                               names: &[ArtNameRc],
+                               links: &[(ArtNameRc, ArtNameRc, LinkKind)])
+                               -> io::Result<()> {
+    let ids: HashMap<&ArtNameRc, usize> = HashMap::from_iter(
+        names.iter().enumerate().map(|(i, n)| (n, i)));
+    try!(w.write_all(b"graph TD\n"));
+    for name in names {
+        let artifact = artifacts.get(name).unwrap();
+        let class = match cmdfmt::get_color(artifact) {
+            Green => "green",
+            Blue => "blue",
+            Yellow => "yellow",
+            _ => "red",
+        };
+        try!(write!(w, "    n{}[\"{}<br/>{}\"]:::{}\n",
+                    ids[name], name.raw, fmt_percents(artifact), class));
+    }
+    for &(ref partof, ref part, kind) in links {
+        let arrow = match kind {
+            LinkKind::Named => "-.->",
+            LinkKind::Parent => "==>",
+            LinkKind::Explicit => "-->",
+        };
+        try!(write!(w, "    n{} {} n{}\n", ids[partof], arrow, ids[part]));
+    }
+    for &(class, fill) in &[("green", "#98fb98"), ("blue", "#87cefa"),
+                            ("yellow", "#f0e68c"), ("red", "#f08080")] {
+        try!(write!(w, "    classDef {} fill:{};\n", class, fill));
+    }
+    Ok(())
+}
+
+/// perform the export command given the inputs
+/// partof: #SPC-export
+pub fn do_export<W: Write>(w: &mut W,
+                           format: ExportFormat,
+                           search: &str,
+                           artifacts: &Artifacts,
+                           search_set: &SearchSettings)
+                           -> Result<(), String> {
+    let (names, links) = try!(get_graph(search, artifacts, search_set));
+    let result = match format {
+        ExportFormat::Dot => write_dot(w, artifacts, &names, &links),
+        ExportFormat::Mermaid => write_mermaid(w, artifacts, &names, &links),
+    };
+    result.map_err(|e| e.to_string())
+}
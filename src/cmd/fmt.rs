@@ -1,6 +1,39 @@
 //! methods to format the FmtArtifact object and write it to a stream
 
+use std::cmp;
+
 use super::types::*;
+use super::markdown;
+use core::types::ApprovalState;
+use regex::Captures;
+
+lazy_static!{
+    static ref ANSI_RE: Regex = Regex::new("\x1b\\[[0-9;]*m").unwrap();
+}
+
+/// the width of the name column, which fits the header
+pub const MIN_NAME_WIDTH: usize = 13;
+
+/// the mark of suspect links and of the artifacts that have them
+pub const SUSPECT: &'static str = "(suspect)";
+
+/// remove the ANSI color codes from text
+pub fn strip_ansi(text: &str) -> String {
+    ANSI_RE.replace_all(text, "")
+}
+
+/// shorten the text to `width` characters, ending it with an ellipsis
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return "".to_string();
+    }
+    let mut out: String = text.chars().take(width - 1).collect();
+    out.push('…');
+    out
+}
 
 impl FmtArtifact {
     /// write the formatted version of the artifact to the
@@ -10,6 +43,18 @@ impl FmtArtifact {
                                 artifacts: &Artifacts,
                                 settings: &Settings, indent: u8)
                                 -> io::Result<()> {
+        // the line is built first so that the text can be fit in the width
+        let mut line: Vec<u8> = Vec::new();
+        try!(self.write_line(&mut line, cwd, artifacts, settings, indent));
+        w.write_all(&line)
+    }
+
+    /// write the artifact into the line. In the table the text is the last
+    /// column and is truncated to fit in the width
+    /// partof: #SPC-ls-layout
+    fn write_line(&self, w: &mut Vec<u8>, cwd: &Path, artifacts: &Artifacts,
+                  settings: &Settings, indent: u8)
+                  -> io::Result<()> {
         let nfno = indent > 0 && self.name_only(); // not-first-name-only
         if !self.name_only() {
             for _ in 0..(indent * 2) {
@@ -31,93 +76,40 @@ impl FmtArtifact {
         };
 
         // format the completeness and name
-        let completed_str = ((artifact.completed * 100.) as u8).to_string();
-        let tested_str = ((artifact.tested * 100.) as u8).to_string();
-        let completed_len = completed_str.len();
-        let tested_len = tested_str.len();
-        if settings.color {
-            // #SPC-ls-color]
-            let (d_sym, d_perc, t_sym, t_perc, name) = if artifact.completed >= 1. &&
-                    artifact.tested >= 1. {
-                let name = if nfno {
-                    Green.paint(self.name.raw.as_str())
-                } else {
-                    Green.bold().underline().paint(self.name.raw.as_str())
-                };
-                (Green.bold().paint("D"), Green.bold().paint(completed_str),
-                 Green.bold().paint("T"), Green.bold().paint(tested_str),
-                 name)
+        let name = self.name.raw.as_str();
+        if nfno {
+            if settings.color {
+                try!(write!(w, "{}", get_color(artifact).paint(name)));
             } else {
-                let mut score = 0;
-                let (d_sym, d_perc) = if artifact.completed >= 1. {
-                    score += 3;
-                    (Blue.bold().paint("D"), Blue.bold().paint(completed_str))
-                } else if artifact.completed >= 0.7 {
-                    score += 2;
-                    (Yellow.bold().paint("-"), Yellow.bold().paint(completed_str))
-                } else if artifact.completed >= 0.4 {
-                    score += 1;
-                    (Yellow.bold().paint("-"), Yellow.bold().paint(completed_str))
-                } else {
-                    (Red.bold().paint("-"), Red.bold().paint(completed_str))
-                };
-                let (t_sym, t_perc) = if artifact.tested >= 1. {
-                    score += 2;
-                    (Blue.bold().paint("T"), Blue.bold().paint(tested_str))
-                } else if artifact.tested >= 0.5 {
-                    score += 1;
-                    (Yellow.bold().paint("-"), Yellow.bold().paint(tested_str))
-                } else {
-                    (Red.bold().paint("-"), Red.bold().paint(tested_str))
-                };
-                let name = match score {
-                    3...4 => Blue,
-                    1...2 => Yellow,
-                    0 => Red,
-                    _ => unreachable!(),
-                };
-                let sname = self.name.raw.as_str();
-                let name = if nfno {
-                    name.paint(sname)
-                } else {
-                    name.bold().underline().paint(sname)
-                };
-                (d_sym, d_perc, t_sym, t_perc, name)
-            };
-            if nfno {
                 try!(write!(w, "{}", name));
-            } else {
-                try!(write!(w, "|{}{}| ", d_sym, t_sym));
-                // format completed %
-                for _ in 0..(3 - completed_len) {
-                    try!(w.write_all(" ".as_ref()));
-                }
-                try!(write!(w, "{}% ", d_perc));
-                // format tested %
-                for _ in 0..(3 - tested_len) {
-                    try!(w.write_all(" ".as_ref()));
-                }
-                try!(write!(w, "{}% ", t_perc));
-                try!(write!(w, "| {} ", name));
-                if name.len() < 45 {
-                    for _ in 0..(45 - name.len()) {
-                        try!(w.write_all(" ".as_ref()));
-                    }
+            }
+            if self.suspect {
+                if settings.color {
+                    try!(write!(w, " {}", Yellow.bold().paint(SUSPECT)));
+                } else {
+                    try!(write!(w, " {}", SUSPECT));
                 }
             }
+            return Ok(());
+        }
+        try!(write_status(w, artifact, settings));
+        if settings.color {
+            try!(write!(w, "{} ", get_color(artifact).bold().underline().paint(name)));
         } else {
-            if nfno {
-                try!(write!(w, "{}", &self.name.raw));
+            try!(write!(w, "{} ", name));
+        }
+        let mut name_len = name.chars().count();
+        // partof: #SPC-review-ls
+        if !artifact.suspect.is_empty() {
+            if settings.color {
+                try!(write!(w, "{} ", Yellow.bold().paint(SUSPECT)));
             } else {
-                let d_sym = if artifact.completed >= 1. {"D"} else {"-"};
-                let t_sym = if artifact.tested >= 1. {"T"} else {"-"};
-                try!(write!(w, "|{}{}| {:>3}% {:>3}% | {:<45} ", d_sym, t_sym,
-                            completed_str, tested_str, &self.name.raw));
+                try!(write!(w, "{} ", SUSPECT));
             }
+            name_len += SUSPECT.len() + 1;
         }
-
-        if nfno {
-            return Ok(());
+        for _ in name_len..self.name_width {
+            try!(w.write_all(" ".as_ref()));
         }
 
         // format the parts
@@ -175,11 +167,40 @@ impl FmtArtifact {
             self.write_end(w);
         }
 
-        // format the text
-        // TODO: use markdown to apply styles to the text
+        // format who approved the artifact, in red if it changed since
+        // partof: #SPC-approve-ls
+        if let Some(ref approval) = self.approval {
+            self.write_header(w, "\n * approval: ", settings);
+            if settings.color {
+                let color = match core::review::approval_state(artifact) {
+                    ApprovalState::Approved => Green,
+                    ApprovalState::Stale => Red,
+                    ApprovalState::Missing => Yellow,
+                };
+                try!(write!(w, "{}", color.paint(approval.as_str())));
+            } else {
+                try!(w.write_all(approval.as_ref()));
+            }
+            self.write_end(w);
+        }
+
+        // format the text, rendering the markdown if it is displayed in full
         if let Some(ref text) = self.text {
             self.write_header(w, "\n * text:\n    ", settings);
-            let lines: Vec<_> = text.split("\n").collect();
+            let mention = |name: &str| mention_color(name, artifacts);
+            let mut lines: Vec<_> = if self.long && settings.color {
+                markdown::render(text, cmp::max(self.width.unwrap_or(80), 24) - 4, &mention)
+            } else if let (false, Some(width)) = (self.long, self.width) {
+                // the text is the last column, so it gets the rest of the line
+                let start = w.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+                let used = strip_ansi(&String::from_utf8_lossy(&w[start..])).chars().count();
+                vec![truncate(text, width.saturating_sub(used + 1))]
+            } else {
+                text.split("\n").map(|l| l.to_string()).collect()
+            };
+            if settings.color && !self.long {
+                lines = lines.iter().map(|l| highlight_mentions(l, &mention)).collect();
+            }
             let text = lines.join("\n    ");
             w.write_all(text.as_ref()).unwrap();
         }
@@ -208,20 +229,110 @@ impl FmtArtifact {
     /// if it is, it is formatted differently
     fn name_only(&self) -> bool {
         match (&self.path, &self.parts, &self.partof,
-               &self.loc, &self.text) {
-            (&None, &None, &None, &None, &None) => true,
+               &self.loc, &self.text, &self.approval) {
+            (&None, &None, &None, &None, &None, &None) => true,
             _ => false,
         }
     }
 }
 
+/// write the `|DT| 100%  50% | ` columns with the completed and tested
+/// status of an artifact
+/// #SPC-ls-color
+pub fn write_status<W: io::Write>(w: &mut W, artifact: &Artifact, settings: &Settings)
+                                  -> io::Result<()> {
+    let completed_str = ((artifact.completed * 100.) as u8).to_string();
+    let tested_str = ((artifact.tested * 100.) as u8).to_string();
+    let d_sym = if artifact.completed >= 1. { "D" } else { "-" };
+    let t_sym = if artifact.tested >= 1. { "T" } else { "-" };
+    if !settings.color {
+        return write!(w, "|{}{}| {:>3}% {:>3}% | ", d_sym, t_sym, completed_str, tested_str);
+    }
+    let (d_color, t_color) = if artifact.completed >= 1. && artifact.tested >= 1. {
+        (Green, Green)
+    } else {
+        let d_color = if artifact.completed >= 1. {
+            Blue
+        } else if artifact.completed >= 0.4 {
+            Yellow
+        } else {
+            Red
+        };
+        let t_color = if artifact.tested >= 1. {
+            Blue
+        } else if artifact.tested >= 0.5 {
+            Yellow
+        } else {
+            Red
+        };
+        (d_color, t_color)
+    };
+    try!(write!(w, "|{}{}| ", d_color.bold().paint(d_sym), t_color.bold().paint(t_sym)));
+    for _ in 0..(3 - completed_str.len()) {
+        try!(w.write_all(" ".as_ref()));
+    }
+    try!(write!(w, "{}% ", d_color.bold().paint(completed_str)));
+    for _ in 0..(3 - tested_str.len()) {
+        try!(w.write_all(" ".as_ref()));
+    }
+    write!(w, "{}% | ", t_color.bold().paint(tested_str))
+}
+
+/// the color of an artifact name mentioned in text, red if it does not exist
+/// partof: #SPC-artifact-mentions-display
+fn mention_color(name: &str, artifacts: &Artifacts) -> Option<Colour> {
+    match ArtName::from_str(name) {
+        Ok(n) => Some(artifacts.get(&n).map_or(Red, get_color)),
+        Err(_) => None,
+    }
+}
+
+/// color the artifact names mentioned in a line of plain text
+fn highlight_mentions(line: &str, mention: &Fn(&str) -> Option<Colour>) -> String {
+    core::vars::MENTION_RE.replace_all(line, |caps: &Captures| {
+        let name = caps.at(0).unwrap();
+        match mention(name) {
+            Some(c) => c.paint(name).to_string(),
+            None => name.to_string(),
+        }
+    })
+}
+
+/// get the color that an artifact's name is displayed in
+///
+/// completed is weighted higher than tested, see SPC-ls-color
+pub fn get_color(artifact: &Artifact) -> Colour {
+    if artifact.completed >= 1. && artifact.tested >= 1. {
+        return Green;
+    }
+    let mut score = 0;
+    if artifact.completed >= 1. {
+        score += 3;
+    } else if artifact.completed >= 0.7 {
+        score += 2;
+    } else if artifact.completed >= 0.4 {
+        score += 1;
+    }
+    if artifact.tested >= 1. {
+        score += 2;
+    } else if artifact.tested >= 0.5 {
+        score += 1;
+    }
+    match score {
+        3...4 => Blue,
+        1...2 => Yellow,
+        0 => Red,
+        _ => unreachable!(),
+    }
+}
+
 pub fn write_table_header<W: io::Write> (
         w: &mut W,
         fmt_set: &FmtSettings,
         settings: &Settings) {
     let mut header = String::new();
     header.write_str("|  | DONE TEST | ARTIFACT NAME").unwrap();
-    for _ in 0..33 {
+    for _ in MIN_NAME_WIDTH..(cmp::max(fmt_set.name_width, MIN_NAME_WIDTH) + 1) {
         header.push(' ');
     }
     if fmt_set.parts {
@@ -236,6 +347,9 @@ pub fn write_table_header<W: io::Write> (
     if fmt_set.path {
         header.write_str("| DEFINED   ").unwrap();
     }
+    if fmt_set.approval {
+        header.write_str("| APPROVAL   ").unwrap();
+    }
     if fmt_set.text {
         header.write_str("| TEXT").unwrap();
     }
@@ -246,3 +360,103 @@ pub fn write_table_header<W: io::Write> (
         write!(w, "{}", header).unwrap();
     }
 }
+
+/// the number of unchanged lines shown around changed lines in a diff
+const DIFF_CONTEXT: usize = 2;
+
+/// get the line operations (' ', '-' or '+') that turn `old` into `new`
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(char, &'a str)> {
+    // only the middle that differs needs the (quadratic) lcs table
+    let mut prefix = 0;
+    while prefix < old.len() && prefix < new.len() && old[prefix] == new[prefix] {
+        prefix += 1;
+    }
+    let mut suffix = 0;
+    while suffix < old.len() - prefix && suffix < new.len() - prefix &&
+          old[old.len() - 1 - suffix] == new[new.len() - 1 - suffix] {
+        suffix += 1;
+    }
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    // lcs[i][j] is the length of the lcs of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops: Vec<(char, &str)> = old[..prefix].iter().map(|l| (' ', *l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', a[i]));
+            i += 1;
+        } else {
+            ops.push(('+', b[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| (' ', *l)));
+    ops
+}
+
+/// write the line diff between the old and new text of a file
+pub fn write_diff<W: io::Write>(w: &mut W, path: &Path, old: &str, new: &str,
+                                settings: &Settings)
+                                -> io::Result<()> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+    let changed: Vec<usize> = ops.iter()
+        .enumerate()
+        .filter(|&(_, &(op, _))| op != ' ')
+        .map(|(i, _)| i)
+        .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    try!(write!(w, "--- {}\n+++ {}\n", path.display(), path.display()));
+    let (mut old_line, mut new_line) = (1, 1);
+    let mut in_hunk = false;
+    for (i, &(op, line)) in ops.iter().enumerate() {
+        let show = changed.iter().any(|&c| c + DIFF_CONTEXT >= i && c <= i + DIFF_CONTEXT);
+        if show && !in_hunk {
+            try!(write!(w, "@@ -{} +{} @@\n", old_line, new_line));
+        }
+        in_hunk = show;
+        if show {
+            let line = format!("{}{}", op, line);
+            match (op, settings.color) {
+                ('-', true) => try!(write!(w, "{}\n", Red.paint(line))),
+                ('+', true) => try!(write!(w, "{}\n", Green.paint(line))),
+                _ => try!(write!(w, "{}\n", line)),
+            }
+        }
+        match op {
+            '-' => old_line += 1,
+            '+' => new_line += 1,
+            _ => {
+                old_line += 1;
+                new_line += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_diff_lines() {
+    let old = vec!["a", "b", "c", "d"];
+    let new = vec!["a", "c", "x", "d", "e"];
+    assert_eq!(diff_lines(&old, &new),
+               vec![(' ', "a"), ('-', "b"), (' ', "c"), ('+', "x"), (' ', "d"), ('+', "e")]);
+    assert_eq!(diff_lines(&old, &old), vec![(' ', "a"), (' ', "b"), (' ', "c"), (' ', "d")]);
+}
@@ -0,0 +1,54 @@
+//! rewrite the artifact files in the canonical format
+
+use super::types::*;
+use core::save;
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("fmt")
+        .about("format the artifact files in the canonical format, sorting artifacts \
+                and normalizing names, partof and text while keeping comments")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("check")
+                 .long("check")
+                 .help("do not write the files, only list the ones that would change \
+                        and exit with 1 if there are any"))
+}
+
+/// get whether the command is only a check
+pub fn get_fmt_cmd(matches: &ArgMatches) -> bool {
+    matches.is_present("check")
+}
+
+/// format every file that artifacts were loaded from, writing the ones
+/// that changed (or only listing them if `check` is set).
+/// Returns whether every file was already formatted
+/// partof: #SPC-fmt
+pub fn do_fmt<W: Write>(w: &mut W, cwd: &Path, artifacts: &Artifacts, check: bool)
+                        -> Result<bool, String> {
+    let mut formatted = true;
+    for path in save::artifact_files(artifacts) {
+        let display = utils::relative_path(&path, cwd);
+        let text = match save::read_file(&path) {
+            Ok(t) => t,
+            Err(e) => return Err(e.to_string()),
+        };
+        let new = match save::fmt_file(&text) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("{}: {}", display.display(), e)),
+        };
+        if new == text {
+            continue;
+        }
+        formatted = false;
+        if check {
+            write!(w, "{}\n", display.display()).unwrap();
+        } else {
+            let result = fs::File::create(&path).and_then(|mut f| f.write_all(new.as_bytes()));
+            if let Err(e) = result {
+                return Err(format!("{}: {}", display.display(), e));
+            }
+            write!(w, "formatted {}\n", display.display()).unwrap();
+        }
+    }
+    Ok(formatted)
+}
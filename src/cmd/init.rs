@@ -13,6 +13,13 @@ exclude_code_paths = []
 # - {cwd}: the path to the directory of the file using it
 # - {repo}: the path to the current repository, which is the closest
 #    directory (searching down) that contains a ".rst" folder
+# and the environment variables can be used as {env:NAME}, or as
+# {env:NAME|default} to use a default when NAME is not set.
+#
+# Globals can be strings, booleans, integers or arrays of them. All globals
+# can be used in the code_paths, where an array gives a path for each element:
+# crates = ["core", "cli"]
+# and: code_paths = ["{repo}/{crates}/src"]
 "#;
 
 const PURPOSE_TOML: &'static str = r#"# project purpose and definition documentation
@@ -1,6 +1,51 @@
 
+use std::cmp;
+use std::ffi::OsString;
+
 use super::types::*;
 use super::fmt as cmdfmt;
+use super::matches;
+use super::output;
+use super::term;
+
+/// the arguments used to select artifacts. These are shared with
+/// every command that filters artifacts the same way `ls` does
+pub fn get_search_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![Arg::with_name("search")
+             .help("artifact names given in form `REQ-foo-[bar, baz-[1,2]]` OR pearl regexp \
+                    pattern if -p is given")
+             .use_delimiter(false),
+         Arg::with_name("pattern")
+             .short("p")
+             .help("search FIELDS using pearl regexp SEARCH.")
+             .value_name("FIELDS")
+             .takes_value(true)
+             .max_values(1)
+             .min_values(0),
+         Arg::with_name("completed")
+             .short("c")
+             .help("filter by completeness (ie `<45`), < and > are inclusive, '>' == `>100`")
+             .takes_value(true),
+         Arg::with_name("tested")
+             .short("t")
+             .help("give a filter for the testedness in %. see '-c'")
+             .takes_value(true),
+         Arg::with_name("query")
+             .short("Q")
+             .long("query")
+             .value_name("EXPR")
+             .help("only include artifacts matching the query, i.e. \
+                    `type:SPC and completed<100 and (text~\"cache\" or path~\"core/\") \
+                    and not has:loc`. Fields are compared with `:` `!=` and `~` (regex), \
+                    completed and tested also with `<` `<=` `>` `>=`")
+             .takes_value(true),
+         Arg::with_name("status")
+             .long("status")
+             .value_name("STATUS")
+             .help("only include artifacts with the status, i.e. `draft`. The same \
+                    as `--query status:STATUS`")
+             .takes_value(true)]
+}
 
 /// Get the ls subcommand, which is what creates the command
 /// for the cmdline
@@ -9,28 +54,10 @@ pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("ls")
         .about("list artifacts according to various parameters")
         .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
-        .arg(Arg::with_name("search")
-                 .help("artifact names given in form `REQ-foo-[bar, baz-[1,2]]` OR pearl regexp \
-                        pattern if -p is given")
-                 .use_delimiter(false))
-        .arg(Arg::with_name("pattern")
-                 .short("p")
-                 .help("search FIELDS using pearl regexp SEARCH.")
-                 .value_name("FIELDS")
-                 .takes_value(true)
-                 .max_values(1)
-                 .min_values(0))
+        .args(&get_search_args())
         .arg(Arg::with_name("long")
                  .short("l")
                  .help("print items in the 'long form'"))
-        .arg(Arg::with_name("completed")
-                 .short("c")
-                 .help("filter by completeness (ie `<45`), < and > are inclusive, '>' == `>100`")
-                 .takes_value(true))
-        .arg(Arg::with_name("tested")
-                 .short("t")
-                 .help("give a filter for the testedness in %. see '-c'")
-                 .takes_value(true))
         .arg(Arg::with_name("all")
                  .short("A")
                  .help("If set, additional flags will be *deactivated* instead of activated"))
@@ -49,10 +76,44 @@ pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
         .arg(Arg::with_name("text")
                  .short("T")
                  .help("display the text description of this artifact (first line only if not -l)"))
+        .arg(Arg::with_name("approval")
+                 .short("V")
+                 .help("display who approved the artifact, and whether it changed since"))
         .arg(Arg::with_name("plain")
                  .long("plain")
                  .help("do not display color in the output"))
-
+        .arg(Arg::with_name("sort")
+                 .long("sort")
+                 .value_name("FIELD")
+                 .help("the field to sort by")
+                 .possible_values(&["name", "completed", "tested", "path", "loc"])
+                 .takes_value(true))
+        .arg(Arg::with_name("reverse")
+                 .long("reverse")
+                 .help("reverse the sort order"))
+        .arg(Arg::with_name("group-by")
+                 .long("group-by")
+                 .value_name("FIELD")
+                 .help("group the artifacts, displaying a header with the completed and \
+                        tested totals of each group")
+                 .possible_values(&["type", "file", "parent"])
+                 .takes_value(true))
+        .arg(Arg::with_name("format")
+                 .long("format")
+                 .value_name("FORMAT")
+                 .help("write every field of the artifacts in a machine readable format")
+                 .possible_values(&["json", "csv", "tsv"])
+                 .conflicts_with_all(&["template", "group-by"])
+                 .takes_value(true))
+        .arg(Arg::with_name("template")
+                 .long("template")
+                 .value_name("TEMPLATE")
+                 .help("write a line for every artifact using the template, i.e. \
+                        '{name}\\t{completed}\\t{loc_path}:{loc_line}'. The fields are: \
+                        name, type, path, completed, tested, loc_path, loc_line, loc_col, \
+                        parts, partof, text")
+                 .conflicts_with("group-by")
+                 .takes_value(true))
 }
 
 /// return (lt, percent) returning None when there is no value
@@ -197,7 +258,30 @@ pub fn get_ls_cmd(matches: &ArgMatches) -> Result<(String, FmtSettings, SearchSe
     fmt_set.partof = matches.is_present("partof");
     fmt_set.loc_path = matches.is_present("loc");
     fmt_set.text = matches.is_present("text");
-    fmt_set.color = !matches.is_present("plain");
+    fmt_set.approval = matches.is_present("approval");
+    fmt_set.color = !matches.is_present("plain") && term::use_color();
+    fmt_set.width = term::width();
+    fmt_set.sort = match matches.value_of("sort") {
+        Some("completed") => SortBy::Completed,
+        Some("tested") => SortBy::Tested,
+        Some("path") => SortBy::Path,
+        Some("loc") => SortBy::Loc,
+        _ => SortBy::Name,
+    };
+    fmt_set.reverse = matches.is_present("reverse");
+    fmt_set.group_by = match matches.value_of("group-by") {
+        Some("type") => GroupBy::Type,
+        Some("file") => GroupBy::File,
+        Some("parent") => GroupBy::Parent,
+        _ => GroupBy::Nothing,
+    };
+    fmt_set.output = match (matches.value_of("format"), matches.value_of("template")) {
+        (Some("json"), _) => OutputFormat::Json,
+        (Some("csv"), _) => OutputFormat::Csv,
+        (Some("tsv"), _) => OutputFormat::Tsv,
+        (_, Some(t)) => OutputFormat::Template(try!(output::get_template(t))),
+        _ => OutputFormat::Table,
+    };
     if matches.is_present("all") {
         // reverse everything
         fmt_set.path = !fmt_set.path;
@@ -205,17 +289,30 @@ pub fn get_ls_cmd(matches: &ArgMatches) -> Result<(String, FmtSettings, SearchSe
         fmt_set.partof = !fmt_set.partof;
         fmt_set.loc_path = !fmt_set.loc_path;
         fmt_set.text = !fmt_set.text;
+        fmt_set.approval = !fmt_set.approval;
     } else if fmt_set.long &&
-       !(fmt_set.path || fmt_set.parts || fmt_set.partof || fmt_set.loc_path || fmt_set.text) {
+       !(fmt_set.path || fmt_set.parts || fmt_set.partof || fmt_set.loc_path || fmt_set.text ||
+         fmt_set.approval) {
         // if long is specified but no other display attributes are specified
         fmt_set.path = true;
         fmt_set.parts = true;
         fmt_set.partof = true;
         fmt_set.loc_path = true;
         fmt_set.text = true;
+        fmt_set.approval = true;
     }
 
-    // #SPC-ls-search
+    let (search, search_set) = try!(get_search_cmd(matches));
+    debug!("ls search: {}, fmt_set: {:?}, search_set: {:?}",
+           search,
+           fmt_set,
+           search_set);
+    Ok((search, fmt_set, search_set))
+}
+
+/// get the search string and settings from the user input
+/// #SPC-ls-search
+pub fn get_search_cmd(matches: &ArgMatches) -> Result<(String, SearchSettings), String> {
     let mut search_set = match (matches.is_present("pattern"), matches.value_of("pattern")) {
         (true, Some(p)) => try!(SearchSettings::from_str(p)),
         (true, None) => SearchSettings::from_str("N").unwrap(),
@@ -235,32 +332,119 @@ pub fn get_ls_cmd(matches: &ArgMatches) -> Result<(String, FmtSettings, SearchSe
         None => {}
     }
     debug!("tested: {:?}", search_set.tested);
+    if let Some(q) = matches.value_of("query") {
+        search_set.query = Some(try!(ui::Query::from_str(q)
+            .map_err(|e| format!("invalid query: {}", e))));
+    }
+    // partof: #SPC-artifact-status-ls
+    if let Some(s) = matches.value_of("status") {
+        let status = ui::Query::Equals(ui::query::Field::Status, s.to_string());
+        search_set.query = Some(match search_set.query.take() {
+            Some(q) => ui::Query::And(Box::new(q), Box::new(status)),
+            None => status,
+        });
+    }
 
     let search = matches.value_of("search").unwrap_or("").to_string();
+    Ok((search, search_set))
+}
 
-    debug!("ls search: {}, fmt_set: {:?}, search_set: {:?}",
-           search,
-           fmt_set,
-           search_set);
-    Ok((search, fmt_set, search_set))
+/// the display flags of `ls` which can be used in the fields of a view
+const VIEW_FIELDS: [(char, &'static str); 8] = [('l', "long"), ('A', "all"), ('D', "path"),
+                                                ('P', "parts"), ('O', "partof"), ('L', "loc"),
+                                                ('T', "text"), ('V', "approval")];
+
+/// expand `rst ls @NAME` into the arguments of the view NAME and parse them,
+/// returning None if no view is used. Arguments given on the cmdline take
+/// precedence over the ones in the view
+/// find the argument that was parsed as the search of ls. The same text can
+/// be the value of an option before it, and the indexes of clap count each
+/// flag of `-lp` and the value of `--sort=name` separately, so each argument
+/// with the text is checked by parsing the arguments with it replaced
+fn find_search_arg(args: &[OsString], search: &str) -> Option<usize> {
+    // a nul cannot be given on the cmdline
+    let marker = "@\0";
+    let is_search = |i: usize| {
+        let mut replaced = args.to_vec();
+        replaced[i] = marker.into();
+        match matches::get_matches(replaced) {
+            Ok(m) => m.subcommand_matches("ls").and_then(|ls| ls.value_of("search")) ==
+                     Some(marker),
+            Err(_) => false,
+        }
+    };
+    (0..args.len()).find(|&i| args[i].to_str() == Some(search) && is_search(i))
 }
 
-/// perform the ls command given the inputs
-pub fn do_ls<W: Write>(w: &mut W,
-                       cwd: &Path,
-                       search: &str,
-                       artifacts: &Artifacts,
-                       fmt_set: &FmtSettings,
-                       search_set: &SearchSettings,
-                       settings: &Settings) {
-    let mut dne: Vec<ArtNameRc> = Vec::new();
-    let mut names: Vec<ArtNameRc> = Vec::new();
-    let mut fmt_set = (*fmt_set).clone();
-    let mut settings = (*settings).clone();
+/// partof: #SPC-ls-views
+pub fn get_view_matches<'a>(args: &[OsString], matches: &ArgMatches, settings: &Settings)
+                            -> Result<Option<ArgMatches<'a>>, String> {
+    let ls = match matches.subcommand_matches("ls") {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+    let search = match ls.value_of("search") {
+        Some(s) if s.starts_with('@') => s,
+        _ => return Ok(None),
+    };
+    let view = match settings.views.get(&search[1..]) {
+        Some(v) => v,
+        None => {
+            let mut names: Vec<&str> = settings.views.keys().map(|k| k.as_str()).collect();
+            names.sort();
+            return Err(format!("unknown view {}, the views are: {}", search, names.join(", ")));
+        }
+    };
 
-    // load settings from cmdline inputs
-    settings.color = fmt_set.color;
+    // the view replaces the search argument
+    let at = match find_search_arg(args, search) {
+        Some(i) => i,
+        None => return Err(format!("{} was not found in the arguments", search)),
+    };
+    let mut expanded: Vec<OsString> = args[..at].to_vec();
+    if let Some(ref s) = view.search {
+        expanded.push(s.into());
+    }
+    let options = [("pattern", "-p", &view.pattern),
+                   ("completed", "-c", &view.completed),
+                   ("tested", "-t", &view.tested),
+                   ("query", "--query=", &view.query),
+                   ("sort", "--sort=", &view.sort),
+                   ("group-by", "--group-by=", &view.group_by)];
+    for &(arg, flag, value) in options.iter() {
+        if let (false, &Some(ref v)) = (ls.is_present(arg), value) {
+            expanded.push(format!("{}{}", flag, v).into());
+        }
+    }
+    if let Some(ref fields) = view.fields {
+        let fields: String = fields.chars()
+            .filter(|c| !VIEW_FIELDS.iter().any(|f| f.0 == *c && ls.is_present(f.1)))
+            .collect();
+        if !fields.is_empty() {
+            expanded.push(format!("-{}", fields).into());
+        }
+    }
+    if view.reverse && !ls.is_present("reverse") {
+        expanded.push("--reverse".into());
+    }
+    expanded.extend(args[at + 1..].iter().cloned());
+    debug!("view {} expanded to: {:?}", search, expanded);
+    match matches::get_matches(expanded) {
+        Ok(m) => Ok(Some(m)),
+        Err(e) => {
+            let msg = e.message.lines().next().unwrap_or("").to_string();
+            Err(format!("invalid view {}: {}", search, msg))
+        }
+    }
+}
 
+/// select the names given by the search string along with the regex
+/// pattern that they should be filtered by.
+///
+/// names are returned sorted and may include names that do not exist
+pub fn select_names(search: &str, artifacts: &Artifacts, search_set: &SearchSettings)
+                    -> Result<(Vec<ArtNameRc>, Regex), String> {
+    let mut names: Vec<ArtNameRc> = Vec::new();
     let pat_case;
     if search_set.use_regex {
         // names to use are determined by filtering the regex
@@ -269,36 +453,131 @@ pub fn do_ls<W: Write>(w: &mut W,
                       .compile();
         pat_case = match pat {
             Ok(p) => p,
-            Err(e) => {
-                error!("Invalid pattern: {}", e.to_string());
-                exit(1);
-            }
+            Err(e) => return Err(format!("Invalid pattern: {}", e.to_string())),
         };
         names.extend(artifacts.keys().map(|n| n.clone()));
-        names.sort();
     } else {
         // names to use are determined from the beginning
-        names.extend(ArtNames::from_str(&search).unwrap());
-        names.sort();
+        names.extend(try!(ArtNames::from_str(&search).map_err(|e| e.to_string())));
         debug!("artifact names selected: {:?}", names);
         pat_case = Regex::new("").unwrap();
     }
-    debug!("fmt_set empty: {}", fmt_set.is_empty());
     if names.len() == 0 && search.len() == 0 {
         // [#SPC-ui-cmdline-ls-flags-empty]
         names.extend(artifacts.keys().cloned());
-        names.sort();
     }
+    names.sort();
+    Ok((names, pat_case))
+}
+
+/// sort the names of existing artifacts by the field in the settings
+/// partof: #SPC-ls-sort
+pub fn sort_names(names: &mut Vec<ArtNameRc>, artifacts: &Artifacts, fmt_set: &FmtSettings) {
+    names.sort();
+    match fmt_set.sort {
+        SortBy::Name => {}
+        // a total order, so that a NaN cannot break the sort
+        SortBy::Completed => names.sort_by(|a, b| {
+            artifacts[a].completed.total_cmp(&artifacts[b].completed)
+        }),
+        SortBy::Tested => names.sort_by(|a, b| {
+            artifacts[a].tested.total_cmp(&artifacts[b].tested)
+        }),
+        SortBy::Path => names.sort_by(|a, b| artifacts[a].path.cmp(&artifacts[b].path)),
+        // artifacts that are not implemented go last
+        SortBy::Loc => names.sort_by_key(|n| match artifacts[n].loc {
+            Some(ref l) => (false, l.path.clone(), l.line_col),
+            None => (true, PathBuf::new(), (0, 0)),
+        }),
+    }
+    if fmt_set.reverse {
+        names.reverse();
+    }
+}
+
+/// split the names into groups by the field in the settings. Groups are
+/// sorted by their key and keep the order of the names
+pub fn group_names(names: Vec<ArtNameRc>, artifacts: &Artifacts, cwd: &Path,
+                   group_by: GroupBy)
+                   -> Vec<(String, Vec<ArtNameRc>)> {
+    if group_by == GroupBy::Nothing {
+        return vec![("".to_string(), names)];
+    }
+    let mut groups: Vec<(String, Vec<ArtNameRc>)> = Vec::new();
+    for name in names {
+        let artifact = &artifacts[&name];
+        let key = match group_by {
+            GroupBy::Type => format!("{:?}", artifact.ty),
+            GroupBy::File => if artifact.path == PathBuf::from("PARENT") {
+                "PARENT".to_string()
+            } else {
+                utils::relative_path(&artifact.path, cwd).display().to_string()
+            },
+            // the parent is displayed the way the first child wrote it
+            GroupBy::Parent => match name.raw.rfind('-') {
+                Some(i) => name.raw[..i].to_string(),
+                None => "".to_string(),
+            },
+            GroupBy::Nothing => unreachable!(),
+        };
+        // names are case insensitive
+        match groups.iter().position(|g| g.0.eq_ignore_ascii_case(&key)) {
+            Some(i) => groups[i].1.push(name),
+            None => groups.push((key, vec![name])),
+        }
+    }
+    groups.sort_by(|a, b| a.0.to_uppercase().cmp(&b.0.to_uppercase()));
+    groups
+}
+
+/// write the header of a group with its completed and tested totals
+fn write_group_header<W: Write>(w: &mut W, key: &str, names: &[ArtNameRc],
+                                artifacts: &Artifacts, settings: &Settings) {
+    let len = names.len() as f32;
+    let completed = names.iter().map(|n| artifacts[n].completed).fold(0., |s, v| s + v) / len;
+    let tested = names.iter().map(|n| artifacts[n].tested).fold(0., |s, v| s + v) / len;
+    let header = format!("== {} ({} artifacts) {}% done {}% tested ==",
+                         key, names.len(), (completed * 100.) as u8, (tested * 100.) as u8);
+    if settings.color {
+        write!(w, "\n{}\n", Style::new().bold().paint(header)).unwrap();
+    } else {
+        write!(w, "\n{}\n", header).unwrap();
+    }
+}
+
+/// the error for names that do not exist, suggesting similar names
+pub fn fmt_dne(dne: &[ArtNameRc], artifacts: &Artifacts) -> String {
+    let names: Vec<String> = dne.iter()
+        .map(|n| format!("{}{}", n, utils::did_you_mean(n, artifacts.keys())))
+        .collect();
+    format!("The following artifacts do not exist: {}", names.join(", "))
+}
+
+/// perform the ls command given the inputs. The artifacts that exist are
+/// written even if some of the names do not, which is then an error
+pub fn do_ls<W: Write>(w: &mut W,
+                       cwd: &Path,
+                       search: &str,
+                       artifacts: &Artifacts,
+                       fmt_set: &FmtSettings,
+                       search_set: &SearchSettings,
+                       settings: &Settings)
+                       -> Result<(), String> {
+    let mut dne: Vec<ArtNameRc> = Vec::new();
+    let mut fmt_set = (*fmt_set).clone();
+    let mut settings = (*settings).clone();
+
+    // load settings from cmdline inputs
+    settings.color = fmt_set.color;
+
+    let (names, pat_case) = try!(select_names(search, artifacts, search_set));
+    debug!("fmt_set empty: {}", fmt_set.is_empty());
     if fmt_set.is_empty() {
         fmt_set.parts = true;
         fmt_set.path = true;
     }
 
-
-    if !fmt_set.long {
-        cmdfmt::write_table_header(w, &fmt_set, &settings);
-    }
-    let mut displayed = ArtNames::new();
+    let mut shown: Vec<ArtNameRc> = Vec::new();
     for name in names {
         let art = match artifacts.get(&name) {
             Some(a) => a,
@@ -308,14 +587,47 @@ pub fn do_ls<W: Write>(w: &mut W,
                 continue;
             }
         };
-        if !ui::show_artifact(&name, art, &pat_case, search_set) {
-            continue;
+        if ui::show_artifact(&name, art, &pat_case, search_set) {
+            shown.push(name);
+        }
+    }
+    sort_names(&mut shown, artifacts, &fmt_set);
+
+    if fmt_set.output != OutputFormat::Table {
+        output::write_output(w, &fmt_set.output, &shown, artifacts, cwd).unwrap();
+        if dne.len() > 0 {
+            return Err(fmt_dne(&dne, artifacts));
+        }
+        return Ok(());
+    }
+    // the name column fits the longest name, but leaves space for the others
+    fmt_set.name_width = shown.iter()
+        .map(|n| if artifacts[n].suspect.is_empty() {
+            n.raw.chars().count()
+        } else {
+            n.raw.chars().count() + cmdfmt::SUSPECT.len() + 1
+        })
+        .fold(cmdfmt::MIN_NAME_WIDTH, cmp::max);
+    if let Some(width) = fmt_set.width {
+        fmt_set.name_width = cmp::min(fmt_set.name_width,
+                                      cmp::max(width / 2, cmdfmt::MIN_NAME_WIDTH));
+    }
+    if !fmt_set.long {
+        cmdfmt::write_table_header(w, &fmt_set, &settings);
+    }
+    let mut displayed = ArtNames::new();
+    for (key, names) in group_names(shown, artifacts, cwd, fmt_set.group_by) {
+        if fmt_set.group_by != GroupBy::Nothing {
+            write_group_header(w, &key, &names, artifacts, &settings);
+        }
+        for name in names {
+            let f = ui::fmt_artifact(&name, artifacts, &fmt_set, fmt_set.recurse,
+                                     &mut displayed);
+            f.write(w, cwd, artifacts, &settings, 0).unwrap(); // FIXME: unwrap
         }
-        let f = ui::fmt_artifact(&name, artifacts, &fmt_set, fmt_set.recurse, &mut displayed);
-        f.write(w, cwd, artifacts, &settings, 0).unwrap(); // FIXME: unwrap
     }
     if dne.len() > 0 {
-        error!("The following artifacts do not exist: {:?}", dne);
-        exit(1);
+        return Err(fmt_dne(&dne, artifacts));
     }
+    Ok(())
 }
@@ -0,0 +1,18 @@
+//! run the language server over stdin and stdout
+
+use super::types::*;
+use super::super::lsp as server;
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("lsp")
+        .about("run a language server over stdin/stdout so that editors can show \
+                the artifacts referenced in code and check artifact files")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+}
+
+/// serve the language server until the editor exits
+pub fn do_lsp<W: Write>(w: &mut W, cfg: &Path) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut r = stdin.lock();
+    server::serve(&mut r, w, cfg)
+}
@@ -0,0 +1,368 @@
+//! render the markdown in artifact text with ANSI styles
+//!
+//! only the subset of markdown used in artifact text is supported: headings,
+//! lists, code blocks and bold, italic and code spans. Anything else is
+//! displayed as it was written
+
+use std::mem;
+
+use super::types::*;
+use core::vars::MENTION_RE;
+
+lazy_static!{
+    static ref HEADING_RE: Regex = Regex::new(r"^(#{1,6})\s+(.*?)\s*#*$").unwrap();
+    static ref LIST_RE: Regex = Regex::new(r"^(\s*)([-*+]|\d+[.)])\s+(.*)$").unwrap();
+}
+
+/// the words highlighted as requirement keywords (see RFC 2119)
+const KEYWORDS: [&'static str; 3] = ["shall", "will", "should"];
+
+/// a piece of text with a single style
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    text: String,
+    style: Style,
+    /// keywords are not highlighted in code
+    code: bool,
+}
+
+/// the words of a paragraph, each of which can have several styles
+type Words = Vec<Vec<Span>>;
+
+fn is_marker_open(prev: Option<char>, next: Option<char>) -> bool {
+    match next {
+        Some(c) => !c.is_whitespace() && !prev.map_or(false, |p| p.is_alphanumeric()),
+        None => false,
+    }
+}
+
+fn is_marker_close(prev: Option<char>, next: Option<char>) -> bool {
+    prev.map_or(false, |p| !p.is_whitespace()) && !next.map_or(false, |n| n.is_alphanumeric())
+}
+
+/// split inline markdown into words, removing the markers
+fn parse_inline(text: &str, base: Style) -> Words {
+    let chars: Vec<char> = text.chars().collect();
+    let (mut bold, mut italic, mut code) = (false, false, false);
+    let mut code_ticks = 0;
+    let mut words: Words = Vec::new();
+    let mut word: Vec<Span> = Vec::new();
+    let mut current = String::new();
+    let style = |bold: bool, italic: bool, code: bool| {
+        if code {
+            return base.fg(Colour::Cyan);
+        }
+        let mut s = base;
+        if bold {
+            s = s.bold();
+        }
+        if italic {
+            s = s.italic();
+        }
+        s
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = if i == 0 { None } else { Some(chars[i - 1]) };
+        let mut toggled = None;
+        if c == '`' {
+            // a code span is closed by the same number of backticks
+            let mut ticks = 1;
+            while chars.get(i + ticks) == Some(&'`') {
+                ticks += 1;
+            }
+            if !code {
+                code_ticks = ticks;
+                toggled = Some((ticks, 'c'));
+            } else if ticks == code_ticks {
+                toggled = Some((ticks, 'c'));
+            } else {
+                current.extend(&chars[i..i + ticks]);
+                i += ticks;
+                continue;
+            }
+        } else if !code && (c == '*' || c == '_') {
+            let double = chars.get(i + 1) == Some(&c);
+            let len = if double { 2 } else { 1 };
+            let next = chars.get(i + len).cloned();
+            let on = if double { bold } else { italic };
+            let valid = if on {
+                is_marker_close(prev, next)
+            } else {
+                is_marker_open(prev, next)
+            };
+            if valid {
+                toggled = Some((len, if double { 'b' } else { 'i' }));
+            }
+        }
+        if let Some((len, marker)) = toggled {
+            if !current.is_empty() {
+                let text = mem::replace(&mut current, String::new());
+                word.push(Span { text: text, style: style(bold, italic, code), code: code });
+            }
+            match marker {
+                'c' => code = !code,
+                'b' => bold = !bold,
+                _ => italic = !italic,
+            }
+            i += len;
+            continue;
+        }
+        if c.is_whitespace() {
+            if !current.is_empty() {
+                let text = mem::replace(&mut current, String::new());
+                word.push(Span { text: text, style: style(bold, italic, code), code: code });
+            }
+            if !word.is_empty() {
+                words.push(word);
+                word = Vec::new();
+            }
+        } else {
+            current.push(c);
+        }
+        i += 1;
+    }
+    if !current.is_empty() {
+        word.push(Span { text: current, style: style(bold, italic, code), code: code });
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// highlight the requirement keywords, i.e. "shall" in "**shall**,"
+fn highlight_keywords(words: &mut Words) {
+    for word in words.iter_mut() {
+        for span in word.iter_mut() {
+            if span.code {
+                continue;
+            }
+            let bare = span.text.trim_matches(|c: char| !c.is_alphabetic()).to_lowercase();
+            if KEYWORDS.contains(&bare.as_str()) {
+                span.style = span.style.bold().fg(Colour::Yellow);
+            }
+        }
+    }
+}
+
+/// color the names of the artifacts mentioned in the text, except in code
+fn highlight_mentions(words: &mut Words, mention: &Fn(&str) -> Option<Colour>) {
+    for word in words.iter_mut() {
+        let mut spans: Vec<Span> = Vec::new();
+        for span in word.drain(..) {
+            if span.code {
+                spans.push(span);
+                continue;
+            }
+            let mut last = 0;
+            for (start, end) in MENTION_RE.find_iter(&span.text) {
+                let colour = match mention(&span.text[start..end]) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                if start > last {
+                    spans.push(Span { text: span.text[last..start].to_string(), ..span.clone() });
+                }
+                spans.push(Span {
+                    text: span.text[start..end].to_string(),
+                    style: span.style.fg(colour),
+                    code: false,
+                });
+                last = end;
+            }
+            if last < span.text.len() {
+                spans.push(Span { text: span.text[last..].to_string(), ..span });
+            }
+        }
+        *word = spans;
+    }
+}
+
+fn word_len(word: &[Span]) -> usize {
+    word.iter().map(|s| s.text.chars().count()).sum()
+}
+
+/// wrap the words to the width, starting the first line with `first` and
+/// indenting the others by its length
+fn wrap(words: &Words, first: &str, width: usize, out: &mut Vec<String>) {
+    let indent = first.chars().count();
+    let mut line = first.to_string();
+    let mut len = indent;
+    let mut empty = true;
+    for word in words {
+        let wlen = word_len(word);
+        if !empty && len + 1 + wlen > width {
+            out.push(line);
+            line = " ".repeat(indent);
+            len = indent;
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+            len += 1;
+        }
+        for span in word {
+            write!(line, "{}", span.style.paint(span.text.as_str())).unwrap();
+        }
+        len += wlen;
+        empty = false;
+    }
+    out.push(line);
+}
+
+/// a block of text which is wrapped together
+struct Paragraph {
+    /// the text at the start of the first line, i.e. a list marker
+    first: String,
+    text: String,
+    style: Style,
+}
+
+fn flush(paragraph: &mut Option<Paragraph>, width: usize,
+         mention: &Fn(&str) -> Option<Colour>, out: &mut Vec<String>) {
+    if let Some(p) = paragraph.take() {
+        let mut words = parse_inline(&p.text, p.style);
+        highlight_keywords(&mut words);
+        highlight_mentions(&mut words, mention);
+        wrap(&words, &p.first, width, out);
+    }
+}
+
+/// render markdown text as lines of styled text wrapped to the width.
+/// `mention` gives the color of the artifact names mentioned in the text
+/// partof: #SPC-ls-markdown
+pub fn render(text: &str, width: usize, mention: &Fn(&str) -> Option<Colour>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut paragraph: Option<Paragraph> = None;
+    let mut fence: Option<String> = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(ref indent) = fence {
+            if trimmed.starts_with("```") {
+                // the code block is closed below
+            } else {
+                // code is not wrapped, only the fence indentation is removed
+                let code = if line.starts_with(indent.as_str()) {
+                    &line[indent.len()..]
+                } else {
+                    trimmed
+                };
+                out.push(format!("  {}", Colour::Cyan.paint(code)));
+                continue;
+            }
+        }
+        if trimmed.starts_with("```") {
+            flush(&mut paragraph, width, mention, &mut out);
+            fence = match fence {
+                Some(_) => None,
+                None => Some(line[..line.len() - line.trim_left().len()].to_string()),
+            };
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut paragraph, width, mention, &mut out);
+            if out.last().map_or(false, |l| !l.is_empty()) {
+                out.push(String::new());
+            }
+            continue;
+        }
+        if let Some(caps) = HEADING_RE.captures(trimmed) {
+            flush(&mut paragraph, width, mention, &mut out);
+            let mut style = Style::new().bold();
+            if caps.at(1).unwrap().len() == 1 {
+                style = style.underline();
+            }
+            paragraph = Some(Paragraph {
+                first: String::new(),
+                text: caps.at(2).unwrap().to_string(),
+                style: style,
+            });
+            flush(&mut paragraph, width, mention, &mut out);
+            continue;
+        }
+        if let Some(caps) = LIST_RE.captures(line) {
+            flush(&mut paragraph, width, mention, &mut out);
+            let marker = caps.at(2).unwrap();
+            let marker = if marker.ends_with('.') || marker.ends_with(')') {
+                marker.to_string()
+            } else {
+                "•".to_string()
+            };
+            paragraph = Some(Paragraph {
+                first: format!("{}{} ", caps.at(1).unwrap(), marker),
+                text: caps.at(3).unwrap().to_string(),
+                style: Style::new(),
+            });
+            continue;
+        }
+        match paragraph {
+            Some(ref mut p) => {
+                p.text.push(' ');
+                p.text.push_str(trimmed);
+            }
+            None => {
+                paragraph = Some(Paragraph {
+                    first: String::new(),
+                    text: trimmed.to_string(),
+                    style: Style::new(),
+                })
+            }
+        }
+    }
+    flush(&mut paragraph, width, mention, &mut out);
+    while out.last().map_or(false, |l| l.is_empty()) {
+        out.pop();
+    }
+    out
+}
+
+#[test]
+fn test_parse_inline() {
+    let plain = Style::new();
+    let words = parse_inline("a **bold** _it_ snake_case `x * y` ``a`b`` 5 * 3", plain);
+    let spans: Vec<(&str, Style)> = words.iter()
+        .flat_map(|w| w.iter().map(|s| (s.text.as_str(), s.style)))
+        .collect();
+    assert_eq!(spans,
+               vec![("a", plain), ("bold", plain.bold()), ("it", plain.italic()),
+                    ("snake_case", plain), ("x", plain.fg(Colour::Cyan)),
+                    ("*", plain.fg(Colour::Cyan)), ("y", plain.fg(Colour::Cyan)),
+                    ("a`b", plain.fg(Colour::Cyan)),
+                    ("5", plain), ("*", plain), ("3", plain)]);
+}
+
+#[test]
+/// partof: #TST-ls-markdown
+fn test_render() {
+    let text = "# Title\nThe tool **shall** be\nfast.\n\n- one two three\n  four\n\n\
+                ```\ncode  here\n```";
+    let lines = render(text, 14, &|_| None);
+    let keyword = Style::new().bold().fg(Colour::Yellow);
+    assert_eq!(lines,
+               vec![format!("{}", Style::new().bold().underline().paint("Title")),
+                    format!("The tool {}", keyword.paint("shall")),
+                    "be fast.".to_string(),
+                    "".to_string(),
+                    "• one two".to_string(),
+                    "  three four".to_string(),
+                    "".to_string(),
+                    format!("  {}", Colour::Cyan.paint("code  here"))]);
+}
+
+#[test]
+/// partof: #TST-artifact-mentions-display
+fn test_render_mentions() {
+    let mention = |name: &str| match name {
+        "SPC-foo" => Some(Colour::Green),
+        _ => Some(Colour::Red),
+    };
+    let lines = render("see SPC-foo, **SPC-dne** and `SPC-foo`", 80, &mention);
+    assert_eq!(lines,
+               vec![format!("see {}, {} and {}",
+                            Colour::Green.paint("SPC-foo"),
+                            Style::new().bold().fg(Colour::Red).paint("SPC-dne"),
+                            Colour::Cyan.paint("SPC-foo"))]);
+}
@@ -2,12 +2,26 @@
 use std::ffi::OsString;
 
 use super::ls;
+use super::export;
+use super::status;
+use super::check;
+use super::format;
+use super::new;
+use super::mv;
+use super::rm;
+use super::set_status;
+use super::review;
+use super::approve;
+use super::watch;
+use super::lsp;
+use super::complete;
+use super::tree;
 use super::init;
 use super::tutorial;
 use super::types::*;
 
-pub fn get_matches<'a, I, T>(args: I) -> ClapResult<ArgMatches<'a>>
-    where I: IntoIterator<Item=T>, T: Into<OsString> {
+/// the definition of the cmdline, also used to complete it
+pub fn get_app<'a, 'b>() -> App<'a, 'b> {
     // [#SPC-ui-cmdline-cmd-help]
     App::new("rst")
         .version(env!("CARGO_PKG_VERSION"))
@@ -29,5 +43,24 @@ pub fn get_matches<'a, I, T>(args: I) -> ClapResult<ArgMatches<'a>>
         .subcommand(tutorial::get_subcommand())
         .subcommand(init::get_subcommand())
         .subcommand(ls::get_subcommand())
-        .get_matches_from_safe(args)
+        .subcommand(tree::get_subcommand())
+        .subcommand(export::get_subcommand())
+        .subcommand(status::get_subcommand())
+        .subcommand(check::get_subcommand())
+        .subcommand(format::get_subcommand())
+        .subcommand(new::get_subcommand())
+        .subcommand(mv::get_subcommand())
+        .subcommand(rm::get_subcommand())
+        .subcommand(set_status::get_subcommand())
+        .subcommand(review::get_subcommand())
+        .subcommand(approve::get_subcommand())
+        .subcommand(watch::get_subcommand())
+        .subcommand(lsp::get_subcommand())
+        .subcommand(complete::get_subcommand())
+        .subcommand(complete::get_complete_subcommand())
+}
+
+pub fn get_matches<'a, I, T>(args: I) -> ClapResult<ArgMatches<'a>>
+    where I: IntoIterator<Item=T>, T: Into<OsString> {
+    get_app().get_matches_from_safe(args)
 }
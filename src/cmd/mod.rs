@@ -8,18 +8,37 @@
 
 use std::env;
 use std::io;
+use std::path::Path;
 use std::ffi::OsString;
 
 use core;
+use core::{Artifacts, Settings};
 use super::VERSION;
 
-use clap::ArgMatches;
+use clap::{ArgMatches, ErrorKind};
 use ansi_term::Colour::Green;
 
 mod types;
 mod matches;
 mod ls;
+mod tree;
 mod fmt;
+mod markdown;
+mod term;
+mod output;
+mod export;
+mod status;
+mod check;
+mod format;
+mod new;
+mod mv;
+mod rm;
+mod set_status;
+mod review;
+mod approve;
+mod watch;
+mod lsp;
+mod complete;
 mod init;
 mod tutorial;
 mod data;  // data mostly for the tutorial
@@ -27,6 +46,9 @@ mod data;  // data mostly for the tutorial
 #[cfg(test)]
 mod tests;
 
+/// the commands whose output is paged
+const PAGED: [&'static str; 2] = ["ls", "tree"];
+
 use super::init_logger;
 
 pub fn get_loglevel(matches: &ArgMatches) -> Option<(u8, bool)> {
@@ -42,22 +64,28 @@ pub fn get_loglevel(matches: &ArgMatches) -> Option<(u8, bool)> {
 }
 
 
-pub fn cmd<'a, W, I, T>(w: &mut W, args: I)
+/// run the command given by args, returning the exit code
+pub fn cmd<'a, W, I, T>(w: &mut W, args: I) -> i32
     where I: IntoIterator<Item=T>,
           T: Into<OsString>,
           W: io::Write {
-    let matches = match matches::get_matches(args) {
+    // the args are kept to expand views
+    let args: Vec<OsString> = args.into_iter().map(|a| a.into()).collect();
+    let mut matches = match matches::get_matches(args.clone()) {
         Ok(m) => m,
         Err(e) => {
             write!(w, "{}", e).unwrap();
-            return;
+            return match e.kind {
+                ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => 0,
+                _ => 1,
+            };
         }
     };
 
     // initialze the logger
     match get_loglevel(&matches) {
         Some((v, q)) => init_logger(q, v, true).unwrap(),
-        None => return,
+        None => return 1,
     };
 
     // If init is selected, do that
@@ -65,10 +93,12 @@ pub fn cmd<'a, W, I, T>(w: &mut W, args: I)
     if let Some(_) = matches.subcommand_matches("init") {
         info!("Calling the init command");
         match init::do_init(&cwd) {
-            Ok(_) => {},
-            Err(e) => println!("ERROR: {}", e),
+            Ok(_) => return 0,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
         }
-        return;
     }
 
     // If tutorial is selected, do that
@@ -78,7 +108,7 @@ pub fn cmd<'a, W, I, T>(w: &mut W, args: I)
             Ok(c) => c,
             Err(e) => {
                 println!("ERROR: {}", e);
-                return;
+                return 1;
             },
         };
         tutorial::do_tutorial(c).unwrap();
@@ -86,7 +116,31 @@ pub fn cmd<'a, W, I, T>(w: &mut W, args: I)
         //     Ok(_) => {},
         //     Err(e) => println!("ERROR: {}", e),
         // }
-        return;
+        return 0;
+    }
+
+    // completions are printed even outside of a project
+    if let Some(c) = matches.subcommand_matches("completions") {
+        info!("Calling the completions command");
+        write!(w, "{}", complete::get_completions_cmd(&c)).unwrap();
+        return 0;
+    }
+    if let Some(c) = matches.subcommand_matches("__complete") {
+        let (words, index) = match complete::get_complete_cmd(&c) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        let get_names = || match core::find_repo(cwd.as_path()) {
+            Some(r) => complete::load_names(&r.join(".rst")),
+            None => Vec::new(),
+        };
+        for c in complete::complete(&words, index, get_names) {
+            write!(w, "{}\n", c).unwrap();
+        }
+        return 0;
     }
 
     // load the artifacts
@@ -94,28 +148,227 @@ pub fn cmd<'a, W, I, T>(w: &mut W, args: I)
         Some(r) => r,
         None => {
             println!("Could not find .rst folder. Try running `rst init -t`");
-            return;
+            return 1;
         }
     };
     let cfg = repo.join(".rst");
     debug!("using cfg dir {:?}", cfg);
 
+    // the language server loads the artifacts itself
+    if let Some(_) = matches.subcommand_matches("lsp") {
+        info!("Calling the lsp command");
+        if let Err(e) = lsp::do_lsp(w, &cfg) {
+            error!("{}", e);
+            return 1;
+        }
+        return 0;
+    }
+
+    // watch reloads the artifacts every time they change
+    if let Some(c) = matches.subcommand_matches("watch") {
+        info!("Calling the watch command");
+        let c = match watch::get_watch_cmd(&c) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        watch::do_watch(w, &cwd, &cfg, &c);
+    }
+
     let (artifacts, settings) = match core::load_path(cfg.as_path()) {
         Ok(v) => v,
         Err(err) => {
             error!("{}", err);
-            return;
+            return 1;
         }
     };
+    match ls::get_view_matches(&args, &matches, &settings) {
+        Ok(Some(m)) => matches = m,
+        Ok(None) => {}
+        Err(e) => {
+            println!("ERROR: {}", e);
+            return 1;
+        }
+    }
+
+    // long output is paged when it is displayed in a terminal
+    if term::is_tty() && PAGED.iter().any(|c| matches.subcommand_matches(c).is_some()) {
+        let mut output: Vec<u8> = Vec::new();
+        let code = run_cmd(&mut output, &cwd, &cfg, &matches, &artifacts, &settings);
+        if let Err(e) = term::page(w, &output) {
+            error!("{}", e);
+            return 1;
+        }
+        return code;
+    }
+    run_cmd(w, &cwd, &cfg, &matches, &artifacts, &settings)
+}
 
+/// run a command which uses the loaded artifacts, returning the exit code
+fn run_cmd<W: io::Write>(w: &mut W, cwd: &Path, cfg: &Path, matches: &ArgMatches,
+                         artifacts: &Artifacts, settings: &Settings) -> i32 {
+    let mut settings = settings.clone();
+    settings.color = settings.color && term::use_color();
+    let settings = &settings;
     if let Some(ls) = matches.subcommand_matches("ls") {
         info!("Calling the ls command");
-        let (search, fmtset, search_set) = ls::get_ls_cmd(&ls).unwrap();
-        ls::do_ls(w, &cwd, &search, &artifacts, &fmtset, &search_set, &settings);
+        let (search, fmtset, search_set) = match ls::get_ls_cmd(&ls) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = ls::do_ls(w, &cwd, &search, &artifacts, &fmtset, &search_set,
+                                  &settings) {
+            error!("{}", e);
+            return 1;
+        }
+    } else if let Some(t) = matches.subcommand_matches("tree") {
+        info!("Calling the tree command");
+        let (roots, depth, color) = match tree::get_tree_cmd(&t) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        let mut settings = settings.clone();
+        settings.color = color;
+        if let Err(e) = tree::do_tree(w, &roots, depth, &artifacts, &settings) {
+            error!("{}", e);
+            return 1;
+        }
+    } else if let Some(e) = matches.subcommand_matches("export") {
+        info!("Calling the export command");
+        let (format, search, search_set) = match export::get_export_cmd(&e) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = export::do_export(w, format, &search, &artifacts, &search_set) {
+            error!("{}", e);
+            return 1;
+        }
+    } else if let Some(_) = matches.subcommand_matches("status") {
+        info!("Calling the status command");
+        status::do_status(w, &cwd, &artifacts, &settings).unwrap();
+    } else if let Some(c) = matches.subcommand_matches("check") {
+        info!("Calling the check command");
+        let (thresholds, require_approved) = match check::get_check_cmd(&c, &settings) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if !check::do_check(w, &cwd, &artifacts, &settings, &thresholds, &require_approved)
+            .unwrap() {
+            return 1;
+        }
+    } else if let Some(f) = matches.subcommand_matches("fmt") {
+        info!("Calling the fmt command");
+        let check = format::get_fmt_cmd(&f);
+        match format::do_fmt(w, &cwd, &artifacts, check) {
+            Ok(true) => {}
+            Ok(false) => if check {
+                return 1;
+            },
+            Err(e) => {
+                error!("{}", e);
+                return 1;
+            }
+        }
+    } else if let Some(n) = matches.subcommand_matches("new") {
+        info!("Calling the new command");
+        let c = match new::get_new_cmd(&n, &cwd) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        match new::do_new(&artifacts, &settings, &c) {
+            Ok(path) => write!(w, "created {} in {}\n", c.name,
+                               core::utils::relative_path(&path, &cwd).display()).unwrap(),
+            Err(e) => {
+                error!("{}", e);
+                return 1;
+            }
+        }
+    } else if let Some(m) = matches.subcommand_matches("mv") {
+        info!("Calling the mv command");
+        let c = match mv::get_mv_cmd(&m) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = mv::do_mv(w, &cwd, &artifacts, &settings, &c) {
+            error!("{}", e);
+            return 1;
+        }
+    } else if let Some(r) = matches.subcommand_matches("rm") {
+        info!("Calling the rm command");
+        let c = match rm::get_rm_cmd(&r) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = rm::do_rm(w, &cwd, &artifacts, &c) {
+            error!("{}", e);
+            return 1;
+        }
+    } else if let Some(s) = matches.subcommand_matches("set-status") {
+        info!("Calling the set-status command");
+        let c = match set_status::get_set_status_cmd(&s) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = set_status::do_set_status(w, &artifacts, &settings, &c) {
+            error!("{}", e);
+            return 1;
+        }
+    } else if let Some(r) = matches.subcommand_matches("review") {
+        info!("Calling the review command");
+        let c = match review::get_review_cmd(&r) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = review::do_review(w, cfg, &artifacts, &c) {
+            error!("{}", e);
+            return 1;
+        }
+    } else if let Some(a) = matches.subcommand_matches("approve") {
+        info!("Calling the approve command");
+        let c = match approve::get_approve_cmd(&a) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return 1;
+            }
+        };
+        if let Err(e) = approve::do_approve(w, cfg, &artifacts, &c) {
+            error!("{}", e);
+            return 1;
+        }
     } else {
         write!(w, "{} {}: use -h to show help",
                Green.bold().paint("rst"),
                Green.paint(VERSION)).unwrap();
     }
+    0
 }
-
@@ -0,0 +1,290 @@
+//! rename artifacts, refactoring every reference to them
+
+use super::types::*;
+use super::fmt as cmdfmt;
+use core::{save, locs};
+use core::save::Change;
+use regex::Captures;
+use std::sync::Arc;
+
+lazy_static!{
+    /// a possible artifact reference in code, i.e. `#SPC-foo`
+    static ref CODE_REF_RE: Regex = Regex::new(r"#([A-Za-z]{3}-[A-Za-z0-9_-]*[A-Za-z0-9_])")
+        .unwrap();
+}
+
+/// the inputs of the mv command
+#[derive(Debug)]
+pub struct MvCmd {
+    pub old: ArtName,
+    pub new: ArtName,
+    pub recursive: bool,
+    pub dry_run: bool,
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("mv")
+        .about("rename an artifact, updating its definition, every partof that names it \
+                and every reference to it in the code")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("old")
+                 .help("the artifact to rename")
+                 .required(true))
+        .arg(Arg::with_name("new")
+                 .help("the new name of the artifact")
+                 .required(true))
+        .arg(Arg::with_name("recursive")
+                 .short("r")
+                 .long("recursive")
+                 .help("also rename all artifacts whose name starts with the artifact's name"))
+        .arg(Arg::with_name("dry-run")
+                 .short("n")
+                 .long("dry-run")
+                 .help("do not write the files, only show a diff of the changes"))
+}
+
+/// get all the information from the user input
+pub fn get_mv_cmd(matches: &ArgMatches) -> Result<MvCmd, String> {
+    let old = try!(ArtName::from_str(matches.value_of("old").unwrap())
+        .map_err(|e| e.to_string()));
+    let new = try!(ArtName::from_str(matches.value_of("new").unwrap())
+        .map_err(|e| e.to_string()));
+    Ok(MvCmd {
+        old: old,
+        new: new,
+        recursive: matches.is_present("recursive"),
+        dry_run: matches.is_present("dry-run"),
+    })
+}
+
+/// get every artifact that will be renamed, mapped to its new name
+pub fn get_renames(cmd: &MvCmd, artifacts: &Artifacts)
+                   -> Result<HashMap<ArtName, ArtName>, String> {
+    let old = match artifacts.keys().find(|n| n.as_ref() == &cmd.old) {
+        Some(n) => n,
+        None => {
+            return Err(format!("{} does not exist{}",
+                               cmd.old, utils::did_you_mean(&cmd.old, artifacts.keys())))
+        }
+    };
+    if cmd.old == cmd.new {
+        return Err(format!("{} is already named {}", cmd.old, cmd.new));
+    }
+    let mut children: Vec<&ArtNameRc> = artifacts.keys()
+        .filter(|n| n.value.len() > old.value.len() && n.value.starts_with(&old.value))
+        .collect();
+    children.sort();
+    let mut renames = HashMap::new();
+    if !cmd.recursive {
+        if let Some(c) = children.first() {
+            return Err(format!("{} has children such as {}, use --recursive to rename them",
+                               old, c));
+        }
+        if artifacts.get(old).unwrap().path == PathBuf::from("PARENT") {
+            return Err(format!("{} is not defined in a file", old));
+        }
+    }
+    renames.insert(old.as_ref().clone(), cmd.new.clone());
+    for c in children {
+        let rest: Vec<&str> = c.raw.split('-').skip(old.value.len()).collect();
+        let new = try!(ArtName::from_str(&format!("{}-{}", cmd.new.raw, rest.join("-")))
+            .map_err(|e| e.to_string()));
+        renames.insert(c.as_ref().clone(), new);
+    }
+    for new in renames.values() {
+        if artifacts.contains_key(new) && !renames.contains_key(new) {
+            return Err(format!("{} already exists", new));
+        }
+    }
+    Ok(renames)
+}
+
+fn rename(name: &ArtName, renames: &HashMap<ArtName, ArtName>) -> ArtNameRc {
+    Arc::new(renames.get(name).unwrap_or(name).clone())
+}
+
+fn rename_all(names: &ArtNames, renames: &HashMap<ArtName, ArtName>) -> ArtNames {
+    names.iter().map(|n| rename(n, renames)).collect()
+}
+
+/// get the partof of an artifact after renaming. Renaming moves an
+/// artifact in the name hierarchy, so it is linked to the parent of its new
+/// name instead of its old one
+fn expected_partof(name: &ArtName, artifact: &Artifact, renames: &HashMap<ArtName, ArtName>)
+                   -> ArtNames {
+    let mut partof = rename_all(&artifact.partof, renames);
+    if let Some(new) = renames.get(name) {
+        if let Some(p) = name.parent() {
+            partof.remove(&rename(&p, renames));
+        }
+        if let Some(p) = new.parent_rc() {
+            partof.insert(p);
+        }
+    }
+    partof
+}
+
+/// get the link structure between the defined artifacts after renaming.
+/// Automatically created parents only exist because of the names and
+/// are left out
+fn get_structure(artifacts: &Artifacts, renames: &HashMap<ArtName, ArtName>)
+                 -> HashMap<ArtNameRc, ArtNames> {
+    let auto_path = PathBuf::from("PARENT");
+    let mut auto = ArtNames::new();
+    let mut defined = ArtNames::new();
+    for (n, a) in artifacts.iter() {
+        if a.path == auto_path {
+            auto.insert(rename(n, renames));
+        } else {
+            defined.insert(rename(n, renames));
+        }
+    }
+    auto.extend(renames.values().filter_map(|n| n.parent_rc()));
+    artifacts.iter()
+        .filter(|&(_, a)| a.path != auto_path)
+        .map(|(n, a)| {
+            let partof = expected_partof(n, a, renames)
+                .into_iter()
+                .filter(|p| defined.contains(p) || !auto.contains(p))
+                .collect();
+            (rename(n, renames), partof)
+        })
+        .collect()
+}
+
+/// rename the artifacts in the toml files, adding explicit partofs for any
+/// automatic links that would otherwise be lost
+fn rename_in_files(artifacts: &Artifacts, renames: &HashMap<ArtName, ArtName>)
+                   -> Result<Vec<Change>, String> {
+    let auto = PathBuf::from("PARENT");
+    // the names of the defined artifacts after renaming, the only
+    // artifacts that can be linked to by name
+    let defined: ArtNames = artifacts.iter()
+        .filter(|&(_, a)| a.path != auto)
+        .map(|(n, _)| rename(n, renames))
+        .collect();
+    let mut changes = Vec::new();
+    for path in save::artifact_files(artifacts) {
+        let original = try!(save::read_file(&path).map_err(|e| e.to_string()));
+        let mut file = try!(save::FileText::from_str(&original)
+            .map_err(|e| format!("{}: {}", path.display(), e)));
+        for table in file.tables.iter_mut().filter(|t| t.is_artifact()) {
+            let name = match ArtName::from_str(&table.name) {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            let artifact = match artifacts.get(&name) {
+                Some(a) => a,
+                None => continue,  // disabled
+            };
+            let explicit = match table.get_str("partof") {
+                Ok(Some(p)) => try!(ArtNames::from_str(&p).map_err(|e| e.to_string())),
+                Ok(None) => ArtNames::new(),
+                Err(e) => return Err(format!("{}: {}", path.display(), e)),
+            };
+            let new_name = rename(&name, renames);
+            let expected = expected_partof(&name, artifact, renames);
+            let mut linked: ArtNames = new_name.named_partofs()
+                .into_iter()
+                .map(Arc::new)
+                .filter(|n| defined.contains(n))
+                .collect();
+            if let Some(p) = new_name.parent_rc() {
+                linked.insert(p);
+            }
+            let mut extra: Vec<&ArtNameRc> = linked.difference(&expected).collect();
+            extra.sort();
+            if let Some(e) = extra.first() {
+                return Err(format!("renaming would automatically link {} to {}",
+                                   new_name, e));
+            }
+            let mut new_explicit = rename_all(&explicit, renames);
+            let lost: Vec<ArtNameRc> = expected.iter()
+                .filter(|p| !linked.contains(*p) && !new_explicit.contains(*p))
+                .cloned()
+                .collect();
+            new_explicit.extend(lost);
+            if *new_name != name {
+                table.rename(&new_name);
+            }
+            if new_explicit != explicit || explicit.iter().any(|n| renames.contains_key(n)) {
+                table.set_partof(&new_explicit);
+            }
+        }
+        let new = file.to_string();
+        if new != original {
+            changes.push((path, original, new));
+        }
+    }
+    Ok(changes)
+}
+
+/// rename the `#ART` references in the code files
+fn rename_in_code(settings: &Settings, renames: &HashMap<ArtName, ArtName>)
+                  -> Result<Vec<Change>, String> {
+    let mut changes = Vec::new();
+    for path in try!(locs::find_code_files(settings).map_err(|e| e.to_string())) {
+        let original = match save::read_file(&path) {
+            Ok(t) => t,
+            Err(e) => {
+                debug!("skipping code file: {}", e);
+                continue;
+            }
+        };
+        let new = CODE_REF_RE.replace_all(&original, |caps: &Captures| {
+            match ArtName::from_str(caps.at(1).unwrap()) {
+                Ok(ref n) if renames.contains_key(n) => format!("#{}", renames[n].raw),
+                _ => caps.at(0).unwrap().to_string(),
+            }
+        });
+        if new != original {
+            changes.push((path, original, new));
+        }
+    }
+    Ok(changes)
+}
+
+/// make sure the project loads with the same link structure after the changes
+pub fn verify_changes(artifacts: &Artifacts, renames: &HashMap<ArtName, ArtName>,
+                      changes: &[Change])
+                      -> Result<(), String> {
+    let files = try!(save::changed_texts(&save::artifact_files(artifacts), changes)
+        .map_err(|e| e.to_string()));
+    let reloaded = try!(save::load_files(&files)
+        .map_err(|e| format!("renamed artifacts would not load: {}", e)));
+    let expected = get_structure(artifacts, renames);
+    let actual = get_structure(&reloaded, &HashMap::new());
+    let mut names: Vec<&ArtNameRc> = expected.keys().chain(actual.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        if expected.get(name) != actual.get(name) {
+            return Err(format!("renaming would change the links of {}", name));
+        }
+    }
+    Ok(())
+}
+
+/// rename the artifacts, writing the changes or a diff of them if this
+/// is a dry run. Returns the changes
+/// partof: #SPC-mv
+pub fn do_mv<W: Write>(w: &mut W, cwd: &Path, artifacts: &Artifacts, settings: &Settings,
+                       cmd: &MvCmd)
+                       -> Result<Vec<Change>, String> {
+    let renames = try!(get_renames(cmd, artifacts));
+    let mut changes = try!(rename_in_files(artifacts, &renames));
+    try!(verify_changes(artifacts, &renames, &changes));
+    changes.extend(try!(rename_in_code(settings, &renames)));
+    if cmd.dry_run {
+        for &(ref path, ref original, ref new) in &changes {
+            let path = utils::relative_path(path, cwd);
+            try!(cmdfmt::write_diff(w, &path, original, new, settings)
+                .map_err(|e| e.to_string()));
+        }
+    } else {
+        try!(save::write_files(&changes).map_err(|e| e.to_string()));
+        try!(write!(w, "renamed {} artifacts in {} files\n", renames.len(), changes.len())
+            .map_err(|e| e.to_string()));
+    }
+    Ok(changes)
+}
@@ -0,0 +1,144 @@
+//! create new artifacts from the cmdline
+
+use super::types::*;
+use core::save;
+
+/// the inputs of the new command
+#[derive(Debug)]
+pub struct NewCmd {
+    pub name: ArtName,
+    pub partof: ArtNames,
+    pub text: String,
+    pub file: Option<PathBuf>,
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("new")
+        .about("create a new artifact, adding it to the file where its parent \
+                or siblings are defined")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("name")
+                 .help("the name of the new artifact")
+                 .required(true))
+        .arg(Arg::with_name("partof")
+                 .short("p")
+                 .long("partof")
+                 .value_name("NAMES")
+                 .help("the artifacts the new artifact is a partof, i.e. `REQ-[foo, bar]`")
+                 .takes_value(true))
+        .arg(Arg::with_name("text")
+                 .short("t")
+                 .long("text")
+                 .value_name("TEXT")
+                 .help("the text of the new artifact")
+                 .takes_value(true))
+        .arg(Arg::with_name("file")
+                 .short("f")
+                 .long("file")
+                 .value_name("FILE")
+                 .help("the file to add the artifact to. Required if it can not be \
+                        determined from the artifact's parent or siblings")
+                 .takes_value(true))
+}
+
+/// get all the information from the user input
+pub fn get_new_cmd(matches: &ArgMatches, cwd: &Path) -> Result<NewCmd, String> {
+    let name = try!(ArtName::from_str(matches.value_of("name").unwrap())
+        .map_err(|e| e.to_string()));
+    let partof = try!(ArtNames::from_str(matches.value_of("partof").unwrap_or(""))
+        .map_err(|e| format!("invalid partof: {}", e)));
+    Ok(NewCmd {
+        name: name,
+        partof: partof,
+        text: matches.value_of("text").unwrap_or("").to_string(),
+        file: matches.value_of("file").map(|f| cwd.join(f)),
+    })
+}
+
+/// choose the file to add a new artifact to: the file of its closest
+/// defined ancestor, or else the file where most of its siblings (or its
+/// closest ancestor's children) are defined
+pub fn choose_file(name: &ArtName, artifacts: &Artifacts) -> Option<PathBuf> {
+    let auto = PathBuf::from("PARENT");
+    let mut ancestor = name.parent();
+    while let Some(p) = ancestor {
+        // only the type is shared, which is too broad to pick a file
+        if p.value.len() <= 1 {
+            break;
+        }
+        if let Some(a) = artifacts.get(&p) {
+            if a.path != auto {
+                return Some(a.path.clone());
+            }
+        }
+        let mut counts: HashMap<&PathBuf, usize> = HashMap::new();
+        for (n, a) in artifacts.iter() {
+            if a.path != auto && n.parent().as_ref() == Some(&p) {
+                *counts.entry(&a.path).or_insert(0) += 1;
+            }
+        }
+        let mut counts: Vec<(&PathBuf, usize)> = counts.into_iter().collect();
+        // most common first, ties broken by path so the choice is stable
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        if let Some(&(path, _)) = counts.first() {
+            return Some(path.clone());
+        }
+        ancestor = p.parent();
+    }
+    None
+}
+
+/// create the new artifact, returning the file it was added to
+/// partof: #SPC-new
+pub fn do_new(artifacts: &Artifacts, settings: &Settings, cmd: &NewCmd)
+              -> Result<PathBuf, String> {
+    let auto = PathBuf::from("PARENT");
+    if let Some(a) = artifacts.get(&cmd.name) {
+        if a.path != auto {
+            return Err(format!("{} already exists in {}", cmd.name, a.path.display()));
+        }
+    }
+    let mut partof: Vec<&ArtNameRc> = cmd.partof.iter().collect();
+    partof.sort();
+    for p in partof {
+        if !artifacts.contains_key(p) {
+            return Err(format!("partof {} does not exist{}",
+                               p, utils::did_you_mean(p, artifacts.keys())));
+        }
+    }
+    let path = match cmd.file {
+        Some(ref f) => f.clone(),
+        None => match choose_file(&cmd.name, artifacts) {
+            Some(f) => f,
+            None => {
+                return Err(format!("could not determine which file to add {} to, \
+                                    use --file", cmd.name))
+            }
+        },
+    };
+    // the file must be somewhere that artifacts are loaded from
+    if path.extension().map_or(true, |e| e != "toml") {
+        return Err(format!("{} is not a toml file", path.display()));
+    }
+    if !settings.paths.iter().any(|p| path.starts_with(p)) {
+        return Err(format!("{} is not in a directory that artifacts are loaded from",
+                           path.display()));
+    }
+    let original = if path.exists() {
+        try!(save::read_file(&path).map_err(|e| e.to_string()))
+    } else {
+        String::new()
+    };
+    let mut text = original.clone();
+    if text != "" {
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push('\n');
+    }
+    text.push_str(&save::fmt_artifact(&cmd.name, &cmd.partof, &cmd.text));
+    // make sure the file is still valid
+    try!(save::load_files(&[(path.clone(), text.clone())]).map_err(|e| e.to_string()));
+    try!(save::write_files(&[(path.clone(), original, text)]).map_err(|e| e.to_string()));
+    Ok(path)
+}
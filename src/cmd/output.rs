@@ -0,0 +1,201 @@
+//! write artifacts in formats that are easy for scripts and other tools
+//! to consume. Unlike the table, nothing here is colored or padded
+
+use super::types::*;
+use strfmt;
+
+/// the names of the fields written for every artifact. These are part of the
+/// interface of `rst ls` and **must not** change
+pub const FIELDS: [&'static str; 11] = ["name", "type", "path", "completed", "tested",
+                                        "loc_path", "loc_line", "loc_col",
+                                        "parts", "partof", "text"];
+
+/// the value of a field, typed so that it can be written as json
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(Option<i64>),
+    List(Vec<String>),
+}
+
+impl ToString for Value {
+    fn to_string(&self) -> String {
+        match *self {
+            Value::Str(ref s) => s.clone(),
+            Value::Int(Some(i)) => i.to_string(),
+            Value::Int(None) => "".to_string(),
+            Value::List(ref l) => l.join(", "),
+        }
+    }
+}
+
+fn display_path(path: &Path, cwd: &Path) -> String {
+    if path == Path::new("PARENT") {
+        "PARENT".to_string()
+    } else {
+        utils::relative_path(path, cwd).display().to_string()
+    }
+}
+
+fn sorted_names(names: &ArtNames) -> Vec<String> {
+    let mut names: Vec<&ArtNameRc> = names.iter().collect();
+    names.sort();
+    names.iter().map(|n| n.raw.clone()).collect()
+}
+
+/// get the value of every field in FIELDS (in the same order) for an artifact.
+///
+/// completed and tested are integer percents, which are negative if they
+/// could not be resolved
+pub fn get_fields(name: &ArtName, artifact: &Artifact, cwd: &Path) -> Vec<(&'static str, Value)> {
+    let (loc_path, loc_line, loc_col) = match artifact.loc {
+        Some(ref l) => (display_path(&l.path, cwd),
+                        Some(l.line_col.0 as i64),
+                        Some(l.line_col.1 as i64)),
+        None => ("".to_string(), None, None),
+    };
+    let values = vec![Value::Str(name.raw.clone()),
+                      Value::Str(format!("{:?}", artifact.ty)),
+                      Value::Str(display_path(&artifact.path, cwd)),
+                      Value::Int(Some((artifact.completed * 100.) as i64)),
+                      Value::Int(Some((artifact.tested * 100.) as i64)),
+                      Value::Str(loc_path),
+                      Value::Int(loc_line),
+                      Value::Int(loc_col),
+                      Value::List(sorted_names(&artifact.parts)),
+                      Value::List(sorted_names(&artifact.partof)),
+                      Value::Str(artifact.text.clone())];
+    FIELDS.iter().cloned().zip(values).collect()
+}
+
+fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn fmt_json(value: &Value) -> String {
+    match *value {
+        Value::Str(ref s) => escape_json(s),
+        Value::Int(Some(i)) => i.to_string(),
+        Value::Int(None) => "null".to_string(),
+        Value::List(ref l) => {
+            let items: Vec<String> = l.iter().map(|s| escape_json(s)).collect();
+            format!("[{}]", items.join(", "))
+        }
+    }
+}
+
+/// quote a csv value according to RFC 4180
+fn escape_csv(s: &str) -> String {
+    if s.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", s.replace("\"", "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// tsv values cannot contain tabs or newlines, so they are backslash escaped
+fn escape_tsv(s: &str) -> String {
+    s.replace("\\", "\\\\").replace("\t", "\\t").replace("\n", "\\n").replace("\r", "\\r")
+}
+
+/// replace the escapes that are commonly used in a template but which
+/// the shell does not expand (`\t`, `\n` and `\\`)
+fn unescape_template(template: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            Some(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// get the template from the user input, failing if it uses an unknown field
+pub fn get_template(template: &str) -> Result<String, String> {
+    let template = unescape_template(template);
+    let vars: HashMap<String, String> = FIELDS.iter()
+        .map(|f| (f.to_string(), String::new()))
+        .collect();
+    match strfmt::strfmt(&template, &vars) {
+        Ok(_) => Ok(template),
+        Err(e) => Err(format!("invalid template (fields are {}): {}",
+                              FIELDS.join(", "), e.to_string())),
+    }
+}
+
+/// write the artifacts in a machine readable format, with one
+/// record for every name
+/// partof: #SPC-ls-format
+pub fn write_output<W: Write>(w: &mut W,
+                              format: &OutputFormat,
+                              names: &[ArtNameRc],
+                              artifacts: &Artifacts,
+                              cwd: &Path)
+                              -> io::Result<()> {
+    let records: Vec<Vec<(&'static str, Value)>> = names.iter()
+        .map(|n| get_fields(n, &artifacts[n], cwd))
+        .collect();
+    match *format {
+        OutputFormat::Table => unreachable!("the table is written by FmtArtifact"),
+        OutputFormat::Json => {
+            try!(w.write_all(b"["));
+            for (i, record) in records.iter().enumerate() {
+                let fields: Vec<String> = record.iter()
+                    .map(|&(k, ref v)| format!("\"{}\": {}", k, fmt_json(v)))
+                    .collect();
+                try!(write!(w, "{}\n  {{{}}}", if i == 0 { "" } else { "," }, fields.join(", ")));
+            }
+            try!(w.write_all(if records.is_empty() { "]\n" } else { "\n]\n" }.as_bytes()));
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let (sep, escape): (&str, fn(&str) -> String) = match *format {
+                OutputFormat::Csv => (",", escape_csv),
+                _ => ("\t", escape_tsv),
+            };
+            try!(write!(w, "{}\n", FIELDS.join(sep)));
+            for record in &records {
+                let values: Vec<String> = record.iter()
+                    .map(|&(_, ref v)| escape(&v.to_string()))
+                    .collect();
+                try!(write!(w, "{}\n", values.join(sep)));
+            }
+        }
+        OutputFormat::Template(ref template) => {
+            for record in &records {
+                let vars: HashMap<String, String> = record.iter()
+                    .map(|&(k, ref v)| (k.to_string(), v.to_string()))
+                    .collect();
+                // the template was validated by get_template
+                let line = strfmt::strfmt(template, &vars).unwrap();
+                try!(write!(w, "{}\n", line));
+            }
+        }
+    }
+    Ok(())
+}
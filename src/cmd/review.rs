@@ -0,0 +1,98 @@
+//! mark the links of artifacts as reviewed, so that they are no longer
+//! suspect
+
+use super::types::*;
+use core::{review, save};
+use core::types::Reviewed;
+
+/// the inputs of the review command
+#[derive(Debug)]
+pub struct ReviewCmd {
+    pub names: Vec<ArtName>,
+    pub all: bool,
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("review")
+        .about("mark the links from artifacts to their parents as reviewed. A link is \
+                suspect when the text of the parent changes after it is reviewed")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("names")
+                 .help("the artifacts whose links to their parents were reviewed")
+                 .multiple(true)
+                 .required_unless("all"))
+        .arg(Arg::with_name("all")
+                 .long("all")
+                 .help("mark the links of every artifact as reviewed"))
+}
+
+/// get all the information from the user input
+pub fn get_review_cmd(matches: &ArgMatches) -> Result<ReviewCmd, String> {
+    let mut names = Vec::new();
+    for n in matches.values_of("names").map_or(Vec::new(), |v| v.collect()) {
+        names.push(try!(ArtName::from_str(n).map_err(|e| e.to_string())));
+    }
+    Ok(ReviewCmd {
+        names: names,
+        all: matches.is_present("all"),
+    })
+}
+
+/// record the reviews in `reviewed`, returning the reviewed artifacts and
+/// the parents they were reviewed against
+pub fn review_names(artifacts: &Artifacts, reviewed: &mut Reviewed, cmd: &ReviewCmd)
+                    -> Result<Vec<(ArtNameRc, Vec<ArtNameRc>)>, String> {
+    let mut names: Vec<ArtNameRc> = Vec::new();
+    if cmd.all {
+        names.extend(artifacts.keys().cloned());
+    }
+    for n in &cmd.names {
+        // the name is displayed as it is defined, not as it was typed
+        match artifacts.get_key_value(n) {
+            Some((n, _)) => names.push(n.clone()),
+            None => {
+                return Err(format!("{} does not exist{}",
+                                   n, utils::did_you_mean(n, artifacts.keys())))
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    let mut out = Vec::new();
+    for name in names {
+        review::review_links(artifacts, reviewed, &name);
+        let mut parents: Vec<ArtNameRc> = reviewed[&name].keys().cloned().collect();
+        parents.sort();
+        out.push((name, parents));
+    }
+    review::prune_reviewed(artifacts, reviewed);
+    Ok(out)
+}
+
+/// write the reviewed links to the file in the settings directory
+/// partof: #SPC-review
+pub fn do_review<W: Write>(w: &mut W, cfg: &Path, artifacts: &Artifacts, cmd: &ReviewCmd)
+                           -> Result<(), String> {
+    let mut reviewed = try!(review::load_reviewed(cfg).map_err(|e| e.to_string()));
+    let done = try!(review_names(artifacts, &mut reviewed, cmd));
+    let path = cfg.join(review::REVIEWED_FILE);
+    let original = if path.exists() {
+        try!(save::read_file(&path).map_err(|e| e.to_string()))
+    } else {
+        String::new()
+    };
+    let change = (path, original, review::fmt_reviewed(&reviewed));
+    try!(save::write_files(&[change]).map_err(|e| e.to_string()));
+    if cmd.all {
+        write!(w, "reviewed the links of {} artifacts\n", done.len()).unwrap();
+        return Ok(());
+    }
+    for (name, parents) in done {
+        if parents.is_empty() {
+            write!(w, "{} has no parents to review\n", name).unwrap();
+        } else {
+            write!(w, "{}: reviewed against {}\n", name, ui::fmt_names(&parents)).unwrap();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! remove artifacts, checking for references to them first
+
+use super::types::*;
+use core::save;
+use std::sync::Arc;
+
+/// the inputs of the rm command
+#[derive(Debug)]
+pub struct RmCmd {
+    pub name: ArtName,
+    pub force: bool,
+    pub cascade: bool,
+}
+
+/// everything that refers to an artifact
+#[derive(Debug, Default, PartialEq)]
+pub struct References {
+    /// artifacts (other than children) that are a partof the artifact
+    pub referrers: Vec<ArtNameRc>,
+    /// artifacts in the name hierarchy below the artifact
+    pub children: Vec<ArtNameRc>,
+    /// code locations of the artifact and its children
+    pub locs: Vec<(ArtNameRc, Loc)>,
+}
+
+impl References {
+    pub fn is_empty(&self) -> bool {
+        self.referrers.is_empty() && self.children.is_empty() && self.locs.is_empty()
+    }
+
+    /// write the references, one per line
+    pub fn write<W: Write>(&self, w: &mut W, cwd: &Path) -> io::Result<()> {
+        for name in &self.referrers {
+            try!(write!(w, " - {} is a partof it\n", name));
+        }
+        for name in &self.children {
+            try!(write!(w, " - {} is a child of it\n", name));
+        }
+        for &(ref name, ref loc) in &self.locs {
+            try!(write!(w, " - {} is implemented at {}:{}\n", name,
+                        utils::relative_path(&loc.path, cwd).display(), loc.line_col.0));
+        }
+        Ok(())
+    }
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("rm")
+        .about("remove an artifact from the file it is defined in. Fails if anything \
+                refers to it unless --force or --cascade is given")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("name")
+                 .help("the artifact to remove")
+                 .required(true))
+        .arg(Arg::with_name("force")
+                 .short("f")
+                 .long("force")
+                 .help("remove only the artifact, even if it is still referred to"))
+        .arg(Arg::with_name("cascade")
+                 .short("c")
+                 .long("cascade")
+                 .help("also remove the artifact's children and remove it from \
+                        every partof that names it")
+                 .conflicts_with("force"))
+}
+
+/// get all the information from the user input
+pub fn get_rm_cmd(matches: &ArgMatches) -> Result<RmCmd, String> {
+    let name = try!(ArtName::from_str(matches.value_of("name").unwrap())
+        .map_err(|e| e.to_string()));
+    Ok(RmCmd {
+        name: name,
+        force: matches.is_present("force"),
+        cascade: matches.is_present("cascade"),
+    })
+}
+
+/// find everything that refers to the artifact, which must exist
+pub fn get_references(name: &ArtName, artifacts: &Artifacts) -> References {
+    let is_child = |n: &ArtName| n.value.len() > name.value.len() &&
+                                 n.value.starts_with(&name.value);
+    let mut refs = References::default();
+    for (n, a) in artifacts.iter() {
+        if is_child(n) {
+            refs.children.push(n.clone());
+        } else if a.partof.contains(name) {
+            refs.referrers.push(n.clone());
+        }
+        if is_child(n) || n.as_ref() == name {
+            if let Some(ref loc) = a.loc {
+                refs.locs.push((n.clone(), loc.clone()));
+            }
+        }
+    }
+    refs.referrers.sort();
+    refs.children.sort();
+    refs.locs.sort_by(|a, b| a.0.cmp(&b.0));
+    refs
+}
+
+/// remove the artifacts from their files, and from every explicit partof
+/// if `strip_partof` is set. Returns the changed files
+fn remove_from_files(artifacts: &Artifacts, removed: &ArtNames, strip_partof: bool)
+                     -> Result<Vec<save::Change>, String> {
+    let mut changes = Vec::new();
+    for path in save::artifact_files(artifacts) {
+        let original = try!(save::read_file(&path).map_err(|e| e.to_string()));
+        let mut file = try!(save::FileText::from_str(&original)
+            .map_err(|e| format!("{}: {}", path.display(), e)));
+        file.tables.retain(|t| match ArtName::from_str(&t.name) {
+            Ok(ref n) => !(t.is_artifact() && removed.contains(n)),
+            Err(_) => true,
+        });
+        if strip_partof {
+            for table in file.tables.iter_mut().filter(|t| t.is_artifact()) {
+                let partof = match table.get_str("partof") {
+                    Ok(Some(p)) => try!(ArtNames::from_str(&p).map_err(|e| e.to_string())),
+                    Ok(None) => continue,
+                    Err(e) => return Err(format!("{}: {}", path.display(), e)),
+                };
+                let stripped: ArtNames = partof.difference(removed).cloned().collect();
+                if stripped != partof {
+                    table.set_partof(&stripped);
+                }
+            }
+        }
+        let new = file.to_string();
+        if new != original {
+            changes.push((path, original, new));
+        }
+    }
+    Ok(changes)
+}
+
+/// remove the artifact after checking what refers to it
+/// partof: #SPC-rm
+pub fn do_rm<W: Write>(w: &mut W, cwd: &Path, artifacts: &Artifacts, cmd: &RmCmd)
+                       -> Result<(), String> {
+    let artifact = match artifacts.get(&cmd.name) {
+        Some(a) => a,
+        None => {
+            return Err(format!("{} does not exist{}",
+                               cmd.name, utils::did_you_mean(&cmd.name, artifacts.keys())))
+        }
+    };
+    let refs = get_references(&cmd.name, artifacts);
+    if !refs.is_empty() {
+        try!(write!(w, "{} is referred to by:\n", cmd.name).map_err(|e| e.to_string()));
+        try!(refs.write(w, cwd).map_err(|e| e.to_string()));
+        if !(cmd.force || cmd.cascade) {
+            return Err(format!("{} is still referred to, use --force or --cascade",
+                               cmd.name));
+        }
+    }
+    let mut removed = ArtNames::new();
+    removed.insert(Arc::new(cmd.name.clone()));
+    if cmd.cascade {
+        removed.extend(refs.children.iter().cloned());
+    } else if artifact.path == PathBuf::from("PARENT") {
+        return Err(format!("{} is not defined in a file, use --cascade to remove \
+                            its children", cmd.name));
+    }
+    let changes = try!(remove_from_files(artifacts, &removed, cmd.cascade));
+    // make sure the files are still valid
+    let files = try!(save::changed_texts(&save::artifact_files(artifacts), &changes)
+        .map_err(|e| e.to_string()));
+    try!(save::load_files(&files).map_err(|e| format!("files would not load: {}", e)));
+    try!(save::write_files(&changes).map_err(|e| e.to_string()));
+    write!(w, "removed {} artifacts from {} files\n", removed.len(), changes.len()).unwrap();
+    for &(ref name, ref loc) in &refs.locs {
+        if removed.contains(name) {
+            warn!("{} is still referenced in the code at {}:{}", name,
+                  utils::relative_path(&loc.path, cwd).display(), loc.line_col.0);
+        }
+    }
+    Ok(())
+}
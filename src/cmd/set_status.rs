@@ -0,0 +1,106 @@
+//! change the status of artifacts, following the allowed transitions
+
+use super::types::*;
+use core::save;
+
+use toml::Value;
+
+/// the inputs of the set-status command
+#[derive(Debug)]
+pub struct SetStatusCmd {
+    pub name: ArtName,
+    pub status: String,
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("set-status")
+        .about("change the status of an artifact, i.e. from draft to approved. Only the \
+                transitions in the settings are allowed")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("name")
+                 .help("the artifact to change")
+                 .required(true))
+        .arg(Arg::with_name("status")
+                 .help("the new status of the artifact")
+                 .required(true))
+}
+
+/// get all the information from the user input
+pub fn get_set_status_cmd(matches: &ArgMatches) -> Result<SetStatusCmd, String> {
+    let name = try!(ArtName::from_str(matches.value_of("name").unwrap())
+        .map_err(|e| e.to_string()));
+    Ok(SetStatusCmd {
+        name: name,
+        status: matches.value_of("status").unwrap().to_string(),
+    })
+}
+
+/// check that the status can be changed from `from` to `to`. An artifact
+/// without a status can be given any status
+/// partof: #SPC-artifact-status-transitions
+pub fn check_transition(settings: &Settings, from: Option<&str>, to: &str)
+                        -> Result<(), String> {
+    if !settings.statuses.iter().any(|s| s == to) {
+        return Err(format!("unknown status {:?}, the statuses are: {}",
+                           to, settings.statuses.join(", ")));
+    }
+    let from = match from {
+        Some(f) => f,
+        None => return Ok(()),
+    };
+    if from == to {
+        return Err(format!("the status is already {}", to));
+    }
+    if settings.transitions.is_empty() ||
+       settings.transitions.iter().any(|t| t.0 == from && t.1 == to) {
+        return Ok(());
+    }
+    let mut allowed: Vec<&str> = settings.transitions.iter()
+        .filter(|t| t.0 == from)
+        .map(|t| t.1.as_str())
+        .collect();
+    allowed.sort();
+    Err(match allowed.len() {
+        0 => format!("the status cannot be changed from {}", from),
+        _ => format!("the status cannot be changed from {} to {}, it can be changed to: {}",
+                     from, to, allowed.join(", ")),
+    })
+}
+
+/// change the status in the file the artifact is defined in
+/// partof: #SPC-set-status
+pub fn do_set_status<W: Write>(w: &mut W, artifacts: &Artifacts, settings: &Settings,
+                               cmd: &SetStatusCmd)
+                               -> Result<(), String> {
+    let artifact = match artifacts.get(&cmd.name) {
+        Some(a) => a,
+        None => {
+            return Err(format!("{} does not exist{}",
+                               cmd.name, utils::did_you_mean(&cmd.name, artifacts.keys())))
+        }
+    };
+    if artifact.path == PathBuf::from("PARENT") {
+        return Err(format!("{} is not defined in a file", cmd.name));
+    }
+    try!(check_transition(settings, artifact.status.as_ref().map(|s| s.as_str()), &cmd.status)
+        .map_err(|e| format!("{}: {}", cmd.name, e)));
+    let original = try!(save::read_file(&artifact.path).map_err(|e| e.to_string()));
+    let mut file = try!(save::FileText::from_str(&original)
+        .map_err(|e| format!("{}: {}", artifact.path.display(), e)));
+    {
+        let table = match file.tables.iter_mut().find(|t| {
+            t.is_artifact() && ArtName::from_str(&t.name).ok().as_ref() == Some(&cmd.name)
+        }) {
+            Some(t) => t,
+            None => {
+                return Err(format!("{} was not found in {}", cmd.name, artifact.path.display()))
+            }
+        };
+        table.set_key("status", &Value::String(cmd.status.clone()).to_string());
+    }
+    let change = (artifact.path.clone(), original, file.to_string());
+    try!(save::write_files(&[change]).map_err(|e| e.to_string()));
+    write!(w, "{}: {} -> {}\n", cmd.name,
+           artifact.status.as_ref().map_or("(none)", |s| s.as_str()), cmd.status).unwrap();
+    Ok(())
+}
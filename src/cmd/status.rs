@@ -0,0 +1,257 @@
+//! analyze the loaded artifacts and report a summary and any errors
+
+use super::types::*;
+use core::{link, locs};
+use core::types::{APPROVED, DRAFT};
+
+/// the types in the order they are displayed
+const TYPES: [ArtType; 4] = [ArtType::REQ, ArtType::SPC, ArtType::RSK, ArtType::TST];
+
+/// totals of the artifacts of a single type
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeTotal {
+    pub ty: ArtType,
+    pub count: usize,
+    pub completed: f32,
+    pub tested: f32,
+}
+
+/// summary of the project and the errors in it
+#[derive(Debug, Default)]
+pub struct Status {
+    pub totals: Vec<TypeTotal>,
+    /// (artifact, partof) pairs where the partof does not exist
+    pub invalid_partof: Vec<(ArtNameRc, ArtNameRc)>,
+    /// artifacts whose completed or tested % could not be resolved
+    pub unresolved: Vec<ArtNameRc>,
+    /// (artifact, parent) pairs where the text of the parent changed since
+    /// the link was reviewed
+    pub suspect: Vec<(ArtNameRc, ArtNameRc)>,
+    /// (artifact, mention) pairs where the artifact mentioned in the text
+    /// does not exist. These are warnings, not errors
+    pub invalid_mentions: Vec<(ArtNameRc, ArtNameRc)>,
+    /// (artifact, parent) pairs where an approved SPC is a partof a REQ
+    /// which is still a draft. These are warnings, not errors
+    pub draft_parents: Vec<(ArtNameRc, ArtNameRc)>,
+    /// references in the code to artifacts that do not exist. These are
+    /// warnings, not errors
+    pub dangling_locs: Vec<(ArtName, Loc)>,
+}
+
+impl Status {
+    pub fn num_errors(&self) -> usize {
+        self.invalid_partof.len() + self.unresolved.len() + self.suspect.len()
+    }
+
+    pub fn num_warnings(&self) -> usize {
+        self.invalid_mentions.len() + self.draft_parents.len() + self.dangling_locs.len()
+    }
+
+    /// write the errors found, one per line
+    pub fn write_errors<W: Write>(&self, w: &mut W, cwd: &Path, artifacts: &Artifacts)
+                                  -> io::Result<()> {
+        for &(ref name, ref partof) in &self.invalid_partof {
+            let artifact = artifacts.get(name).unwrap();
+            try!(write!(w, " - [{}] {} has invalid partof {}{}\n",
+                        utils::relative_path(&artifact.path, cwd).display(), name, partof,
+                        utils::did_you_mean(partof, artifacts.keys())));
+        }
+        for name in &self.unresolved {
+            try!(write!(w, " - {} could not resolve completed/tested %\n", name));
+        }
+        for &(ref name, ref parent) in &self.suspect {
+            let artifact = artifacts.get(name).unwrap();
+            try!(write!(w, " - [{}] {} is suspect: {} changed since the link was reviewed, \
+                            run `rst review {}` once it is checked\n",
+                        utils::relative_path(&artifact.path, cwd).display(), name, parent,
+                        name));
+        }
+        Ok(())
+    }
+
+    /// write the warnings found, one per line
+    pub fn write_warnings<W: Write>(&self, w: &mut W, cwd: &Path, artifacts: &Artifacts)
+                                    -> io::Result<()> {
+        for &(ref name, ref mention) in &self.invalid_mentions {
+            let artifact = artifacts.get(name).unwrap();
+            try!(write!(w, " - [{}] {} mentions {} which does not exist{}\n",
+                        utils::relative_path(&artifact.path, cwd).display(), name, mention,
+                        utils::did_you_mean(mention, artifacts.keys())));
+        }
+        for &(ref name, ref parent) in &self.draft_parents {
+            let artifact = artifacts.get(name).unwrap();
+            try!(write!(w, " - [{}] {} is {} but {} is still {}\n",
+                        utils::relative_path(&artifact.path, cwd).display(), name, APPROVED,
+                        parent, DRAFT));
+        }
+        for &(ref name, ref loc) in &self.dangling_locs {
+            try!(write!(w, " - [{}:{}] {} does not exist{}\n",
+                        utils::relative_path(&loc.path, cwd).display(), loc.line_col.0, name,
+                        utils::did_you_mean(name, artifacts.keys())));
+        }
+        Ok(())
+    }
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("status")
+        .about("display a summary of the artifacts and any errors in them")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+}
+
+/// analyze the artifacts, finding the totals and all errors
+/// partof: #SPC-status
+pub fn get_status(artifacts: &Artifacts) -> Status {
+    let mut status = Status::default();
+    for ty in TYPES.iter() {
+        status.totals.push(TypeTotal {
+            ty: ty.clone(),
+            count: 0,
+            completed: 0.,
+            tested: 0.,
+        });
+    }
+    let mut names: Vec<&ArtNameRc> = artifacts.keys().collect();
+    names.sort();
+    for name in names {
+        let artifact = artifacts.get(name).unwrap();
+        // obsolete artifacts are not counted in the totals
+        if !link::is_obsolete(artifact) {
+            let total = status.totals.iter_mut().find(|t| t.ty == artifact.ty).unwrap();
+            total.count += 1;
+            total.completed += artifact.completed;
+            total.tested += artifact.tested;
+        }
+        let mut partofs: Vec<_> = artifact.partof.iter().collect();
+        partofs.sort();
+        for p in partofs {
+            if !artifacts.contains_key(p) {
+                status.invalid_partof.push((name.clone(), p.clone()));
+            }
+        }
+        let mut mentions: Vec<_> = artifact.mentions.iter().collect();
+        mentions.sort();
+        for m in mentions {
+            if !artifacts.contains_key(m) {
+                status.invalid_mentions.push((name.clone(), m.clone()));
+            }
+        }
+        if artifact.completed < 0. || artifact.tested < 0. {
+            status.unresolved.push(name.clone());
+        }
+        // partof: #SPC-review-status
+        let mut suspect: Vec<_> = artifact.suspect.iter().collect();
+        suspect.sort();
+        for p in suspect {
+            status.suspect.push((name.clone(), p.clone()));
+        }
+        // partof: #SPC-artifact-status-draft
+        if artifact.ty == ArtType::SPC && artifact.status.as_ref().map_or(false, |s| s == APPROVED) {
+            let mut parents: Vec<_> = artifact.partof.iter()
+                .filter(|p| p.get_type() == ArtType::REQ)
+                .filter(|p| artifacts.get(p.clone())
+                    .map_or(false, |a| a.status.as_ref().map_or(false, |s| s == DRAFT)))
+                .collect();
+            parents.sort();
+            for p in parents {
+                status.draft_parents.push((name.clone(), p.clone()));
+            }
+        }
+    }
+    for total in status.totals.iter_mut() {
+        if total.count > 0 {
+            total.completed /= total.count as f32;
+            total.tested /= total.count as f32;
+        }
+    }
+    status
+}
+
+/// find the references in the code to artifacts that do not exist. The code
+/// is searched again since only the valid locations are kept when loading
+pub fn find_dangling_locs(artifacts: &Artifacts, settings: &Settings) -> Vec<(ArtName, Loc)> {
+    match locs::find_locs(settings) {
+        Ok(l) => locs::dangling_locs(artifacts, &l),
+        Err(e) => {
+            error!("{}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// display the status of the project
+pub fn do_status<W: Write>(w: &mut W, cwd: &Path, artifacts: &Artifacts, settings: &Settings)
+                           -> io::Result<()> {
+    let mut status = get_status(artifacts);
+    status.dangling_locs = find_dangling_locs(artifacts, settings);
+    try!(write!(w, "{} artifacts\n", artifacts.len()));
+    for total in &status.totals {
+        try!(write!(w, "  {:?} {:>5} | {:>3}% done {:>3}% tested\n",
+                    total.ty,
+                    total.count,
+                    (total.completed * 100.) as u8,
+                    (total.tested * 100.) as u8));
+    }
+    if status.num_errors() > 0 {
+        try!(write!(w, "{} errors:\n", status.num_errors()));
+        try!(status.write_errors(w, cwd, artifacts));
+    }
+    if status.num_warnings() > 0 {
+        try!(write!(w, "{} warnings:\n", status.num_warnings()));
+        try!(status.write_warnings(w, cwd, artifacts));
+    }
+    Ok(())
+}
+
+#[test]
+/// partof: #TST-artifact-status-draft
+fn test_status() {
+    let mut artifacts = core::load::load_toml_simple("\
+        [REQ-foo]\n\
+        status = 'draft'\n\
+        [REQ-bar]\n\
+        status = 'approved'\n\
+        [SPC-foo]\n\
+        status = 'approved'\n\
+        partof = 'REQ-bar'\n\
+        [SPC-bar]\n\
+        status = 'draft'\n\
+        [SPC-old]\n\
+        status = 'obsolete'\n");
+    core::link::do_links(&mut artifacts).unwrap();
+    let status = get_status(&artifacts);
+    let pairs: Vec<(&str, &str)> = status.draft_parents.iter()
+        .map(|&(ref n, ref p)| (n.raw.as_str(), p.raw.as_str()))
+        .collect();
+    assert_eq!(pairs, vec![("SPC-foo", "REQ-foo")]);
+    assert_eq!(status.num_warnings(), 1);
+    // the obsolete SPC-old is not counted
+    assert_eq!(status.totals[1].count, 3);
+    let mut out: Vec<u8> = Vec::new();
+    status.write_warnings(&mut out, Path::new(""), &artifacts).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               " - [test] SPC-foo is approved but REQ-foo is still draft\n");
+}
+
+#[test]
+fn test_dangling_locs() {
+    let dir = core::TempDir::new("rst-test-dangling");
+    let mut f = fs::File::create(dir.join("foo.rs")).unwrap();
+    f.write_all(b"// \x23SPC-foo-bar\n\n// \x23SPC-foo-baz and \x23SPC-dne\n").unwrap();
+    let mut settings = Settings::new();
+    settings.code_paths.push_back(dir.to_path_buf());
+    let mut artifacts = core::load::load_toml_simple("[SPC-foo-bar]\n");
+    core::link::do_links(&mut artifacts).unwrap();
+
+    let mut status = get_status(&artifacts);
+    assert_eq!(status.num_warnings(), 0);
+    status.dangling_locs = find_dangling_locs(&artifacts, &settings);
+    let names: Vec<&str> = status.dangling_locs.iter().map(|&(ref n, _)| n.raw.as_str()).collect();
+    assert_eq!(names, vec!["SPC-foo-baz", "SPC-dne"]);
+    assert_eq!(status.num_warnings(), 2);
+    let mut out: Vec<u8> = Vec::new();
+    status.write_warnings(&mut out, &dir, &artifacts).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               " - [foo.rs:3] SPC-foo-baz does not exist (did you mean SPC-foo-bar?)\n \
+                - [foo.rs:3] SPC-dne does not exist\n");
+}
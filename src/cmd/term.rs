@@ -0,0 +1,134 @@
+//! information about the terminal that rst is writing to, and paging
+//! output that does not fit in it
+
+use std::env;
+use std::ffi::OsString;
+use std::process::{Command, Stdio};
+
+use atty;
+
+use super::types::*;
+
+/// whether stdout is a terminal
+pub fn is_tty() -> bool {
+    atty::is(atty::Stream::Stdout)
+}
+
+/// whether to use color: only when writing to a terminal and `NO_COLOR`
+/// is not set (see https://no-color.org)
+pub fn use_color() -> bool {
+    color_allowed(env::var_os("NO_COLOR"), is_tty())
+}
+
+/// whether color is allowed given the value of `NO_COLOR`, where an empty
+/// value is the same as it not being set
+fn color_allowed(no_color: Option<OsString>, tty: bool) -> bool {
+    no_color.map_or(true, |v| v.is_empty()) && tty
+}
+
+/// the (columns, rows) of the terminal
+#[cfg(unix)]
+fn size() -> Option<(usize, usize)> {
+    use libc;
+    use std::mem;
+    let mut ws: libc::winsize = unsafe { mem::zeroed() };
+    let r = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+    if r == 0 && ws.ws_col > 0 {
+        Some((ws.ws_col as usize, ws.ws_row as usize))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn size() -> Option<(usize, usize)> {
+    None
+}
+
+fn env_usize(var: &str) -> Option<usize> {
+    env::var(var).ok().and_then(|v| v.parse::<usize>().ok()).and_then(|v| if v > 0 {
+        Some(v)
+    } else {
+        None
+    })
+}
+
+/// the number of columns of the terminal, from `COLUMNS` or the terminal
+/// itself. Output that is not written to a terminal has no width
+pub fn width() -> Option<usize> {
+    if !is_tty() {
+        return None;
+    }
+    env_usize("COLUMNS").or_else(|| size().map(|s| s.0))
+}
+
+/// the number of rows of the terminal, from `LINES` or the terminal itself
+pub fn height() -> Option<usize> {
+    if !is_tty() {
+        return None;
+    }
+    env_usize("LINES").or_else(|| size().map(|s| s.1))
+}
+
+/// write the output, through `$PAGER` (default `less`) if it is longer
+/// than the terminal. Setting `PAGER` to an empty string disables paging
+/// partof: #SPC-ls-pager
+pub fn page<W: Write>(w: &mut W, output: &[u8]) -> io::Result<()> {
+    let pager = env::var("PAGER").unwrap_or("less".to_string());
+    page_with(w, output, height(), &pager)
+}
+
+/// write the output through the pager if it has at least `height` lines
+fn page_with<W: Write>(w: &mut W, output: &[u8], height: Option<usize>, pager: &str)
+                       -> io::Result<()> {
+    let lines = output.iter().filter(|b| **b == b'\n').count();
+    let mut args = pager.split_whitespace();
+    let program = match (height, args.next()) {
+        (Some(h), Some(p)) if lines >= h => p,
+        _ => return w.write_all(output),
+    };
+    let mut cmd = Command::new(program);
+    cmd.args(&args.collect::<Vec<_>>()).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        // display colors and quit if the output fits after all
+        cmd.env("LESS", "FRX");
+    }
+    let mut child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("could not run the pager `{}`: {}", pager, e);
+            return w.write_all(output);
+        }
+    };
+    if let Err(e) = child.stdin.take().unwrap().write_all(output) {
+        // the user quit the pager before reading everything
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            return Err(e);
+        }
+    }
+    try!(child.wait());
+    Ok(())
+}
+
+#[test]
+/// partof: #TST-ls-pager
+fn test_color_and_page() {
+    assert!(color_allowed(None, true));
+    assert!(color_allowed(Some(OsString::new()), true));
+    assert!(!color_allowed(Some(OsString::from("1")), true));
+    assert!(!color_allowed(None, false));
+
+    let output = b"a\nb\nc\n";
+    let written = |height: Option<usize>, pager: &str| {
+        let mut w: Vec<u8> = Vec::new();
+        page_with(&mut w, output, height, pager).unwrap();
+        w
+    };
+    // output which fits, which is not to a terminal or with no pager is
+    // written as it is
+    assert_eq!(written(Some(10), "less"), output);
+    assert_eq!(written(None, "less"), output);
+    assert_eq!(written(Some(2), ""), output);
+    // so is output whose pager cannot be run
+    assert_eq!(written(Some(2), "rst-pager-which-does-not-exist -R"), output);
+}
@@ -1,5 +1,41 @@
 /// test cmdline modules
 
+use super::types::*;
+
 mod test_cmd;
 mod test_tutorial;
+mod test_export;
+mod test_mv;
+mod test_rm;
+mod test_set_status;
+mod test_review;
+mod test_approve;
+mod test_tree;
+
+mod test_output;
+mod test_watch;
+mod test_complete;
+
+/// create a project in a new directory in the temp directory which loads
+/// the artifacts in `reqs` and the code in `src`. The directory is removed
+/// when the returned value is dropped
+fn create_project(name: &str, files: &[(&str, &str)]) -> core::TempDir {
+    let dir = core::TempDir::new(name);
+    for d in &[".rst", "reqs", "src"] {
+        fs::create_dir_all(dir.join(d)).unwrap();
+    }
+    let settings = (".rst/settings.toml", "[settings]\n\
+                                           artifact_paths = ['{repo}/reqs']\n\
+                                           code_paths = ['{repo}/src']\n");
+    for &(f, t) in files.iter().chain(Some(&settings)) {
+        fs::File::create(dir.join(f)).unwrap().write_all(t.as_bytes()).unwrap();
+    }
+    dir
+}
 
+/// read a file of a project
+fn read(dir: &Path, path: &str) -> String {
+    let mut text = String::new();
+    fs::File::open(dir.join(path)).unwrap().read_to_string(&mut text).unwrap();
+    text
+}
@@ -0,0 +1,47 @@
+use super::super::types::*;
+use super::super::approve::*;
+use super::super::check;
+use core::types::Approvals;
+
+#[test]
+/// partof: #TST-approve-cmd
+fn test_approve() {
+    let text = "\
+        [REQ-foo]\ntext = 'foo'\n\
+        [REQ-bar]\ntext = 'bar'\n\
+        [REQ-old]\nstatus = 'obsolete'\n\
+        [SPC-foo]\n";
+    let mut artifacts = core::load::load_toml_simple(text);
+    core::link::do_links(&mut artifacts).unwrap();
+    let mut approvals = Approvals::new();
+    let cmd = |n: &str| ApproveCmd { name: ArtName::from_str(n).unwrap(), by: "Jane".into() };
+    assert_eq!(approve_name(&artifacts, &mut approvals, &cmd("REQ-fo"), "now").unwrap_err(),
+               "REQ-fo does not exist (did you mean REQ-foo?)");
+    let (name, approval) = approve_name(&artifacts, &mut approvals, &cmd("req-foo"), "now")
+        .unwrap();
+    assert_eq!(name.raw, "REQ-foo");
+    assert_eq!(approval.by, "Jane");
+    approve_name(&artifacts, &mut approvals, &cmd("REQ-bar"), "then").unwrap();
+
+    // the REQ parent and the obsolete REQ-old do not need to be approved
+    let types = [ArtType::REQ];
+    core::review::set_approvals(&mut artifacts, &approvals);
+    assert!(check::check_approvals(&artifacts, &types).is_empty());
+    assert_eq!(check::check_approvals(&artifacts, &[ArtType::SPC]),
+               vec!["SPC-foo is not approved".to_string()]);
+
+    let mut artifacts = core::load::load_toml_simple(&text.replace("'bar'", "'changed'"));
+    core::link::do_links(&mut artifacts).unwrap();
+    core::review::set_approvals(&mut artifacts, &approvals);
+    assert_eq!(check::check_approvals(&artifacts, &types),
+               vec!["REQ-bar changed since it was approved by Jane at then".to_string()]);
+    let mut out: Vec<u8> = Vec::new();
+    let settings = Settings::new();
+    assert!(!check::do_check(&mut out, Path::new(""), &artifacts, &settings, &[], &types)
+        .unwrap());
+    assert_eq!(String::from_utf8(out).unwrap(),
+               "check failed: 0 errors, 0 threshold failures, 1 approval failures\n \
+                - REQ-bar changed since it was approved by Jane at then\n");
+    let mut out: Vec<u8> = Vec::new();
+    assert!(check::do_check(&mut out, Path::new(""), &artifacts, &settings, &[], &[]).unwrap());
+}
@@ -3,6 +3,7 @@ use super::super::types::*;
 use super::super::matches::*;
 use super::super::ls;
 
+use std::ffi::OsString;
 use std::thread;
 use std::time;
 
@@ -45,6 +46,51 @@ fn test_get_matches() {
 }
 
 
+#[test]
+/// partof: #TST-ls-views
+fn test_view_matches() {
+    let mut settings = Settings::new();
+    settings.views.insert("todo".to_string(), core::View {
+        search: Some("REQ".to_string()),
+        pattern: Some("NT".to_string()),
+        completed: Some("<".to_string()),
+        fields: Some("TD".to_string()),
+        reverse: true,
+        ..core::View::default()
+    });
+    let get = |args: &[&str]| {
+        let args: Vec<OsString> = args.iter().map(|a| OsString::from(a)).collect();
+        let matches = get_matches(args.clone()).unwrap();
+        ls::get_view_matches(&args, &matches, &settings)
+            .map(|m| m.map(|m| ls::get_ls_cmd(m.subcommand_matches("ls").unwrap()).unwrap()))
+    };
+    assert!(get(&["rst", "ls", "REQ"]).unwrap().is_none());
+    assert!(get(&["rst", "ls", "@nope"]).is_err());
+
+    let (search, fmt_set, search_set) = get(&["rst", "ls", "@todo"]).unwrap().unwrap();
+    assert_eq!(search, "REQ");
+    assert!(fmt_set.text && fmt_set.path && !fmt_set.parts && fmt_set.reverse);
+    assert!(search_set.name && search_set.text && !search_set.parts);
+    assert_eq!(search_set.completed, PercentSearch { lt: true, perc: 0 });
+
+    // the cmdline takes precedence
+    let (_, fmt_set, search_set) = get(&["rst", "ls", "-c", "50", "@todo", "-T", "-p", "P"])
+        .unwrap().unwrap();
+    assert!(fmt_set.text && fmt_set.path);
+    assert!(!search_set.name && search_set.parts);
+    assert_eq!(search_set.completed, PercentSearch { lt: false, perc: 50 });
+
+    // only the search is replaced when an option has the same value
+    let args: Vec<OsString> = ["rst", "ls", "--status", "@todo", "@todo"].iter()
+        .map(|a| OsString::from(a))
+        .collect();
+    let matches = get_matches(args.clone()).unwrap();
+    let matches = ls::get_view_matches(&args, &matches, &settings).unwrap().unwrap();
+    let ls = matches.subcommand_matches("ls").unwrap();
+    assert_eq!(ls.value_of("search"), Some("REQ"));
+    assert_eq!(ls.value_of("status"), Some("@todo"));
+}
+
 #[test]
 fn test_ls() {
     let (mut fmt_set, mut search_set, settings) = (FmtSettings::default(),
@@ -112,8 +158,8 @@ text = 'tst for foo_bar'
               &artifacts,
               &fmt_set,
               &search_set,
-              &settings);
-    let expected = b"\x1b[1m|  | DONE TEST | ARTIFACT NAME                                 | PARTS   | DEFINED   \n\x1b[0m|\x1b[1;34mD\x1b[0m\x1b[1;33m-\x1b[0m| \x1b[1;34m100\x1b[0m%  \x1b[1;33m50\x1b[0m% | \x1b[1;4;34mreq-foo\x1b[0m                                       | \x1b[34mSPC-foo\x1b[0m | ../../reqs/foo.toml \n";
+              &settings).unwrap();
+    let expected = b"\x1b[1m|  | DONE TEST | ARTIFACT NAME | PARTS   | DEFINED   \n\x1b[0m|\x1b[1;34mD\x1b[0m\x1b[1;33m-\x1b[0m| \x1b[1;34m100\x1b[0m%  \x1b[1;33m50\x1b[0m% | \x1b[1;4;34mreq-foo\x1b[0m       | \x1b[34mSPC-foo\x1b[0m | ../../reqs/foo.toml \n";
     assert_eq!(vb(expected), w);
 
     // do default list with color disabled
@@ -125,9 +171,9 @@ text = 'tst for foo_bar'
               &artifacts,
               &fmt_set,
               &search_set,
-              &settings);
+              &settings).unwrap();
     debug_bytes(&w);
-    let expected = b"|  | DONE TEST | ARTIFACT NAME                                 | PARTS   | DEFINED   \n|D-| 100%  50% | req-foo                                       | SPC-foo | ../../reqs/foo.toml \n";
+    let expected = b"|  | DONE TEST | ARTIFACT NAME | PARTS   | DEFINED   \n|D-| 100%  50% | req-foo       | SPC-foo | ../../reqs/foo.toml \n";
     assert_eq!(vb(expected), w);
 
     // ls all fields
@@ -147,9 +193,100 @@ text = 'tst for foo_bar'
               &artifacts,
               &fmt_set,
               &search_set,
-              &settings);
-    let expected = b"\x1b[1m|  | DONE TEST | ARTIFACT NAME                                 | PARTS   | PARTOF   | IMPLEMENTED   | DEFINED   | TEXT\n\x1b[0m|\x1b[1;34mD\x1b[0m\x1b[1;33m-\x1b[0m| \x1b[1;34m100\x1b[0m%  \x1b[1;33m50\x1b[0m% | \x1b[1;4;34mREQ-foo\x1b[0m                                       | \x1b[34mSPC-foo\x1b[0m | \x1b[34mREQ\x1b[0m | ../../reqs/foo.toml | req for foo \n|\x1b[1;34mD\x1b[0m\x1b[1;33m-\x1b[0m| \x1b[1;34m100\x1b[0m%  \x1b[1;33m50\x1b[0m% | \x1b[1;4;34mSPC\x1b[0m                                           | \x1b[34mSPC-foo\x1b[0m |  | PARENT | AUTO \n";
+              &settings).unwrap();
+    let expected = b"\x1b[1m|  | DONE TEST | ARTIFACT NAME | PARTS   | PARTOF   | IMPLEMENTED   | DEFINED   | TEXT\n\x1b[0m|\x1b[1;34mD\x1b[0m\x1b[1;33m-\x1b[0m| \x1b[1;34m100\x1b[0m%  \x1b[1;33m50\x1b[0m% | \x1b[1;4;34mREQ-foo\x1b[0m       | \x1b[34mSPC-foo\x1b[0m | \x1b[34mREQ\x1b[0m | ../../reqs/foo.toml | req for foo \n|\x1b[1;34mD\x1b[0m\x1b[1;33m-\x1b[0m| \x1b[1;34m100\x1b[0m%  \x1b[1;33m50\x1b[0m% | \x1b[1;4;34mSPC\x1b[0m           | \x1b[34mSPC-foo\x1b[0m |  | PARENT | AUTO \n";
     assert_eq!(vb(expected), w);
 
+    // the text is truncated to fit in the width
+    // #TST-ls-layout
+    w.clear();
+    let mut fmt_set = FmtSettings::default();
+    fmt_set.text = true;
+    fmt_set.width = Some(40);
+    ls::do_ls(&mut w,
+              &cwd,
+              "tst-foo_bar",
+              &artifacts,
+              &fmt_set,
+              &SearchSettings::default(),
+              &settings).unwrap();
+    let expected = "|  | DONE TEST | ARTIFACT NAME | TEXT\n|--|   0%   0% | tst-foo_bar   | tst f…\n";
+    assert_eq!(String::from_utf8(w.clone()).unwrap(), expected);
+
+    // the artifacts that exist are still written when some names do not
+    w.clear();
+    let mut fmt_set = FmtSettings::default();
+    fmt_set.text = true;
+    let err = ls::do_ls(&mut w,
+                        &cwd,
+                        "tst-foo_bar,tst-dne",
+                        &artifacts,
+                        &fmt_set,
+                        &SearchSettings::default(),
+                        &settings)
+        .unwrap_err();
+    assert!(err.starts_with("The following artifacts do not exist: tst-dne"), "{}", err);
+    assert!(String::from_utf8(w.clone()).unwrap().contains("| tst-foo_bar "));
+
     // debug_bytes(&w);
 }
+
+#[test]
+/// partof: #TST-ls-sort
+fn test_ls_sort_group() {
+    let mut artifacts = core::load::load_toml_simple(r"
+[REQ-a]
+[REQ-b]
+[SPC-a-x]
+[SPC-b]
+");
+    core::link::do_links(&mut artifacts).unwrap();
+    let set = |artifacts: &mut Artifacts, n: &str, completed: f32, path: &str| {
+        let a = artifacts.get_mut(&ArtName::from_str(n).unwrap()).unwrap();
+        a.completed = completed;
+        a.path = PathBuf::from(path);
+    };
+    set(&mut artifacts, "REQ-a", 0.5, "reqs/b.toml");
+    set(&mut artifacts, "REQ-b", 0.1, "reqs/a.toml");
+    set(&mut artifacts, "SPC-a-x", 1., "reqs/a.toml");
+    set(&mut artifacts, "SPC-b", 0.2, "reqs/b.toml");
+    let raw = |names: &[ArtNameRc]| -> Vec<String> {
+        names.iter().map(|n| n.raw.clone()).collect()
+    };
+    let all: Vec<ArtNameRc> = ["REQ-a", "REQ-b", "SPC-a-x", "SPC-b"].iter()
+        .map(|n| ArtNameRc::from_str(n).unwrap())
+        .collect();
+
+    let mut fmt_set = FmtSettings::default();
+    let mut names = all.clone();
+    fmt_set.sort = SortBy::Completed;
+    ls::sort_names(&mut names, &artifacts, &fmt_set);
+    assert_eq!(raw(&names), vec!["REQ-b", "SPC-b", "REQ-a", "SPC-a-x"]);
+
+    // a value that is not a number does not break the sort
+    let mut nan = artifacts.clone();
+    set(&mut nan, "REQ-b", ::std::f32::NAN, "reqs/a.toml");
+    let mut names = all.clone();
+    ls::sort_names(&mut names, &nan, &fmt_set);
+    assert_eq!(raw(&names), vec!["SPC-b", "REQ-a", "SPC-a-x", "REQ-b"]);
+
+    // ties keep the name order, unless reversed
+    fmt_set.sort = SortBy::Path;
+    fmt_set.reverse = true;
+    ls::sort_names(&mut names, &artifacts, &fmt_set);
+    assert_eq!(raw(&names), vec!["SPC-b", "REQ-a", "SPC-a-x", "REQ-b"]);
+
+    let cwd = PathBuf::from("");
+    let groups = ls::group_names(all.clone(), &artifacts, &cwd, GroupBy::Type);
+    assert_eq!(groups.iter().map(|g| (g.0.clone(), raw(&g.1))).collect::<Vec<_>>(),
+               vec![("REQ".to_string(), vec!["REQ-a".to_string(), "REQ-b".to_string()]),
+                    ("SPC".to_string(), vec!["SPC-a-x".to_string(), "SPC-b".to_string()])]);
+    let groups = ls::group_names(all.clone(), &artifacts, &cwd, GroupBy::File);
+    assert_eq!(groups.iter().map(|g| g.0.clone()).collect::<Vec<_>>(),
+               vec!["reqs/a.toml", "reqs/b.toml"]);
+    let groups = ls::group_names(all.clone(), &artifacts, &cwd, GroupBy::Parent);
+    assert_eq!(groups.iter().map(|g| (g.0.clone(), g.1.len())).collect::<Vec<_>>(),
+               vec![("REQ".to_string(), 2), ("SPC".to_string(), 1),
+                    ("SPC-a".to_string(), 1)]);
+    assert_eq!(ls::group_names(all.clone(), &artifacts, &cwd, GroupBy::Nothing).len(), 1);
+}
@@ -0,0 +1,95 @@
+use super::super::types::*;
+use super::super::complete::*;
+
+fn words(cmdline: &str) -> Vec<String> {
+    cmdline.split(' ').map(|w| w.to_string()).collect()
+}
+
+fn names() -> Vec<String> {
+    vec!["REQ-foo".to_string(), "REQ-foo-bar".to_string(), "SPC-foo".to_string()]
+}
+
+#[test]
+fn test_get_args() {
+    let parent = App::new("rst")
+        .arg(Arg::with_name("quiet").short("q").long("quiet").global(true));
+    let app = SubCommand::with_name("new")
+        .arg(Arg::with_name("partof")
+                 .short("p")
+                 .long("partof")
+                 .value_name("NAMES")
+                 .takes_value(true))
+        .arg(Arg::with_name("format")
+                 .long("format")
+                 .possible_values(&["md", "rst"])
+                 .takes_value(true))
+        .arg(Arg::with_name("secret").long("secret").hidden(true))
+        .arg(Arg::with_name("name").required(true))
+        .subcommand(SubCommand::with_name("sub"))
+        .subcommand(SubCommand::with_name("__hidden").setting(AS::Hidden));
+    let args = get_args(&app, Some(&parent));
+    assert_eq!(args.subcommands, vec!["sub", "help"]);
+    assert_eq!(args.options.iter().map(|o| o.names.clone()).collect::<Vec<_>>(),
+               vec![vec!["-q", "--quiet"], vec!["-p", "--partof"], vec!["--format"],
+                    vec!["-h", "--help"]]);
+    assert_eq!(args.options[0].value_name, None);
+    assert_eq!(args.options[1].value_name, Some("NAMES".to_string()));
+    assert_eq!(args.options[2].value_name, Some("format".to_string()));
+    assert_eq!(args.options[2].values, vec!["md", "rst"]);
+    assert_eq!(args.positionals[0].names, vec!["name"]);
+
+    // the version is only a flag of rst itself
+    let args = get_args(&parent, None);
+    assert_eq!(args.options.iter().map(|o| o.names.clone()).collect::<Vec<_>>(),
+               vec![vec!["-q", "--quiet"], vec!["-h", "--help"], vec!["-V", "--version"]]);
+    assert!(args.subcommands.is_empty());
+}
+
+#[test]
+/// partof: #TST-completions
+fn test_complete() {
+    let no_names = || -> Vec<String> { panic!("names should not be loaded") };
+    assert_eq!(complete(&words("rst ls"), 1, no_names), vec!["ls", "lsp"]);
+    assert!(complete(&words("rst ls --"), 2, no_names).contains(&"--reverse".to_string()));
+    assert_eq!(complete(&words("rst export m"), 2, no_names), vec!["mermaid"]);
+    assert_eq!(complete(&words("rst ls --format c"), 3, no_names), vec!["csv"]);
+    assert_eq!(complete(&words("rst completions f"), 2, no_names), vec!["fish"]);
+
+    // search fields are completed one letter at a time
+    let fields = complete(&words("rst ls -p NT"), 3, no_names);
+    assert_eq!(fields.len(), 6);
+    assert!(fields.iter().all(|f| f.starts_with("NT") && f.len() == 3));
+    assert!(!fields.contains(&"NTN".to_string()));
+
+    // artifact names
+    assert_eq!(complete(&words("rst ls req-f"), 2, names), vec!["REQ-foo", "REQ-foo-bar"]);
+    assert_eq!(complete(&words("rst ls -l SP"), 3, names), vec!["SPC-foo"]);
+    assert_eq!(complete(&words("rst watch -i 100 tree R"), 5, names),
+               vec!["REQ-foo", "REQ-foo-bar"]);
+    assert_eq!(complete(&words("rst new -p REQ-foo,S SPC-bar"), 3, names),
+               vec!["REQ-foo,SPC-foo"]);
+    // the second argument of mv is a new name, but still completed
+    assert_eq!(complete(&words("rst mv REQ-foo-bar R"), 3, names),
+               vec!["REQ-foo", "REQ-foo-bar"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_cache() {
+    use std::os::unix::fs::symlink;
+    let dir = core::TempDir::new("rst-test-cache");
+    let victim = dir.join("victim");
+    fs::File::create(&victim).unwrap().write_all(b"keep").unwrap();
+
+    // a link planted at the path is replaced, not written through
+    let path = dir.join("cache").join("complete.json");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    symlink(&victim, &path).unwrap();
+    write_cache(&path, "names").unwrap();
+    assert!(!fs::symlink_metadata(&path).unwrap().file_type().is_symlink());
+    let read = |p: &Path| core::save::read_file(p).unwrap();
+    assert_eq!(read(&path), "names");
+    assert_eq!(read(&victim), "keep");
+    // and no temporary file is left behind
+    assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+}
@@ -0,0 +1,59 @@
+
+use super::super::types::*;
+use super::super::export::*;
+
+#[test]
+/// partof: #TST-export
+fn test_export() {
+    let mut artifacts = core::load::load_toml_simple(r"
+[REQ-foo]
+[REQ-foo-bar]
+[SPC-foo]
+[SPC-baz]
+partof = 'REQ-foo'
+");
+    for (n, a) in artifacts.iter_mut() {
+        if n.as_ref() == &ArtName::from_str("spc-foo").unwrap() {
+            a.loc = Some(Loc::fake());
+        }
+    }
+    core::link::do_links(&mut artifacts).unwrap();
+    let req_foo = ArtName::from_str("REQ-foo").unwrap();
+    assert_eq!(get_link_kind(&ArtName::from_str("REQ-foo-bar").unwrap(), &req_foo),
+               LinkKind::Parent);
+    assert_eq!(get_link_kind(&ArtName::from_str("SPC-foo").unwrap(), &req_foo),
+               LinkKind::Named);
+    assert_eq!(get_link_kind(&ArtName::from_str("SPC-baz").unwrap(), &req_foo),
+               LinkKind::Explicit);
+
+    // only the searched for artifacts and the links between them are exported
+    let mut w: Vec<u8> = Vec::new();
+    do_export(&mut w, ExportFormat::Dot, "REQ-foo, SPC-[foo, baz]", &artifacts,
+              &SearchSettings::new()).unwrap();
+    let expected = "\
+digraph artifacts {
+    node [shape=box, style=filled];
+    \"REQ-FOO\" [label=\"REQ-foo\\n33% 0%\", fillcolor=lightcoral];
+    \"SPC-BAZ\" [label=\"SPC-baz\\n0% 0%\", fillcolor=lightcoral];
+    \"SPC-FOO\" [label=\"SPC-foo\\n100% 0%\", fillcolor=lightskyblue];
+    \"REQ-FOO\" -> \"SPC-BAZ\" [style=solid];
+    \"REQ-FOO\" -> \"SPC-FOO\" [style=dashed];
+}
+";
+    assert_eq!(String::from_utf8(w).unwrap(), expected);
+
+    let mut w: Vec<u8> = Vec::new();
+    do_export(&mut w, ExportFormat::Mermaid, "REQ-foo, REQ-foo-bar", &artifacts,
+              &SearchSettings::new()).unwrap();
+    let expected = "\
+graph TD
+    n0[\"REQ-foo<br/>33% 0%\"]:::red
+    n1[\"REQ-foo-bar<br/>0% 0%\"]:::red
+    n0 ==> n1
+    classDef green fill:#98fb98;
+    classDef blue fill:#87cefa;
+    classDef yellow fill:#f0e68c;
+    classDef red fill:#f08080;
+";
+    assert_eq!(String::from_utf8(w).unwrap(), expected);
+}
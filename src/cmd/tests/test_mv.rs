@@ -0,0 +1,197 @@
+
+use super::super::types::*;
+use super::super::mv::*;
+use super::super::new;
+
+fn load(text: &str) -> Artifacts {
+    let mut artifacts = core::load::load_toml_simple(text);
+    core::link::do_links(&mut artifacts).unwrap();
+    artifacts
+}
+
+fn mv_cmd(old: &str, new: &str, recursive: bool) -> MvCmd {
+    MvCmd {
+        old: ArtName::from_str(old).unwrap(),
+        new: ArtName::from_str(new).unwrap(),
+        recursive: recursive,
+        dry_run: true,
+    }
+}
+
+#[test]
+/// partof: #TST-mv
+fn test_get_renames() {
+    let artifacts = load("
+[REQ-foo]
+[REQ-foo-bar]
+[REQ-foo-bar-baz]
+[REQ-other]
+[SPC-foo]
+");
+    let name = |s| ArtName::from_str(s).unwrap();
+    let renames = get_renames(&mv_cmd("SPC-foo", "SPC-new", false), &artifacts).unwrap();
+    assert_eq!(renames.len(), 1);
+    assert_eq!(renames[&name("SPC-foo")].raw, "SPC-new");
+
+    // children can only be renamed recursively
+    assert!(get_renames(&mv_cmd("REQ-foo", "REQ-new", false), &artifacts).is_err());
+    let renames = get_renames(&mv_cmd("REQ-foo", "REQ-new", true), &artifacts).unwrap();
+    assert_eq!(renames.len(), 3);
+    assert_eq!(renames[&name("REQ-foo-bar-baz")].raw, "REQ-new-bar-baz");
+
+    // the new names must not exist
+    assert!(get_renames(&mv_cmd("REQ-foo-bar-baz", "REQ-other", false), &artifacts).is_err());
+    assert!(get_renames(&mv_cmd("REQ-dne", "REQ-new", false), &artifacts).is_err());
+    assert!(get_renames(&mv_cmd("REQ-foo", "REQ-foo", true), &artifacts).is_err());
+}
+
+#[test]
+/// partof: #TST-new
+fn test_choose_file() {
+    let mut artifacts = core::load::load_toml_simple("
+[REQ-foo]
+[REQ-foo-a]
+[REQ-bar-a]
+[REQ-bar-b]
+[REQ-baz-a]
+");
+    let set_path = |artifacts: &mut Artifacts, n: &str, p: &str| {
+        artifacts.get_mut(&ArtName::from_str(n).unwrap()).unwrap().path = PathBuf::from(p);
+    };
+    set_path(&mut artifacts, "REQ-foo", "foo.toml");
+    set_path(&mut artifacts, "REQ-foo-a", "other.toml");
+    set_path(&mut artifacts, "REQ-bar-a", "bar.toml");
+    set_path(&mut artifacts, "REQ-bar-b", "bar.toml");
+    set_path(&mut artifacts, "REQ-baz-a", "baz.toml");
+    core::link::do_links(&mut artifacts).unwrap();
+    let choose = |n| new::choose_file(&ArtName::from_str(n).unwrap(), &artifacts);
+    // the parent's file is used first
+    assert_eq!(choose("REQ-foo-b"), Some(PathBuf::from("foo.toml")));
+    assert_eq!(choose("REQ-foo-a-x"), Some(PathBuf::from("other.toml")));
+    // then the siblings' files
+    assert_eq!(choose("REQ-bar-c"), Some(PathBuf::from("bar.toml")));
+    assert_eq!(choose("REQ-bar-c-d"), Some(PathBuf::from("bar.toml")));
+    assert_eq!(choose("REQ-new"), None);
+}
+
+#[test]
+fn test_do_mv() {
+    let dir = super::create_project("rst-test-mv", &[
+        ("reqs/foo.toml", "[REQ-foo]\ntext = 'the foo'\n\n\
+                           [SPC-bar]\npartof = 'REQ-foo'  # keep me\n"),
+        ("src/foo.rs", "/// \x23SPC-bar\nfn bar() {}\n")]);
+    let (artifacts, mut settings) = core::load_path(&dir.join(".rst")).unwrap();
+    settings.color = false;
+
+    // a dry run only shows the diff
+    let mut out: Vec<u8> = Vec::new();
+    let cmd = mv_cmd("SPC-bar", "SPC-baz", false);
+    let changes = do_mv(&mut out, &dir, &artifacts, &settings, &cmd).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+--- reqs/foo.toml
++++ reqs/foo.toml
+@@ -2 +2 @@
+ text = 'the foo'
+ \n-[SPC-bar]
++[SPC-baz]
+ partof = 'REQ-foo'  # keep me
+--- src/foo.rs
++++ src/foo.rs
+@@ -1 +1 @@
+-/// \x23SPC-bar
++/// \x23SPC-baz
+ fn bar() {}
+");
+    assert_eq!(super::read(&dir, "src/foo.rs"), "/// \x23SPC-bar\nfn bar() {}\n");
+
+    let mut cmd = mv_cmd("SPC-bar", "SPC-baz", false);
+    cmd.dry_run = false;
+    let mut out: Vec<u8> = Vec::new();
+    do_mv(&mut out, &dir, &artifacts, &settings, &cmd).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "renamed 1 artifacts in 2 files\n");
+    assert_eq!(super::read(&dir, "reqs/foo.toml"),
+               "[REQ-foo]\ntext = 'the foo'\n\n[SPC-baz]\npartof = 'REQ-foo'  # keep me\n");
+    assert_eq!(super::read(&dir, "src/foo.rs"), "/// \x23SPC-baz\nfn bar() {}\n");
+
+    // the partof that names the artifact is rewritten
+    let (artifacts, settings) = core::load_path(&dir.join(".rst")).unwrap();
+    let mut cmd = mv_cmd("REQ-foo", "REQ-new", false);
+    cmd.dry_run = false;
+    do_mv(&mut Vec::new(), &dir, &artifacts, &settings, &cmd).unwrap();
+    assert_eq!(super::read(&dir, "reqs/foo.toml"),
+               "[REQ-new]\ntext = 'the foo'\n\n\
+                [SPC-baz]\npartof = \"REQ-new\"  # keep me\n");
+    let (artifacts, _) = core::load_path(&dir.join(".rst")).unwrap();
+    let baz = &artifacts[&ArtName::from_str("SPC-baz").unwrap()];
+    assert!(baz.partof.contains(&ArtName::from_str("REQ-new").unwrap()));
+}
+
+#[test]
+fn test_verify_changes() {
+    let dir = super::create_project("rst-test-mv-verify", &[
+        ("reqs/foo.toml", "[REQ-foo]\n[SPC-bar]\npartof = 'REQ-foo'\n")]);
+    let (artifacts, _) = core::load_path(&dir.join(".rst")).unwrap();
+    let path = dir.join("reqs/foo.toml");
+    let original = super::read(&dir, "reqs/foo.toml");
+    let mut renames = HashMap::new();
+    renames.insert(ArtName::from_str("SPC-bar").unwrap(), ArtName::from_str("SPC-baz").unwrap());
+    let change = |new: &str| vec![(path.clone(), original.clone(), new.to_string())];
+
+    assert!(verify_changes(&artifacts, &renames,
+                           &change("[REQ-foo]\n[SPC-baz]\npartof = 'REQ-foo'\n")).is_ok());
+    assert_eq!(verify_changes(&artifacts, &renames, &change("[REQ-foo]\n[SPC-baz]\n"))
+                   .unwrap_err(),
+               "renaming would change the links of SPC-baz");
+    assert!(verify_changes(&artifacts, &renames, &change("[REQ-foo\n")).unwrap_err()
+                .starts_with("renamed artifacts would not load: "));
+}
+
+#[test]
+fn test_do_new() {
+    let dir = super::create_project("rst-test-new", &[
+        ("reqs/foo.toml", "[REQ-foo]\ntext = 'the foo'\n")]);
+    let (artifacts, mut settings) = core::load_path(&dir.join(".rst")).unwrap();
+    let cmd = |name: &str, partof: &str, file: Option<&str>| new::NewCmd {
+        name: ArtName::from_str(name).unwrap(),
+        partof: ArtNames::from_str(partof).unwrap(),
+        text: "the bar\nin two lines\n".to_string(),
+        file: file.map(|f| dir.join(f)),
+    };
+
+    // the artifact is added to its parent's file
+    let path = new::do_new(&artifacts, &settings, &cmd("REQ-foo-bar", "SPC-dne", None));
+    assert_eq!(path.unwrap_err(), "partof SPC-dne does not exist");
+    let path = new::do_new(&artifacts, &settings, &cmd("REQ-foo-bar", "REQ-foo", None)).unwrap();
+    assert_eq!(path, dir.join("reqs/foo.toml"));
+    assert_eq!(super::read(&dir, "reqs/foo.toml"), "\
+[REQ-foo]
+text = 'the foo'
+
+[REQ-foo-bar]
+partof = \"REQ-foo\"
+text = '''
+the bar
+in two lines
+'''
+");
+    assert!(new::do_new(&artifacts, &settings, &cmd("REQ-foo", "", None)).unwrap_err()
+                .starts_with("REQ-foo already exists in "));
+    assert!(new::do_new(&artifacts, &settings, &cmd("REQ-other", "", None)).is_err());
+
+    // a new file must be where artifacts are loaded from
+    new::do_new(&artifacts, &settings, &cmd("REQ-other", "", Some("reqs/other.toml"))).unwrap();
+    assert_eq!(super::read(&dir, "reqs/other.toml"),
+               "[REQ-other]\ntext = '''\nthe bar\nin two lines\n'''\n");
+    assert!(new::do_new(&artifacts, &settings, &cmd("REQ-other", "", Some("src/other.toml")))
+                .unwrap_err()
+                .ends_with("is not in a directory that artifacts are loaded from"));
+    assert!(new::do_new(&artifacts, &settings, &cmd("REQ-other", "", Some("reqs/other.md")))
+                .unwrap_err()
+                .ends_with("is not a toml file"));
+    // the directory only has to be in the settings, even if it is empty
+    fs::create_dir(dir.join("docs")).unwrap();
+    settings.paths.push_back(dir.join("docs"));
+    new::do_new(&artifacts, &settings, &cmd("REQ-docs", "", Some("docs/new.toml"))).unwrap();
+    assert!(super::read(&dir, "docs/new.toml").starts_with("[REQ-docs]\n"));
+}
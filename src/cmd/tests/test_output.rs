@@ -0,0 +1,61 @@
+use super::super::types::*;
+use super::super::output::*;
+
+#[test]
+/// partof: #TST-ls-format
+fn test_output() {
+    let mut artifacts = core::load::load_toml_simple(r#"
+[REQ-foo]
+text = "a \"quoted\", multi\nline\ttext"
+[SPC-foo]
+"#);
+    for (n, a) in artifacts.iter_mut() {
+        a.path = PathBuf::from("/repo/reqs/foo.toml");
+        if n.as_ref() == &ArtName::from_str("spc-foo").unwrap() {
+            a.loc = Some(Loc {
+                path: PathBuf::from("/repo/src/foo.rs"),
+                line_col: (3, 4),
+            });
+        }
+    }
+    core::link::do_links(&mut artifacts).unwrap();
+    let mut names: Vec<ArtNameRc> = artifacts.keys()
+        .filter(|n| n.as_ref() != &ArtName::from_str("REQ").unwrap()
+                    && n.as_ref() != &ArtName::from_str("SPC").unwrap())
+        .cloned()
+        .collect();
+    names.sort();
+    let cwd = PathBuf::from("/repo");
+    let write = |format: OutputFormat| -> String {
+        let mut w: Vec<u8> = Vec::new();
+        write_output(&mut w, &format, &names, &artifacts, &cwd).unwrap();
+        String::from_utf8(w).unwrap()
+    };
+
+    assert_eq!(write(OutputFormat::Json), "[
+  {\"name\": \"REQ-foo\", \"type\": \"REQ\", \"path\": \"reqs/foo.toml\", \
+\"completed\": 100, \"tested\": 0, \"loc_path\": \"\", \"loc_line\": null, \"loc_col\": null, \
+\"parts\": [\"SPC-foo\"], \"partof\": [\"REQ\"], \
+\"text\": \"a \\\"quoted\\\", multi\\nline\\ttext\"},
+  {\"name\": \"SPC-foo\", \"type\": \"SPC\", \"path\": \"reqs/foo.toml\", \
+\"completed\": 100, \"tested\": 0, \"loc_path\": \"src/foo.rs\", \"loc_line\": 3, \"loc_col\": 4, \
+\"parts\": [], \"partof\": [\"REQ-foo\", \"SPC\"], \"text\": \"\"}
+]
+");
+    assert_eq!(write(OutputFormat::Csv), "\
+name,type,path,completed,tested,loc_path,loc_line,loc_col,parts,partof,text
+REQ-foo,REQ,reqs/foo.toml,100,0,,,,SPC-foo,REQ,\"a \"\"quoted\"\", multi
+line\ttext\"
+SPC-foo,SPC,reqs/foo.toml,100,0,src/foo.rs,3,4,,\"REQ-foo, SPC\",
+");
+    assert_eq!(write(OutputFormat::Tsv), "\
+name\ttype\tpath\tcompleted\ttested\tloc_path\tloc_line\tloc_col\tparts\tpartof\ttext
+REQ-foo\tREQ\treqs/foo.toml\t100\t0\t\t\t\tSPC-foo\tREQ\ta \"quoted\", multi\\nline\\ttext
+SPC-foo\tSPC\treqs/foo.toml\t100\t0\tsrc/foo.rs\t3\t4\t\tREQ-foo, SPC\t
+");
+
+    let template = get_template(r"{name}\t{completed}\t{loc_path}:{loc_line}").unwrap();
+    assert_eq!(write(OutputFormat::Template(template)),
+               "REQ-foo\t100\t:\nSPC-foo\t100\tsrc/foo.rs:3\n");
+    assert!(get_template("{name} {bad}").is_err());
+}
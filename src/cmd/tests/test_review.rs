@@ -0,0 +1,61 @@
+use super::super::types::*;
+use super::super::review::*;
+use super::super::status;
+use super::super::ls;
+use core::types::Reviewed;
+
+#[test]
+/// partof: #TST-review-cmd
+fn test_review() {
+    let name = |n: &str| ArtNameRc::from_str(n).unwrap();
+    let text = "\
+        [REQ-foo]\ntext = 'foo'\n\
+        [SPC-foo]\n\
+        [TST-foo]\npartof = 'SPC-foo'\n";
+    let mut artifacts = core::load::load_toml_simple(text);
+    core::link::do_links(&mut artifacts).unwrap();
+    let mut reviewed = Reviewed::new();
+    let cmd = ReviewCmd { names: vec![ArtName::from_str("SPC-fo").unwrap()], all: false };
+    assert_eq!(review_names(&artifacts, &mut reviewed, &cmd).unwrap_err(),
+               "SPC-fo does not exist (did you mean SPC-foo?)");
+
+    let cmd = ReviewCmd { names: Vec::new(), all: true };
+    let done = review_names(&artifacts, &mut reviewed, &cmd).unwrap();
+    assert_eq!(done.len(), 6);
+    // artifacts without parents that were written in a file are not kept
+    let mut children: Vec<_> = reviewed.keys().cloned().collect();
+    children.sort();
+    assert_eq!(children, vec![name("SPC-foo"), name("TST-foo")]);
+
+    // changing the text of REQ-foo makes SPC-foo suspect, which is an error
+    let mut artifacts = core::load::load_toml_simple(&text.replace("'foo'", "'changed'"));
+    core::link::do_links(&mut artifacts).unwrap();
+    core::review::set_suspects(&mut artifacts, &reviewed);
+    let st = status::get_status(&artifacts);
+    assert_eq!(st.suspect, vec![(name("SPC-foo"), name("REQ-foo"))]);
+    assert_eq!(st.num_errors(), 1);
+    let mut out: Vec<u8> = Vec::new();
+    st.write_errors(&mut out, Path::new(""), &artifacts).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               " - [test] SPC-foo is suspect: REQ-foo changed since the link was reviewed, \
+                run `rst review SPC-foo` once it is checked\n");
+
+    // ls marks the suspect artifact in its own row and the parent in the partof column
+    let mut out: Vec<u8> = Vec::new();
+    let mut fmt_set = FmtSettings::default();
+    fmt_set.partof = true;
+    ls::do_ls(&mut out, Path::new(""), "spc-foo", &artifacts, &fmt_set,
+              &SearchSettings::default(), &Settings::default()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               "|  | DONE TEST | ARTIFACT NAME     | PARTOF   \n\
+                |--|   0%   0% | spc-foo (suspect) | REQ-foo (suspect), SPC \n");
+
+    // reviewing it again clears it
+    let cmd = ReviewCmd { names: vec![ArtName::from_str("spc-foo").unwrap()], all: false };
+    let done = review_names(&artifacts, &mut reviewed, &cmd).unwrap();
+    assert_eq!(done, vec![(name("SPC-foo"), vec![name("REQ-foo")])]);
+    let mut artifacts = core::load::load_toml_simple(&text.replace("'foo'", "'changed'"));
+    core::link::do_links(&mut artifacts).unwrap();
+    core::review::set_suspects(&mut artifacts, &reviewed);
+    assert_eq!(status::get_status(&artifacts).num_errors(), 0);
+}
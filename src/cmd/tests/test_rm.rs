@@ -0,0 +1,85 @@
+
+use super::super::types::*;
+use super::super::rm::*;
+
+#[test]
+/// partof: #TST-rm
+fn test_get_references() {
+    let mut artifacts = core::load::load_toml_simple("
+[REQ-foo]
+[REQ-foo-bar]
+[SPC-foo]
+[SPC-other]
+partof = 'REQ-foo'
+[SPC-foo-bar]
+");
+    artifacts.get_mut(&ArtName::from_str("SPC-foo").unwrap()).unwrap().loc = Some(Loc::fake());
+    core::link::do_links(&mut artifacts).unwrap();
+    let names = |names: &[ArtNameRc]| -> Vec<String> {
+        names.iter().map(|n| n.raw.clone()).collect()
+    };
+    let refs = get_references(&ArtName::from_str("REQ-foo").unwrap(), &artifacts);
+    assert_eq!(names(&refs.referrers), vec!["SPC-foo", "SPC-other"]);
+    assert_eq!(names(&refs.children), vec!["REQ-foo-bar"]);
+    assert!(refs.locs.is_empty());
+
+    let refs = get_references(&ArtName::from_str("SPC-foo").unwrap(), &artifacts);
+    assert!(refs.referrers.is_empty());
+    assert_eq!(names(&refs.children), vec!["SPC-foo-bar"]);
+    assert_eq!(refs.locs.len(), 1);
+
+    let refs = get_references(&ArtName::from_str("SPC-other").unwrap(), &artifacts);
+    assert!(refs.is_empty());
+}
+
+#[test]
+fn test_do_rm() {
+    let files = [("reqs/foo.toml", "\
+[SPC-foo]
+text = 'the foo'
+
+[SPC-foo-bar]
+
+[SPC-other]
+text = 'not removed'
+"),
+                 ("reqs/tst.toml", "[TST-baz]\npartof = 'SPC-[foo, other]'\n"),
+                 ("src/foo.rs", "// \x23SPC-foo-bar\n")];
+    let dir = super::create_project("rst-test-rm", &files);
+    let (artifacts, _) = core::load_path(&dir.join(".rst")).unwrap();
+    let cmd = |name: &str, force: bool, cascade: bool| RmCmd {
+        name: ArtName::from_str(name).unwrap(),
+        force: force,
+        cascade: cascade,
+    };
+
+    // nothing is removed while there are references
+    let mut out: Vec<u8> = Vec::new();
+    assert_eq!(do_rm(&mut out, &dir, &artifacts, &cmd("SPC-foo", false, false)).unwrap_err(),
+               "SPC-foo is still referred to, use --force or --cascade");
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+SPC-foo is referred to by:
+ - TST-baz is a partof it
+ - SPC-foo-bar is a child of it
+ - SPC-foo-bar is implemented at src/foo.rs:1
+");
+    assert_eq!(super::read(&dir, "reqs/foo.toml"), files[0].1);
+    assert!(do_rm(&mut Vec::new(), &dir, &artifacts, &cmd("SPC-dne", false, false)).is_err());
+
+    // an artifact without references is removed
+    let mut out: Vec<u8> = Vec::new();
+    do_rm(&mut out, &dir, &artifacts, &cmd("TST-baz", false, false)).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "removed 1 artifacts from 1 files\n");
+    assert_eq!(super::read(&dir, "reqs/tst.toml"), "");
+    fs::File::create(dir.join("reqs/tst.toml")).unwrap().write_all(files[1].1.as_bytes()).unwrap();
+
+    // cascading removes the children and the name from every partof
+    let mut out: Vec<u8> = Vec::new();
+    do_rm(&mut out, &dir, &artifacts, &cmd("SPC-foo", false, true)).unwrap();
+    assert!(String::from_utf8(out).unwrap().ends_with("removed 2 artifacts from 2 files\n"));
+    // the blank line above SPC-other belongs to it
+    assert_eq!(super::read(&dir, "reqs/foo.toml"), "\n[SPC-other]\ntext = 'not removed'\n");
+    assert_eq!(super::read(&dir, "reqs/tst.toml"), "[TST-baz]\npartof = \"SPC-other\"\n");
+    let (artifacts, _) = core::load_path(&dir.join(".rst")).unwrap();
+    assert!(!artifacts.contains_key(&ArtName::from_str("SPC-foo-bar").unwrap()));
+}
@@ -0,0 +1,75 @@
+use super::super::types::*;
+use super::super::set_status::*;
+
+#[test]
+/// partof: #TST-set-status
+fn test_check_transition() {
+    let mut settings = Settings::new();
+    settings.statuses = vec!["draft".to_string(), "review".to_string(),
+                             "approved".to_string()];
+    // without transitions any change is allowed
+    assert!(check_transition(&settings, Some("approved"), "draft").is_ok());
+    assert!(check_transition(&settings, None, "review").is_ok());
+    assert_eq!(check_transition(&settings, Some("draft"), "done").unwrap_err(),
+               "unknown status \"done\", the statuses are: draft, review, approved");
+    assert_eq!(check_transition(&settings, Some("draft"), "draft").unwrap_err(),
+               "the status is already draft");
+
+    settings.transitions = vec![("draft".to_string(), "review".to_string()),
+                                ("review".to_string(), "approved".to_string()),
+                                ("review".to_string(), "draft".to_string())];
+    assert!(check_transition(&settings, Some("draft"), "review").is_ok());
+    assert!(check_transition(&settings, None, "approved").is_ok());
+    assert_eq!(check_transition(&settings, Some("draft"), "approved").unwrap_err(),
+               "the status cannot be changed from draft to approved, it can be changed to: \
+                review");
+    assert_eq!(check_transition(&settings, Some("approved"), "draft").unwrap_err(),
+               "the status cannot be changed from approved");
+}
+
+#[test]
+fn test_do_set_status() {
+    let files = [("reqs/foo.toml", "\
+[REQ-foo]
+status = 'draft'  # waiting on review
+text = 'the foo'
+
+[REQ-bar]
+"),
+                 (".rst/status.toml", "[settings]\n\
+                                       statuses = ['draft', 'approved', 'obsolete', \
+                                                   'on \"hold\" \\ later']\n\
+                                       transitions = ['draft -> approved', \
+                                                      'approved -> obsolete']\n")];
+    let dir = super::create_project("rst-test-set-status", &files);
+    let (artifacts, settings) = core::load_path(&dir.join(".rst")).unwrap();
+    let cmd = |name: &str, status: &str| SetStatusCmd {
+        name: ArtName::from_str(name).unwrap(),
+        status: status.to_string(),
+    };
+
+    // a forbidden transition leaves the file untouched
+    let mut out: Vec<u8> = Vec::new();
+    assert_eq!(do_set_status(&mut out, &artifacts, &settings, &cmd("REQ-foo", "obsolete"))
+                   .unwrap_err(),
+               "REQ-foo: the status cannot be changed from draft to obsolete, it can be \
+                changed to: approved");
+    assert!(do_set_status(&mut out, &artifacts, &settings, &cmd("REQ-baz", "draft")).is_err());
+    assert_eq!(super::read(&dir, "reqs/foo.toml"), files[0].1);
+
+    // the status is replaced in place, keeping its comment
+    do_set_status(&mut out, &artifacts, &settings, &cmd("REQ-foo", "approved")).unwrap();
+    // an artifact without a status gets one, which is escaped in the file
+    let hold = "on \"hold\" \\ later";
+    do_set_status(&mut out, &artifacts, &settings, &cmd("REQ-bar", hold)).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               format!("REQ-foo: draft -> approved\nREQ-bar: (none) -> {}\n", hold));
+    let (artifacts, _) = core::load_path(&dir.join(".rst")).unwrap();
+    let status = |name: &str| {
+        artifacts[&ArtName::from_str(name).unwrap()].status.clone().unwrap()
+    };
+    assert_eq!(status("REQ-foo"), "approved");
+    assert_eq!(status("REQ-bar"), hold);
+    let text = super::read(&dir, "reqs/foo.toml");
+    assert!(text.contains("status = \"approved\"  # waiting on review\n"), "{}", text);
+}
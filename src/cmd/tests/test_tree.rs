@@ -0,0 +1,54 @@
+
+use super::super::types::*;
+use super::super::tree::*;
+
+#[test]
+/// partof: #TST-tree
+fn test_tree() {
+    let mut artifacts = core::load::load_toml_simple(r"
+[REQ-foo]
+[REQ-foo-bar]
+[REQ-foo-baz]
+[SPC-foo]
+[SPC-other]
+partof = 'REQ-foo, REQ-foo-bar'
+");
+    core::link::do_links(&mut artifacts).unwrap();
+    let mut settings = Settings::new();
+    settings.color = false;
+
+    let mut w: Vec<u8> = Vec::new();
+    do_tree(&mut w, "REQ-foo", u8::max_value(), &artifacts, &settings).unwrap();
+    let expected = "\
+|--|   0%   0% | REQ-foo
+|--|   0%   0% | ├── REQ-foo-bar
+|--|   0%   0% | │   └── SPC-other
+|--|   0%   0% | ├── REQ-foo-baz
+|--|   0%   0% | ├── SPC-foo
+|--|   0%   0% | └── SPC-other (*)
+";
+    assert_eq!(String::from_utf8(w).unwrap(), expected);
+
+    // the depth limits the levels shown
+    let mut w: Vec<u8> = Vec::new();
+    do_tree(&mut w, "REQ-foo", 1, &artifacts, &settings).unwrap();
+    assert_eq!(String::from_utf8(w).unwrap().lines().count(), 5);
+
+    // artifacts displayed under an earlier root are not expanded again
+    let mut w: Vec<u8> = Vec::new();
+    do_tree(&mut w, "REQ-foo-bar, SPC-[foo, other]", u8::max_value(), &artifacts, &settings)
+        .unwrap();
+    let expected = "\
+|--|   0%   0% | REQ-foo-bar
+|--|   0%   0% | └── SPC-other
+|--|   0%   0% | SPC-foo
+|--|   0%   0% | SPC-other (*)
+";
+    assert_eq!(String::from_utf8(w).unwrap(), expected);
+
+    // by default every artifact that is not a partof another is a root
+    assert_eq!(get_roots("", &artifacts).unwrap().iter().map(|n| n.raw.clone())
+                   .collect::<Vec<_>>(),
+               vec!["REQ", "SPC"]);
+    assert!(get_roots("REQ-dne", &artifacts).is_err());
+}
@@ -0,0 +1,37 @@
+use super::super::types::*;
+use super::super::watch::*;
+
+#[test]
+/// partof: #TST-watch
+fn test_snapshot() {
+    let dir = core::TempDir::new("rst-test-watch");
+    let cfg = dir.join(".rst");
+    for d in &[&cfg, &dir.join("reqs"), &dir.join("src/excluded")] {
+        fs::create_dir_all(d).unwrap();
+    }
+    for f in &[".rst/settings.toml", "reqs/foo.toml", "src/foo.rs", "src/excluded/bar.rs"] {
+        fs::File::create(dir.join(f)).unwrap();
+    }
+    let mut settings = Settings::new();
+    settings.paths.push_back(dir.join("reqs"));
+    settings.code_paths.push_back(dir.join("src"));
+    settings.exclude_code_paths.push_back(dir.join("src/excluded"));
+
+    let snap = snapshot(&cfg, &settings);
+    let mut files: Vec<PathBuf> = snap.keys().cloned().collect();
+    files.sort();
+    assert_eq!(files, vec![cfg.join("settings.toml"), dir.join("reqs/foo.toml"),
+                           dir.join("src/foo.rs")]);
+
+    // changes in excluded paths are ignored
+    fs::File::create(dir.join("src/excluded/baz.rs")).unwrap();
+    assert_eq!(snapshot(&cfg, &settings), snap);
+
+    // files being added or removed are changes
+    fs::File::create(dir.join("reqs/bar.toml")).unwrap();
+    assert!(snapshot(&cfg, &settings) != snap);
+    fs::remove_file(dir.join("reqs/bar.toml")).unwrap();
+    assert_eq!(snapshot(&cfg, &settings), snap);
+    fs::remove_file(dir.join("src/foo.rs")).unwrap();
+    assert!(snapshot(&cfg, &settings) != snap);
+}
@@ -0,0 +1,127 @@
+//! display the artifacts as a tree of their parts
+
+use super::types::*;
+use super::fmt as cmdfmt;
+use super::term;
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("tree")
+        .about("display the artifacts as a tree following their parts. Artifacts \
+                that were already displayed are marked with (*)")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp])
+        .arg(Arg::with_name("root")
+                 .help("the artifacts to start from in form `REQ-foo-[bar, baz]`. \
+                        Defaults to every artifact that is not a partof another")
+                 .use_delimiter(false))
+        .arg(Arg::with_name("depth")
+                 .short("d")
+                 .long("depth")
+                 .value_name("N")
+                 .help("the number of levels of parts to display")
+                 .takes_value(true))
+        .arg(Arg::with_name("plain")
+                 .long("plain")
+                 .help("do not display color in the output"))
+}
+
+/// get the roots, depth and whether to use color from the user input
+pub fn get_tree_cmd(matches: &ArgMatches) -> Result<(String, u8, bool), String> {
+    let depth = match matches.value_of("depth") {
+        Some(d) => try!(d.parse::<u8>().map_err(|e| format!("invalid depth: {}", e))),
+        None => u8::max_value(),
+    };
+    Ok((matches.value_of("root").unwrap_or("").to_string(),
+        depth,
+        !matches.is_present("plain") && term::use_color()))
+}
+
+/// get the roots of the tree, which are the given names or every
+/// artifact that is not a partof another
+pub fn get_roots(roots: &str, artifacts: &Artifacts) -> Result<Vec<ArtNameRc>, String> {
+    let mut names: Vec<ArtNameRc> = if roots == "" {
+        artifacts.iter()
+            .filter(|&(_, a)| a.partof.is_empty())
+            .map(|(n, _)| n.clone())
+            .collect()
+    } else {
+        try!(ArtNames::from_str(roots).map_err(|e| e.to_string())).into_iter().collect()
+    };
+    names.sort();
+    for n in &names {
+        if !artifacts.contains_key(n) {
+            return Err(format!("{} does not exist{}",
+                               n, utils::did_you_mean(n, artifacts.keys())));
+        }
+    }
+    Ok(names)
+}
+
+fn write_node<W: Write>(w: &mut W,
+                        node: &FmtArtifact,
+                        artifacts: &Artifacts,
+                        settings: &Settings,
+                        prefix: &str,
+                        last: bool,
+                        level: u8,
+                        depth: u8)
+                        -> io::Result<()> {
+    let artifact = artifacts.get(&node.name).unwrap();
+    try!(cmdfmt::write_status(w, artifact, settings));
+    let connector = match (level, last) {
+        (0, _) => "",
+        (_, true) => "└── ",
+        (_, false) => "├── ",
+    };
+    try!(write!(w, "{}{}", prefix, connector));
+    if settings.color {
+        try!(write!(w, "{}", cmdfmt::get_color(artifact).bold().paint(node.name.raw.as_str())));
+    } else {
+        try!(write!(w, "{}", node.name.raw));
+    }
+    // only artifacts that were already displayed are left unexpanded
+    // before the depth is reached
+    if node.parts.is_none() && level < depth {
+        try!(w.write_all(" (*)".as_ref()));
+    }
+    try!(w.write_all("\n".as_ref()));
+    if level >= depth {
+        return Ok(());
+    }
+    if let Some(ref parts) = node.parts {
+        let prefix = match (level, last) {
+            (0, _) => prefix.to_string(),
+            (_, true) => format!("{}    ", prefix),
+            (_, false) => format!("{}│   ", prefix),
+        };
+        for (i, part) in parts.iter().enumerate() {
+            try!(write_node(w, part, artifacts, settings, &prefix, i == parts.len() - 1,
+                            level + 1, depth));
+        }
+    }
+    Ok(())
+}
+
+/// write the tree of each root
+/// partof: #SPC-tree
+pub fn do_tree<W: Write>(w: &mut W, roots: &str, depth: u8, artifacts: &Artifacts,
+                         settings: &Settings)
+                         -> Result<(), String> {
+    let roots = try!(get_roots(roots, artifacts));
+    let mut fmt_set = FmtSettings::default();
+    fmt_set.parts = true;
+    fmt_set.recurse = depth.saturating_sub(1);
+    // artifacts are only expanded the first time they are displayed, even
+    // when they are under another root
+    let mut displayed = ArtNames::new();
+    for root in roots {
+        let node = if displayed.contains(&root) {
+            FmtArtifact { name: root.clone(), ..FmtArtifact::default() }
+        } else {
+            displayed.insert(root.clone());
+            ui::fmt_artifact(&root, artifacts, &fmt_set, fmt_set.recurse, &mut displayed)
+        };
+        try!(write_node(w, &node, artifacts, settings, "", true, 0, depth)
+            .map_err(|e| e.to_string()));
+    }
+    Ok(())
+}
@@ -1,16 +1,18 @@
 // Traits
-pub use std::io::Write;
+pub use std::io::{Read, Write};
 pub use std::fmt::Write as FmtWrite;
 pub use std::iter::FromIterator;
 
 // stdlib
 pub use std::collections::{HashSet, HashMap};
 pub use std::process::exit;
+pub use std::fs;
 pub use std::path::{Path, PathBuf};
 
 // string processing
 pub use std::io;
 pub use ansi_term::Style;
+pub use ansi_term::Colour;
 pub use ansi_term::Colour::{Red, Blue, Green, Yellow};
 pub use regex::{Regex, RegexBuilder};
 
@@ -26,5 +28,6 @@ pub use super::super::core::{
     ArtName, ArtNameRc, ArtNames,
     LoadFromStr};
 pub use super::super::ui;
-pub use super::super::ui::{FmtSettings, FmtArtifact, PercentSearch, SearchSettings};
+pub use super::super::ui::{FmtSettings, FmtArtifact, PercentSearch, SearchSettings,
+                           SortBy, GroupBy, OutputFormat};
 
@@ -0,0 +1,156 @@
+//! re-run a command every time the artifact, code or settings files change
+
+use std::ffi::OsString;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::types::*;
+use super::ls;
+use super::term;
+use super::matches;
+
+/// the commands that can be watched. Commands that write to files or
+/// are interactive are not included
+const WATCHABLE: [&'static str; 5] = ["ls", "tree", "export", "status", "check"];
+
+/// the modified times of every watched file
+pub type Snapshot = HashMap<PathBuf, SystemTime>;
+
+pub struct WatchCmd<'a> {
+    /// the command as the user wrote it, for display
+    pub cmd: String,
+    /// the args of the command, to expand views after every load
+    pub args: Vec<OsString>,
+    pub matches: ArgMatches<'a>,
+    /// how often to check for changes in milliseconds
+    pub interval: u64,
+}
+
+pub fn get_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("watch")
+        .about("re-run a command whenever the artifacts, code or settings change, \
+                i.e. `rst watch ls -c '<100'`")
+        .settings(&[AS::DeriveDisplayOrder, AS::ColoredHelp, AS::TrailingVarArg])
+        .arg(Arg::with_name("interval")
+                 .short("i")
+                 .long("interval")
+                 .value_name("MS")
+                 .help("how often to check for changes in milliseconds, default 500")
+                 .takes_value(true))
+        .arg(Arg::with_name("cmd")
+                 .help("the command to run and its arguments")
+                 .multiple(true)
+                 .required(true))
+}
+
+/// get the command to watch from the user input
+pub fn get_watch_cmd<'a>(matches: &ArgMatches) -> Result<WatchCmd<'a>, String> {
+    let interval = match matches.value_of("interval") {
+        Some(i) => try!(i.parse::<u64>().map_err(|e| format!("invalid interval: {}", e))),
+        None => 500,
+    };
+    let args: Vec<&str> = matches.values_of("cmd").unwrap().collect();
+    if !WATCHABLE.contains(&args[0]) {
+        return Err(format!("cannot watch `{}`, the commands that can be watched are: {}",
+                           args[0], WATCHABLE.join(", ")));
+    }
+    let cmd_args: Vec<OsString> = Some("rst").into_iter().chain(args.clone())
+        .map(|a| a.into())
+        .collect();
+    let cmd_matches = try!(matches::get_matches(cmd_args.clone()).map_err(|e| e.to_string()));
+    Ok(WatchCmd {
+        cmd: args.join(" "),
+        args: cmd_args,
+        matches: cmd_matches,
+        interval: interval,
+    })
+}
+
+fn snapshot_path(path: &Path, snapshot: &mut Snapshot) {
+    let meta = match fs::metadata(path) {
+        Ok(m) => m,
+        Err(_) => return,
+    };
+    if meta.is_dir() {
+        let entries = match fs::read_dir(path) {
+            Ok(e) => e,
+            Err(_) => return,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            snapshot_path(&entry.path(), snapshot);
+        }
+    } else if let Ok(modified) = meta.modified() {
+        snapshot.insert(path.to_path_buf(), modified);
+    }
+}
+
+/// get the modified times of the files in the settings directory, the artifact
+/// paths and the code paths. Files that are added or removed also change the
+/// snapshot
+/// partof: #SPC-watch-snapshot
+pub fn snapshot(cfg: &Path, settings: &Settings) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    snapshot_path(cfg, &mut snapshot);
+    for path in &settings.paths {
+        snapshot_path(path, &mut snapshot);
+    }
+    // the excluded paths are skipped, so they may be changed freely
+    if let Ok(files) = core::locs::find_code_files(settings) {
+        for f in files {
+            snapshot_path(&f, &mut snapshot);
+        }
+    }
+    snapshot
+}
+
+/// run the command, then reload the artifacts and run it again every
+/// time something changes. This never returns
+/// partof: #SPC-watch
+pub fn do_watch<W: Write>(w: &mut W, cwd: &Path, cfg: &Path, cmd: &WatchCmd) -> ! {
+    // if the artifacts fail to load, the last settings that loaded are
+    // used to know what to watch
+    let mut settings = Settings::new();
+    loop {
+        // clear the screen and move to the top, unless the output is
+        // redirected to a file or another command
+        if term::is_tty() {
+            write!(w, "\x1b[2J\x1b[1;1H").unwrap();
+        }
+        let header = format!("Every {}ms: rst {}", cmd.interval, cmd.cmd);
+        if term::use_color() {
+            write!(w, "{}\n\n", Green.paint(header)).unwrap();
+        } else {
+            write!(w, "{}\n\n", header).unwrap();
+        }
+        // the snapshot is taken before loading so that changes made while
+        // loading are not missed
+        let mut last = snapshot(cfg, &settings);
+        match core::load_path(cfg) {
+            Ok((artifacts, s)) => {
+                // views can change with the settings
+                match ls::get_view_matches(&cmd.args, &cmd.matches, &s) {
+                    Ok(Some(m)) => super::run_cmd(w, cwd, cfg, &m, &artifacts, &s),
+                    Ok(None) => super::run_cmd(w, cwd, cfg, &cmd.matches, &artifacts, &s),
+                    Err(e) => {
+                        error!("{}", e);
+                        1
+                    }
+                };
+                if (&s.paths, &s.code_paths, &s.exclude_code_paths) !=
+                   (&settings.paths, &settings.code_paths, &settings.exclude_code_paths) {
+                    last = snapshot(cfg, &s);
+                }
+                settings = s;
+            }
+            Err(err) => error!("{}", err),
+        }
+        w.flush().unwrap();
+
+        loop {
+            thread::sleep(Duration::from_millis(cmd.interval));
+            if snapshot(cfg, &settings) != last {
+                break;
+            }
+        }
+    }
+}
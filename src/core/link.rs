@@ -1,6 +1,7 @@
 //! module that discovers artifact's links
 
-use super::types::*;
+use super::prelude::*;
+use super::utils;
 use ui;
 
 pub fn do_links(artifacts: &mut Artifacts) -> LoadResult<()> {
@@ -44,9 +45,14 @@ pub fn create_parents(artifacts: &mut Artifacts) {
             ty: name.get_type(),
             path: PathBuf::from("PARENT"),
             text: "AUTO".to_string(),
+            hash: String::new(),
             partof: HashSet::new(),
+            status: None,
             parts: HashSet::new(),
             loc: None,
+            mentions: HashSet::new(),
+            suspect: HashSet::new(),
+            approval: None,
             completed: -1.0,
             tested: -1.0,
         };
@@ -73,7 +79,7 @@ pub fn link_named_partofs(artifacts: &mut Artifacts) {
     for (name, artifact) in artifacts.iter_mut() {
         for p in name.named_partofs() {
             if artifacts_keys.contains(&p) {
-                artifact.partof.insert(Rc::new(p));
+                artifact.partof.insert(Arc::new(p));
             }
         }
     }
@@ -82,7 +88,7 @@ pub fn link_named_partofs(artifacts: &mut Artifacts) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::types::*;
+    use super::super::prelude::*;
     use core::load::load_toml_simple;
     #[test]
     fn test_link_named_partofs() {
@@ -138,6 +144,30 @@ pub fn validate_partof(artifacts: &Artifacts) -> LoadResult<()> {
     Ok(())
 }
 
+/// warn about the artifacts mentioned in text that do not exist
+/// partof: #SPC-artifact-mentions-validate
+pub fn warn_mentions(artifacts: &Artifacts) -> u64 {
+    let mut warnings: u64 = 0;
+    let mut names: Vec<&ArtNameRc> = artifacts.keys().collect();
+    names.sort();
+    for name in names {
+        let artifact = &artifacts[name];
+        let mut mentions: Vec<_> = artifact.mentions.iter()
+            .filter(|m| !artifacts.contains_key(*m))
+            .collect();
+        mentions.sort();
+        for m in mentions {
+            warn!("[{:?}] {} mentions {} which does not exist{}",
+                  artifact.path,
+                  name,
+                  m,
+                  utils::did_you_mean(m, artifacts.keys()));
+            warnings += 1;
+        }
+    }
+    warnings
+}
+
 /// traverse all artifacts and their `partof` members and cross-link them to
 /// the artifact's `parts` members
 /// [#SPC-core-artifact-attrs-parts-link]
@@ -149,14 +179,15 @@ pub fn link_parts(artifacts: &mut Artifacts) -> u64 {
         // get the artifacts this is a `partof`, this artifact should be in all of their `parts`
         for partof in artifact.partof.iter() {
             if !artifacts.contains_key(partof) {
-                warn!("[{:?}] {} has invalid partof = {}",
+                warn!("[{:?}] {} has invalid partof = {}{}",
                       artifact.path,
                       name,
-                      partof);
+                      partof,
+                      utils::did_you_mean(partof, artifacts.keys()));
                 warnings += 1;
                 continue;
             }
-            // TODO: there is no get_key(K).clone() yet, so we can't re-use Rc data here
+            // TODO: there is no get_key(K).clone() yet, so we can't re-use Arc data here
             // https://github.com/rust-lang/rfcs/pull/1175
             if !artifact_parts.contains_key(partof) {
                 artifact_parts.insert(partof.clone(), HashSet::new());
@@ -173,6 +204,47 @@ pub fn link_parts(artifacts: &mut Artifacts) -> u64 {
 }
 
 
+/// whether the artifact is obsolete, so it is skipped in the completed and
+/// tested ratios of its parents
+/// partof: #SPC-artifact-status-obsolete
+pub fn is_obsolete(artifact: &Artifact) -> bool {
+    artifact.status.as_ref().map_or(false, |s| s == OBSOLETE)
+}
+
+/// use the default statuses if none are set in the settings, then make sure
+/// the status of every artifact and the transitions only use those statuses
+/// partof: #SPC-artifact-status
+pub fn validate_status(artifacts: &Artifacts, settings: &mut Settings) -> LoadResult<()> {
+    if settings.statuses.is_empty() {
+        settings.statuses = DEFAULT_STATUSES.iter().map(|s| s.to_string()).collect();
+    }
+    let mut errors: Vec<String> = Vec::new();
+    let valid = |s: &str| settings.statuses.iter().any(|v| v == s);
+    for &(ref from, ref to) in &settings.transitions {
+        for s in &[from, to] {
+            if !valid(s) {
+                errors.push(format!("the transition {} -> {} uses the unknown status {:?}",
+                                    from, to, s));
+            }
+        }
+    }
+    for (name, artifact) in artifacts.iter() {
+        if let Some(ref s) = artifact.status {
+            if !valid(s) {
+                errors.push(format!("[{}] {} has unknown status {:?}",
+                                    artifact.path.display(), name, s));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        errors.sort();
+        return Err(LoadError::new(format!("invalid statuses, the statuses are: {}\n- {}",
+                                          settings.statuses.join(", "),
+                                          errors.join("\n- "))));
+    }
+    Ok(())
+}
+
 /// discover how complete and how tested all artifacts are (or are not!)
 /// [#SPC-core-coverage-percent-done]
 pub fn set_completed(artifacts: &mut Artifacts) -> usize {
@@ -210,15 +282,18 @@ pub fn set_completed(artifacts: &mut Artifacts) -> usize {
                     artifacts.get_mut(name).unwrap().completed = {
                         let artifact = artifacts.get(name).unwrap();
                         // get the completed values, ignoring TSTs that are part of SPCs
+                        // and obsolete artifacts
                         let completed: Vec<f32> = if artifact.ty == ArtType::SPC {
                             artifact.parts
                                     .iter()
                                     .filter(|n| artifacts.get(n.clone()).unwrap().ty != ArtType::TST)
+                                    .filter(|n| !is_obsolete(&artifacts[n.clone()]))
                                     .map(|n| artifacts.get(n).unwrap().completed)
                                     .collect()
                         } else {
                             artifact.parts
                                     .iter()
+                                    .filter(|n| !is_obsolete(&artifacts[n.clone()]))
                                     .map(|n| artifacts.get(n).unwrap().completed)
                                     .collect()
                         };
@@ -304,11 +379,15 @@ pub fn set_tested(artifacts: &mut Artifacts) -> usize {
             if got_it {
                 artifacts.get_mut(name).unwrap().tested = {
                     let artifact = artifacts.get(name).unwrap();
-                    artifact.parts
-                            .iter()
-                            .map(|n| artifacts.get(n).unwrap().tested)
-                            .fold(0.0, |sum, x| sum + x) /
-                    artifact.parts.len() as f32
+                    let tested: Vec<f32> = artifact.parts
+                        .iter()
+                        .filter(|n| !is_obsolete(&artifacts[n.clone()]))
+                        .map(|n| artifacts.get(n).unwrap().tested)
+                        .collect();
+                    match tested.len() {
+                        0 => 0.0,
+                        _ => tested.iter().fold(0.0, |sum, x| sum + x) / tested.len() as f32,
+                    }
                 };
                 found.insert(name.clone());
                 known.insert(name.clone());
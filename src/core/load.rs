@@ -1,8 +1,9 @@
 //! loadrs
 //! loading of raw artifacts from files and text
 
-use super::types::*;
+use super::prelude::*;
 use super::vars;
+use super::review;
 
 use super::utils;
 
@@ -10,10 +11,14 @@ use toml::{Parser, Value, Table};
 
 lazy_static!{
     pub static ref ARTIFACT_ATTRS: HashSet<String> = HashSet::from_iter(
-        ["disabled", "text", "partof"].iter().map(|s| s.to_string()));
+        ["disabled", "text", "partof", "status"].iter().map(|s| s.to_string()));
     pub static ref SETTINGS_ATTRS: HashSet<String> = HashSet::from_iter(
         ["disabled", "artifact_paths",
-         "code_paths", "exclude_code_paths"].iter().map(|s| s.to_string()));
+         "code_paths", "exclude_code_paths", "thresholds",
+         "statuses", "transitions"].iter().map(|s| s.to_string()));
+    pub static ref VIEW_ATTRS: HashSet<String> = HashSet::from_iter(
+        ["search", "pattern", "completed", "tested", "query",
+         "fields", "sort", "group_by", "reverse"].iter().map(|s| s.to_string()));
 }
 
 macro_rules! get_attr {
@@ -87,6 +92,26 @@ impl Settings {
                                                                      &df_vec),
                                                           "exclude_code_paths",
                                                           "settings");
+        let thresholds: Vec<String> = check_type!(get_vecstr(tbl, "thresholds", &df_vec),
+                                                  "thresholds",
+                                                  "settings");
+        let statuses: Vec<String> = check_type!(get_vecstr(tbl, "statuses", &df_vec),
+                                                "statuses",
+                                                "settings");
+        let transitions: Vec<String> = check_type!(get_vecstr(tbl, "transitions", &df_vec),
+                                                   "transitions",
+                                                   "settings");
+        let mut parsed_transitions = Vec::with_capacity(transitions.len());
+        for t in &transitions {
+            let mut split = t.splitn(2, "->").map(|s| s.trim().to_string());
+            match (split.next(), split.next()) {
+                (Some(from), Some(to)) => parsed_transitions.push((from, to)),
+                _ => {
+                    return Err(LoadError::new(format!("invalid transition {:?}, it must be \
+                                                       of the form \"FROM -> TO\"", t)))
+                }
+            }
+        }
         Ok(Settings {
             disabled: check_type!(get_attr!(tbl, "disabled", false, Boolean),
                                   "disabled",
@@ -94,14 +119,53 @@ impl Settings {
             paths: str_paths.iter().map(|s| PathBuf::from(s)).collect(),
             code_paths: code_paths.iter().map(|s| PathBuf::from(s)).collect(),
             exclude_code_paths: exclude_code_paths.iter().map(|s| PathBuf::from(s)).collect(),
+            thresholds: thresholds,
             color: true,
+            views: HashMap::new(),
+            statuses: statuses,
+            transitions: parsed_transitions,
+        })
+    }
+}
+
+impl View {
+    /// Load a view from a TOML Table. The values are validated when
+    /// the view is used
+    pub fn from_table(name: &str, tbl: &Table) -> LoadResult<View> {
+        let invalid_attrs: Vec<_> = tbl.keys()
+                                       .filter(|k| !VIEW_ATTRS.contains(k.as_str()))
+                                       .collect();
+        if invalid_attrs.len() > 0 {
+            let mut msg = String::new();
+            write!(msg, "view {} has invalid attributes: {:?}", name, invalid_attrs).unwrap();
+            return Err(LoadError::new(msg));
+        }
+        let get = |attr: &str| -> LoadResult<Option<String>> {
+            match tbl.get(attr) {
+                Some(&Value::String(ref s)) => Ok(Some(s.clone())),
+                None => Ok(None),
+                _ => Err(LoadError::new(format!("view {} has invalid attribute: {}", name, attr))),
+            }
+        };
+        Ok(View {
+            search: try!(get("search")),
+            pattern: try!(get("pattern")),
+            completed: try!(get("completed")),
+            tested: try!(get("tested")),
+            query: try!(get("query")),
+            fields: try!(get("fields")),
+            sort: try!(get("sort")),
+            group_by: try!(get("group_by")),
+            reverse: check_type!(get_attr!(tbl, "reverse", false, Boolean),
+                                 "reverse",
+                                 format!("view {}", name)),
         })
     }
 }
 
 
 /// parse toml using a std error for this library
-fn parse_toml(toml: &str) -> LoadResult<Table> {
+pub fn parse_toml(toml: &str) -> LoadResult<Table> {
     let mut parser = Parser::new(toml);
     match parser.parse() {
         Some(table) => Ok(table),
@@ -130,7 +194,7 @@ impl Artifact {
             _ => return Err(LoadError::new("must contain a single table".to_string())),
         };
         let artifact = try!(Artifact::from_table(&name, &Path::new("from_str"), value));
-        Ok((Rc::new(name), artifact))
+        Ok((Arc::new(name), artifact))
     }
 
     /// Create an artifact object from a toml Table
@@ -148,28 +212,51 @@ impl Artifact {
 
         // partf: #SPC-artifact-partof-1: explicitly set artifact
         let partof_str = check_type!(get_attr!(tbl, "partof", df_str, String), "partof", name);
+        let status = match tbl.get("status") {
+            Some(&Value::String(ref s)) => Some(s.clone()),
+            None => None,
+            _ => return Err(LoadError::new(format!("{} has invalid attribute: status", name))),
+        };
+        let text = check_type!(get_attr!(tbl, "text", df_str, String), "text", name);
         Ok(Artifact {
             // loaded vars
             ty: name.get_type(),
             path: path.to_path_buf(),
-            text: check_type!(get_attr!(tbl, "text", df_str, String), "text", name),
+            hash: review::hash_text(&text),
+            text: text,
             partof: try!(ArtNames::from_str(&partof_str)),
+            status: status,
             loc: None,
 
             // calculated vars
             parts: HashSet::new(),
+            mentions: HashSet::new(),
+            suspect: HashSet::new(),
+            approval: None,
             completed: -1.0,
             tested: -1.0,
         })
     }
 }
 
+/// the text of a global that is not an array, booleans and integers
+/// are formatted as they are written
+/// partof: #SPC-vars-typed
+fn global_scalar(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref s) => Some(s.clone()),
+        Value::Boolean(b) => Some(b.to_string()),
+        Value::Integer(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
 /// Load artifacts and settings from a toml Table
 pub fn load_file_table(file_table: &mut Table,
                        path: &Path,
                        artifacts: &mut Artifacts,
                        settings: &mut Vec<(PathBuf, Settings)>,
-                       variables: &mut Vec<(PathBuf, Variables)>)
+                       variables: &mut Vec<(PathBuf, Globals)>)
                        -> LoadResult<u64> {
     let mut msg: Vec<u8> = Vec::new();
     let mut num_loaded: u64 = 0;
@@ -187,21 +274,46 @@ pub fn load_file_table(file_table: &mut Table,
         _ => return Err(LoadError::new("settings must be a Table".to_string())),
     }
 
+    match file_table.remove("views") {
+        Some(Value::Table(t)) => {
+            let mut views = HashMap::new();
+            for (k, v) in t {
+                let view = match v {
+                    Value::Table(ref vt) => try!(View::from_table(&k, vt)),
+                    _ => return Err(LoadError::new(format!("view {} must be a Table", k))),
+                };
+                views.insert(k, view);
+            }
+            // views are resolved with the settings of the file
+            if settings.last().map_or(true, |s| s.0.as_path() != path) {
+                settings.push((path.to_path_buf(), Settings::new()));
+            }
+            settings.last_mut().unwrap().1.views = views;
+        }
+        None => {}
+        _ => return Err(LoadError::new("views must be a Table".to_string())),
+    }
+
     match file_table.remove("globals") {
         Some(Value::Table(t)) => {
-            let mut lvars = Variables::new();
+            let mut lvars = Globals::new();
             for (k, v) in t {
                 if vars::DEFAULT_GLOBALS.contains(k.as_str()) {
-                    return Err(LoadError::new("cannot use variables: repo, cwd".to_string()));
+                    return Err(LoadError::new("cannot use variables: repo, cwd, env".to_string()));
                 }
-                lvars.insert(k.clone(),
-                             match v {
-                                 Value::String(s) => s.to_string(),
-                                 _ => {
-                                     return Err(LoadError::new(k.to_string() +
-                                                               " global var must be of type str"))
-                                 }
-                             });
+                let global = match v {
+                    Value::Array(ref a) => a.iter().map(global_scalar).collect::<Option<_>>()
+                        .map(Global::Array),
+                    ref v => global_scalar(v).map(Global::Str),
+                };
+                match global {
+                    Some(g) => lvars.insert(k, g),
+                    None => {
+                        return Err(LoadError::new(k.to_string() +
+                                                  " global var must be a str, bool, integer \
+                                                   or an array of them"))
+                    }
+                };
             }
             variables.push((path.to_path_buf(), lvars));
         }
@@ -235,7 +347,7 @@ pub fn load_file_table(file_table: &mut Table,
             continue;
         }
         let artifact = try!(Artifact::from_table(&aname, path, art_tbl));
-        artifacts.insert(Rc::new(aname), artifact);
+        artifacts.insert(Arc::new(aname), artifact);
         num_loaded += 1;
     }
     return Ok(num_loaded);
@@ -244,7 +356,7 @@ pub fn load_file_table(file_table: &mut Table,
 pub fn load_toml_simple(text: &str) -> Artifacts {
     let mut artifacts = Artifacts::new();
     let mut settings: Vec<(PathBuf, Settings)> = Vec::new();
-    let mut variables: Vec<(PathBuf, Variables)> = Vec::new();
+    let mut variables: Vec<(PathBuf, Globals)> = Vec::new();
     let path = PathBuf::from("test");
     load_toml(&path, text, &mut artifacts, &mut settings, &mut variables).unwrap();
     artifacts
@@ -255,7 +367,7 @@ pub fn load_toml(path: &Path,
                  text: &str,
                  artifacts: &mut Artifacts,
                  settings: &mut Vec<(PathBuf, Settings)>,
-                 variables: &mut Vec<(PathBuf, Variables)>)
+                 variables: &mut Vec<(PathBuf, Globals)>)
                  -> LoadResult<u64> {
     // parse the text
     let mut table = try!(parse_toml(text));
@@ -268,7 +380,7 @@ pub fn load_toml(path: &Path,
 pub fn load_file(path: &Path,
                  artifacts: &mut Artifacts,
                  settings: &mut Vec<(PathBuf, Settings)>,
-                 variables: &mut Vec<(PathBuf, Variables)>)
+                 variables: &mut Vec<(PathBuf, Globals)>)
                  -> LoadResult<u64> {
     // let mut text: Vec<u8> = Vec::new();
 
@@ -295,7 +407,7 @@ pub fn load_dir(path: &Path,
                 loaded_dirs: &mut HashSet<PathBuf>,
                 artifacts: &mut Artifacts,
                 settings: &mut Vec<(PathBuf, Settings)>,
-                variables: &mut Vec<(PathBuf, Variables)>)
+                variables: &mut Vec<(PathBuf, Globals)>)
                 -> LoadResult<u64> {
     loaded_dirs.insert(path.to_path_buf());
     // TDOO: if load_path.is_dir()
@@ -329,6 +441,9 @@ pub fn load_dir(path: &Path,
                 // only load toml files
                 continue;
             }
+            if review::is_review_file(&fpath) {
+                continue;
+            }
             match load_file(fpath.as_path(), artifacts, settings, variables) {
                 Ok(n) => num_loaded += n,
                 Err(err) => {
@@ -358,6 +473,13 @@ pub fn load_dir(path: &Path,
     }
 }
 
+/// format the default globals in a path from the settings
+fn fmt_path(path: &Path, vars: &Variables, fpath: &Path) -> LoadResult<String> {
+    vars::fmt_known(path.to_str().unwrap(), vars).map_err(|e| {
+        LoadError::new(format!("ERROR at {}: {}", fpath.display(), e.desc))
+    })
+}
+
 /// push settings found (loaded_settings) into a main settings object
 /// repo_map is a pre-compiled hashset mapping dirs->repo_path (for performance)
 /// partof: #SPC-settings-resolve
@@ -366,7 +488,7 @@ pub fn resolve_settings(settings: &mut Settings,
                         loaded_settings: &Vec<(PathBuf, Settings)>)
                         -> LoadResult<()> {
     // now resolve all path names
-    let mut vars: HashMap<String, String> = HashMap::new();
+    let mut vars = Variables::new();
     for ps in loaded_settings.iter() {
         let ref settings_item: &Settings = &ps.1;
 
@@ -384,23 +506,42 @@ pub fn resolve_settings(settings: &mut Settings,
 
         // push resolved paths
         for p in settings_item.paths.iter() {
-            let p = try!(utils::do_strfmt(p.to_str().unwrap(), &vars, &fpath));
+            let p = try!(fmt_path(p, &vars, &fpath));
+            let p = try!(utils::do_strfmt(&p, &vars, &fpath));
             settings.paths.push_back(PathBuf::from(p));
         }
 
-        // TODO: it is possible to be able to use all global variables in code_paths
-        //    but then it must be done in a separate step
-        // push resolved code_paths
+        // the code paths can use any global, so only the default globals are
+        // resolved here. The others are resolved with vars::resolve_code_paths
         for p in settings_item.code_paths.iter() {
-            let p = try!(utils::do_strfmt(p.to_str().unwrap(), &vars, &fpath));
-            settings.code_paths.push_back(PathBuf::from(p));
+            settings.code_paths.push_back(PathBuf::from(try!(fmt_path(p, &vars, &fpath))));
         }
-
-        // push resolved exclude_code_paths
         for p in settings_item.exclude_code_paths.iter() {
-            let p = try!(utils::do_strfmt(p.to_str().unwrap(), &vars, &fpath));
+            let p = try!(fmt_path(p, &vars, &fpath));
             settings.exclude_code_paths.push_back(PathBuf::from(p));
         }
+
+        settings.thresholds.extend(settings_item.thresholds.iter().cloned());
+
+        if !settings_item.statuses.is_empty() {
+            if !settings.statuses.is_empty() {
+                return Err(LoadError::new("statuses are defined more than once".to_string()));
+            }
+            settings.statuses = settings_item.statuses.clone();
+        }
+        if !settings_item.transitions.is_empty() {
+            if !settings.transitions.is_empty() {
+                return Err(LoadError::new("transitions are defined more than once".to_string()));
+            }
+            settings.transitions = settings_item.transitions.clone();
+        }
+
+        for (name, view) in settings_item.views.iter() {
+            if settings.views.contains_key(name) {
+                return Err(LoadError::new(format!("view {} is defined more than once", name)));
+            }
+            settings.views.insert(name.clone(), view.clone());
+        }
     }
     Ok(())
 }
@@ -410,13 +551,13 @@ pub fn resolve_settings(settings: &mut Settings,
 pub fn load_raw(path: &Path)
                 -> LoadResult<(Artifacts,
                                Settings,
-                               Vec<(PathBuf, Variables)>,
+                               Vec<(PathBuf, Globals)>,
                                HashMap<PathBuf, PathBuf>)> {
     let mut artifacts = Artifacts::new();
     let mut settings = Settings::new();
     let mut loaded_dirs: HashSet<PathBuf> = HashSet::new(); // see SPC-load-dir, RSK-2-load-loop
     let mut loaded_settings: Vec<(PathBuf, Settings)> = Vec::new();
-    let mut loaded_vars: Vec<(PathBuf, Variables)> = Vec::new();
+    let mut loaded_vars: Vec<(PathBuf, Globals)> = Vec::new();
     // repo_map maps directories to their found base-repositories
     let mut repo_map: HashMap<PathBuf, PathBuf> = HashMap::new();
     let mut msg = String::new();
@@ -433,11 +574,15 @@ pub fn load_raw(path: &Path)
     }
 
     // #SPC-core-load-parts-1:<load and validate all paths recursively>
+    // the paths that were loaded are kept in the settings, since they
+    // are needed by commands that watch or write to the artifact files
+    let mut paths: VecDeque<PathBuf> = VecDeque::new();
     while settings.paths.len() > 0 {
         let dir = settings.paths.pop_front().unwrap(); // it has len, it better pop!
         if loaded_dirs.contains(&dir) {
             continue;
         }
+        paths.push_back(dir.clone());
         debug!("Loading artifacts: {:?}", dir);
         loaded_settings.clear();
         loaded_dirs.insert(dir.to_path_buf());
@@ -462,6 +607,7 @@ pub fn load_raw(path: &Path)
         // see: SPC-settings-resolve
         try!(resolve_settings(&mut settings, &mut repo_map, &loaded_settings));
     }
+    settings.paths = paths;
 
     Ok((artifacts, settings, loaded_vars, repo_map))
 }
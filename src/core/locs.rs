@@ -1,5 +1,5 @@
 use std::io;
-use super::types::*;
+use super::prelude::*;
 
 lazy_static!{
     pub static ref SPC: VecDeque<char> = VecDeque::from_iter(vec!['#', 'S', 'P', 'C', '-']);
@@ -16,10 +16,11 @@ pub fn find_locs_text(path: &Path,
     let mut prev_char = ' ';
     let mut start_pos = 0;
     let mut start_col = 0;
-    let (mut pos, mut line, mut col) = (0, 1, 0); // line starts at 1
+    let (mut line, mut col) = (1, 0); // line starts at 1
     // pretty simple parse tree... just do it ourselves!
     // Looking for #LOC-[a-z0-9_-] case insensitive
-    for c in text.chars() {
+    // pos is a byte index so that names can be split out of non-ascii text
+    for (pos, c) in text.char_indices() {
         if prev == *SPC || prev == *TST {  // TODO: I'm sure this is not as fast as possible
             if prev_char == ' ' {
                 start_pos = pos - 5;
@@ -71,7 +72,6 @@ pub fn find_locs_text(path: &Path,
             }
             _ => col += 1,
         };
-        pos += 1;
     }
     error
 }
@@ -106,10 +106,10 @@ pub fn find_locs_file(path: &Path,
     find_locs_text(path, &text, locs)
 }
 
-/// recursively find all locs given a directory
-fn find_locs_dir(path: &PathBuf, loaded_dirs: &mut HashSet<PathBuf>,
-                 locs: &mut HashMap<ArtName, Loc>)
-                 -> bool {
+/// recursively find all code files given a directory
+fn find_code_files_dir(path: &PathBuf, loaded_dirs: &mut HashSet<PathBuf>,
+                       files: &mut Vec<PathBuf>)
+                       -> bool {
     loaded_dirs.insert(path.to_path_buf());
     let read_dir = match fs::read_dir(path) {
         Ok(d) => d,
@@ -140,10 +140,7 @@ fn find_locs_dir(path: &PathBuf, loaded_dirs: &mut HashSet<PathBuf>,
         if ftype.is_dir() {
             dirs_to_load.push(fpath.clone());
         } else if ftype.is_file() {
-            match find_locs_file(&fpath, locs) {
-                true => error = true,
-                false => {},
-            }
+            files.push(fpath);
         }
     };
 
@@ -151,7 +148,7 @@ fn find_locs_dir(path: &PathBuf, loaded_dirs: &mut HashSet<PathBuf>,
         if loaded_dirs.contains(&d) {
             continue;
         }
-        match find_locs_dir(&d, loaded_dirs, locs) {
+        match find_code_files_dir(&d, loaded_dirs, files) {
             true => error = true,
             false => {},
         }
@@ -159,11 +156,10 @@ fn find_locs_dir(path: &PathBuf, loaded_dirs: &mut HashSet<PathBuf>,
     error
 }
 
-/// search through the code_paths in settings to find all valid locs
-/// partof: #SPC-loc
-pub fn find_locs(settings: &mut Settings) -> LoadResult<HashMap<ArtName, Loc>> {
-    info!("parsing code files for artifacts...");
-    let mut locs: HashMap<ArtName, Loc> = HashMap::new();
+/// find all the code files in the code_paths in settings, skipping the
+/// excluded paths
+pub fn find_code_files(settings: &Settings) -> LoadResult<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
     let mut loaded_dirs: HashSet<PathBuf> = HashSet::from_iter(
         settings.exclude_code_paths.iter().map(|p| p.to_path_buf()));
     // first make sure the excluded directories exist
@@ -175,17 +171,34 @@ pub fn find_locs(settings: &mut Settings) -> LoadResult<HashMap<ArtName, Loc>> {
         }
     }
     debug!("initial excluded code paths: {:?}", loaded_dirs);
-    while settings.code_paths.len() > 0 {
-        let dir = settings.code_paths.pop_front().unwrap(); // it has len, it better pop!
-        if loaded_dirs.contains(&dir) {
+    for dir in settings.code_paths.iter() {
+        if loaded_dirs.contains(dir) {
             continue
         }
         debug!("Loading from code: {:?}", dir);
-        match find_locs_dir(&dir, &mut loaded_dirs, &mut locs) {
+        match find_code_files_dir(dir, &mut loaded_dirs, &mut files) {
             false => {},
-            true => return Err(LoadError::new("encountered errors while finding locations".to_string())),
+            true => return Err(LoadError::new("encountered errors while finding code files".to_string())),
         }
     }
+    Ok(files)
+}
+
+/// search through the code_paths in settings to find all valid locs
+/// partof: #SPC-loc
+pub fn find_locs(settings: &Settings) -> LoadResult<HashMap<ArtName, Loc>> {
+    info!("parsing code files for artifacts...");
+    let mut locs: HashMap<ArtName, Loc> = HashMap::new();
+    let mut error = false;
+    for path in try!(find_code_files(settings)) {
+        match find_locs_file(&path, &mut locs) {
+            true => error = true,
+            false => {},
+        }
+    }
+    if error {
+        return Err(LoadError::new("encountered errors while finding locations".to_string()));
+    }
     Ok(locs)
 }
 
@@ -199,3 +212,14 @@ pub fn attach_locs(artifacts: &mut Artifacts, locs: &HashMap<ArtName, Loc>) {
         artifact.loc = Some(loc.clone());
     }
 }
+
+/// get the locations of artifacts that do not exist, sorted by where they
+/// are. Must be called after linking so that auto-created parents exist
+pub fn dangling_locs(artifacts: &Artifacts, locs: &HashMap<ArtName, Loc>) -> Vec<(ArtName, Loc)> {
+    let mut dangling: Vec<(ArtName, Loc)> = locs.iter()
+        .filter(|&(n, _)| !artifacts.contains_key(n))
+        .map(|(n, l)| (n.clone(), l.clone()))
+        .collect();
+    dangling.sort_by(|a, b| (&a.1.path, a.1.line_col).cmp(&(&b.1.path, b.1.line_col)));
+    dangling
+}
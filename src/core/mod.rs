@@ -1,9 +1,10 @@
 use std::path::Path;
 
-use time;
+use project::Project;
 
 // General
 pub mod types;
+mod prelude;
 
 // for loading
 pub mod utils;
@@ -11,6 +12,8 @@ pub mod utils;
 pub mod vars;
 pub mod link;
 pub mod locs;
+pub mod save;
+pub mod review;
 
 #[cfg(test)]
 mod tests;
@@ -22,9 +25,40 @@ pub use core::types::{
     Artifact, Artifacts,
     ArtType, Loc,
     ArtName, ArtNameRc, ArtNames,
-    Settings, LoadFromStr};
+    Settings, View, LoadFromStr};
 pub use core::load::load_toml;
 
+/// a new directory in the temp directory for a test, which is removed when
+/// it is dropped. The process id is part of the name so that the tests of
+/// concurrent runs do not share it
+#[cfg(test)]
+pub struct TempDir(::std::path::PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let dir = ::std::env::temp_dir().join(format!("{}-{}", name, ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&dir);
+        ::std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+#[cfg(test)]
+impl ::std::ops::Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = ::std::fs::remove_dir_all(&self.0);
+    }
+}
+
 #[cfg(test)]
 use super::init_logger;
 #[cfg(test)]
@@ -39,26 +73,11 @@ pub fn init_logger_test() {
 
 /// do all core loading operations defined in SPC-core-load-parts
 /// includes loading and validating raw data, resolving and applying
-/// variables, and linking artifacts
+/// variables, and linking artifacts. See `Project` for the typed errors
 /// LOC-core-load-path
 pub fn load_path(path: &Path) -> LoadResult<(Artifacts, Settings)>{
-    let start = time::get_time();
-    info!("loading path: {}", path.to_string_lossy().as_ref());
-    let (mut artifacts, mut settings, loaded_vars, mut repo_map) = try!(load::load_raw(path));
-
-    info!("resolving and filling variables");
-    let mut variables = try!(vars::resolve_loaded_vars(loaded_vars, &mut repo_map));
-    try!(vars::fill_text_fields(&mut artifacts, &mut variables, &mut repo_map));
-
-    info!("finding and attaching locations");
-    let locs = try!(locs::find_locs(&mut settings));
-    locs::attach_locs(&mut artifacts, &locs);
-
-    // do all links
-    try!(link::do_links(&mut artifacts));
-    let total = time::get_time() - start;
-    info!("Done loading: {} artifacts loaded successfullly in {:.3} seconds",
-          artifacts.len(), total.num_milliseconds() as f64 * 1e-3);
-    Ok((artifacts, settings))
+    match Project::from_path(path) {
+        Ok(p) => Ok(p.into_parts()),
+        Err(e) => Err(LoadError::from(e)),
+    }
 }
-
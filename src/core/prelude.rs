@@ -0,0 +1,18 @@
+//! the types used throughout core, including the std items which are
+//! private to the crate
+
+// Traits
+pub use std::io::{Read, Write};
+pub use std::fmt::Write as WriteStr;
+pub use std::iter::FromIterator;
+pub use std::clone::Clone;
+pub use std::convert::AsRef;
+pub use std::str::FromStr;
+
+// stdlib
+pub use std::fs;
+pub use std::path::{Path, PathBuf};
+pub use std::collections::{HashMap, HashSet, VecDeque};
+pub use std::sync::Arc;
+
+pub use core::types::*;
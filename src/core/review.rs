@@ -0,0 +1,264 @@
+//! the reviews of the links between artifacts, kept in `.rst/reviewed.toml`,
+//! and the approvals of artifacts, kept in `.rst/approvals.toml`
+//!
+//! When the text of a parent changes, the artifacts that are a partof it may
+//! no longer satisfy it. The hash of the parent's text is stored when a link
+//! is reviewed, and the link is "suspect" while the hash is different.
+//! Approvals store a hash of the approved text, partof and status in the same
+//! way.
+
+use super::prelude::*;
+use super::load;
+
+use toml::Value;
+
+/// the file in the settings directory that stores the reviewed hashes
+pub const REVIEWED_FILE: &'static str = "reviewed.toml";
+
+/// the file in the settings directory that stores the approvals
+pub const APPROVALS_FILE: &'static str = "approvals.toml";
+
+/// the files in the settings directory which are written by rst and are
+/// not loaded as artifacts
+const REVIEW_FILES: [&'static str; 2] = [REVIEWED_FILE, APPROVALS_FILE];
+
+/// whether the path is a file written by rst into the `.rst` directory,
+/// which is skipped when loading the artifacts
+pub fn is_review_file(path: &Path) -> bool {
+    let in_cfg = path.parent()
+        .and_then(|p| p.file_name())
+        .map_or(false, |n| n == ".rst");
+    in_cfg && path.file_name().map_or(false, |n| REVIEW_FILES.iter().any(|f| n == *f))
+}
+
+/// a hash of the text which is stable between versions of rst and rust
+/// (64 bit FNV-1a). Trailing whitespace is ignored so that reformatting
+/// does not make the links suspect
+/// partof: #SPC-review-hash
+pub fn hash_text(text: &str) -> String {
+    let lines: Vec<&str> = text.trim().lines().map(|l| l.trim_right()).collect();
+    fnv_hash(&lines.join("\n"))
+}
+
+fn fnv_hash(text: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in text.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// a hash of the attributes that define an artifact: its text (as hashed by
+/// `hash_text`), its partof and its status. Links are only made suspect by
+/// a change of the text, but an approval is stale after any of them change
+/// partof: #SPC-approve-stale
+pub fn hash_approval(artifact: &Artifact) -> String {
+    let mut partof: Vec<String> = artifact.partof.iter().map(|n| n.value.join("-")).collect();
+    partof.sort();
+    fnv_hash(&format!("{}\n{}\n{}", artifact.hash, partof.join(", "),
+                      artifact.status.as_ref().map_or("", |s| s.as_str())))
+}
+
+/// load the reviewed hashes from the settings directory, which has none
+/// if the file does not exist
+pub fn load_reviewed(cfg: &Path) -> LoadResult<Reviewed> {
+    let path = cfg.join(REVIEWED_FILE);
+    if !path.exists() {
+        return Ok(Reviewed::new());
+    }
+    let text = try!(super::save::read_file(&path));
+    parse_reviewed(&text).map_err(|e| LoadError::new(format!("{}: {}", path.display(), e.desc)))
+}
+
+/// parse the text of the reviewed file, which has a table for each child
+/// mapping its parents to their hashes
+pub fn parse_reviewed(text: &str) -> LoadResult<Reviewed> {
+    let table = try!(load::parse_toml(text));
+    let mut reviewed = Reviewed::new();
+    for (child, parents) in &table {
+        let child = try!(ArtNameRc::from_str(child));
+        let parents = match *parents {
+            Value::Table(ref t) => t,
+            _ => return Err(LoadError::new(format!("{} must be a table", child))),
+        };
+        let mut hashes = HashMap::new();
+        for (parent, hash) in parents {
+            let parent = try!(ArtNameRc::from_str(parent));
+            match *hash {
+                Value::String(ref h) => hashes.insert(parent, h.clone()),
+                _ => {
+                    return Err(LoadError::new(format!("the hash of {} in {} must be a string",
+                                                      parent, child)))
+                }
+            };
+        }
+        reviewed.insert(child, hashes);
+    }
+    Ok(reviewed)
+}
+
+/// format the reviewed hashes as they are written to the file, sorted so
+/// that the file only changes where the reviews do
+pub fn fmt_reviewed(reviewed: &Reviewed) -> String {
+    let mut out = String::new();
+    out.push_str("# the hash of the text of each parent as of the last review of the link.\n\
+                  # This file is written by `rst review`\n");
+    let mut children: Vec<_> = reviewed.iter().filter(|&(_, p)| !p.is_empty()).collect();
+    children.sort_by(|a, b| a.0.cmp(b.0));
+    for (child, parents) in children {
+        write!(out, "\n[{}]\n", child.raw).unwrap();
+        let mut parents: Vec<_> = parents.iter().collect();
+        parents.sort_by(|a, b| a.0.cmp(b.0));
+        for (parent, hash) in parents {
+            write!(out, "{} = \"{}\"\n", parent.raw, hash).unwrap();
+        }
+    }
+    out
+}
+
+/// mark the links whose parent's text changed since they were reviewed.
+/// Links which were never reviewed are not suspect
+/// partof: #SPC-review-suspect
+pub fn set_suspects(artifacts: &mut Artifacts, reviewed: &Reviewed) {
+    let hashes: HashMap<ArtNameRc, String> = artifacts.iter()
+        .map(|(n, a)| (n.clone(), a.hash.clone()))
+        .collect();
+    for (name, artifact) in artifacts.iter_mut() {
+        let reviews = match reviewed.get(name) {
+            Some(r) => r,
+            None => continue,
+        };
+        for parent in &artifact.partof {
+            match (reviews.get(parent), hashes.get(parent)) {
+                (Some(old), Some(new)) if old != new => {
+                    artifact.suspect.insert(parent.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// record the current text of the parents of an artifact as reviewed.
+/// Parents which were created automatically have no text to review
+/// partof: #SPC-review-links
+pub fn review_links(artifacts: &Artifacts, reviewed: &mut Reviewed, name: &ArtNameRc) {
+    let artifact = &artifacts[name];
+    // the names of the artifacts are used since the names in partof can be
+    // written differently, i.e. when they are linked automatically
+    let hashes = artifact.partof.iter()
+        .filter_map(|p| artifacts.get_key_value(p))
+        .filter(|&(_, a)| a.path != PathBuf::from("PARENT"))
+        .map(|(p, a)| (p.clone(), a.hash.clone()))
+        .collect();
+    reviewed.insert(name.clone(), hashes);
+}
+
+/// remove the reviews of links which no longer exist
+pub fn prune_reviewed(artifacts: &Artifacts, reviewed: &mut Reviewed) {
+    reviewed.retain(|child, parents| match artifacts.get(child) {
+        Some(a) => {
+            parents.retain(|p, _| a.partof.contains(p));
+            !parents.is_empty()
+        }
+        None => false,
+    });
+}
+
+/// load the approvals from the settings directory, which has none if the
+/// file does not exist
+pub fn load_approvals(cfg: &Path) -> LoadResult<Approvals> {
+    let path = cfg.join(APPROVALS_FILE);
+    if !path.exists() {
+        return Ok(Approvals::new());
+    }
+    let text = try!(super::save::read_file(&path));
+    parse_approvals(&text).map_err(|e| LoadError::new(format!("{}: {}", path.display(), e.desc)))
+}
+
+/// parse the text of the approvals file, which has a table for each
+/// approved artifact
+pub fn parse_approvals(text: &str) -> LoadResult<Approvals> {
+    let table = try!(load::parse_toml(text));
+    let mut approvals = Approvals::new();
+    for (name, value) in &table {
+        let name = try!(ArtNameRc::from_str(name));
+        let get = |attr: &str| match *value {
+            Value::Table(ref t) => match t.get(attr) {
+                Some(&Value::String(ref s)) => Ok(s.clone()),
+                _ => Err(LoadError::new(format!("the approval of {} must have the string {}",
+                                                name, attr))),
+            },
+            _ => Err(LoadError::new(format!("{} must be a table", name))),
+        };
+        let approval = Approval {
+            by: try!(get("by")),
+            at: try!(get("at")),
+            hash: try!(get("hash")),
+        };
+        approvals.insert(name, approval);
+    }
+    Ok(approvals)
+}
+
+/// format the approvals as they are written to the file, sorted by name
+pub fn fmt_approvals(approvals: &Approvals) -> String {
+    let mut out = String::new();
+    out.push_str("# the approvals of the artifacts. An approval is stale when the text,\n\
+                  # partof or status of the artifact changes. This file is written by\n\
+                  # `rst approve`\n");
+    let mut names: Vec<_> = approvals.iter().collect();
+    names.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, approval) in names {
+        write!(out, "\n[{}]\nby = {}\nat = {}\nhash = {}\n", name.raw,
+               Value::String(approval.by.clone()),
+               Value::String(approval.at.clone()),
+               Value::String(approval.hash.clone())).unwrap();
+    }
+    out
+}
+
+/// attach the approvals to the artifacts they are for
+pub fn set_approvals(artifacts: &mut Artifacts, approvals: &Approvals) {
+    for (name, approval) in approvals {
+        if let Some(artifact) = artifacts.get_mut(name) {
+            artifact.approval = Some(approval.clone());
+        }
+    }
+}
+
+/// whether the artifact is approved, and if it changed since
+pub fn approval_state(artifact: &Artifact) -> ApprovalState {
+    match artifact.approval {
+        None => ApprovalState::Missing,
+        Some(ref a) if a.hash != hash_approval(artifact) => ApprovalState::Stale,
+        Some(_) => ApprovalState::Approved,
+    }
+}
+
+/// record the approval of the current version of an artifact. Artifacts
+/// which were created automatically have nothing to approve
+pub fn approve(artifacts: &Artifacts, approvals: &mut Approvals, name: &ArtNameRc, by: &str,
+               at: &str)
+               -> LoadResult<Approval> {
+    let artifact = match artifacts.get(name) {
+        Some(a) => a,
+        None => return Err(LoadError::new(format!("{} does not exist", name))),
+    };
+    if artifact.path == PathBuf::from("PARENT") {
+        return Err(LoadError::new(format!("{} is not defined in a file", name)));
+    }
+    let approval = Approval {
+        by: by.to_string(),
+        at: at.to_string(),
+        hash: hash_approval(artifact),
+    };
+    approvals.insert(name.clone(), approval.clone());
+    Ok(approval)
+}
+
+/// remove the approvals of artifacts which no longer exist
+pub fn prune_approvals(artifacts: &Artifacts, approvals: &mut Approvals) {
+    approvals.retain(|name, _| artifacts.contains_key(name));
+}
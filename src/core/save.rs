@@ -0,0 +1,836 @@
+//! savers
+//! editing and canonical formatting of artifact toml files
+//!
+//! the toml library throws away comments and formatting, so files are split
+//! into tables of raw lines instead. Unchanged lines are written back exactly
+//! as they were loaded.
+
+use super::prelude::*;
+use super::load;
+use super::link;
+
+use toml::{Value, Table};
+use regex::Regex;
+use std::cmp;
+
+lazy_static!{
+    static ref HEADER_RE: Regex = Regex::new(
+        r#"^\s*\[\s*("[^"]*"|'[^']*'|[^\[\]'"\s]+)\s*\]\s*(#.*)?$"#).unwrap();
+    static ref KEY_RE: Regex = Regex::new(
+        r#"^\s*("[^"]*"|'[^']*'|[A-Za-z0-9_-]+)\s*="#).unwrap();
+
+    /// tables that are not artifacts
+    pub static ref SPECIAL_TABLES: HashSet<String> = HashSet::from_iter(
+        ["settings", "globals", "views"].iter().map(|s| s.to_string()));
+}
+
+/// a line or group of lines in the body of a table
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// a `key = value` pair, which may span multiple lines
+    Key(String, Vec<String>),
+    /// a comment or blank line
+    Other(String),
+}
+
+/// a table in a toml file and the comments and blank lines above it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileTable {
+    pub comments: Vec<String>,
+    pub header: String,
+    pub name: String,
+    pub items: Vec<Item>,
+}
+
+/// the text of a toml file split into its tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileText {
+    pub tables: Vec<FileTable>,
+    /// comments and blank lines after the last table
+    pub end: Vec<String>,
+}
+
+fn strip_quotes(s: &str) -> String {
+    if s.len() >= 2 && (s.starts_with('"') || s.starts_with('\'')) {
+        s[1..s.len() - 1].to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn is_other(line: &str) -> bool {
+    let l = line.trim();
+    l == "" || l.starts_with('#')
+}
+
+/// return the multi-line string delimiter that is left open by `s`, if any
+fn open_delim(s: &str, open: Option<&'static str>) -> Option<&'static str> {
+    let mut open = open;
+    let mut s = s;
+    loop {
+        match open {
+            Some(d) => {
+                match s.find(d) {
+                    Some(i) => {
+                        s = &s[i + 3..];
+                        open = None;
+                    }
+                    None => return open,
+                }
+            }
+            None => {
+                let lit = s.find("'''");
+                let basic = s.find("\"\"\"");
+                let (i, d) = match (lit, basic) {
+                    (Some(l), Some(b)) => if l < b { (l, "'''") } else { (b, "\"\"\"") },
+                    (Some(l), None) => (l, "'''"),
+                    (None, Some(b)) => (b, "\"\"\""),
+                    (None, None) => return None,
+                };
+                s = &s[i + 3..];
+                open = Some(d);
+            }
+        }
+    }
+}
+
+/// the number of `[` left open by `s`, ignoring brackets inside of strings
+fn bracket_depth(s: &str, depth: i64) -> i64 {
+    let mut depth = depth;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in s.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                match c {
+                    '"' | '\'' => quote = Some(c),
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    '#' => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+    depth
+}
+
+/// the index of the `#` that starts a comment in `s`, ignoring any inside
+/// of strings
+fn comment_start(s: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                match c {
+                    '"' | '\'' => quote = Some(c),
+                    '#' => return Some(i),
+                    _ => {}
+                }
+            }
+        }
+    }
+    None
+}
+
+impl FileText {
+    /// split the text into tables. `to_string` on the result returns the
+    /// original text
+    /// partof: #SPC-save-parse
+    pub fn from_str(text: &str) -> LoadResult<FileText> {
+        let mut out = FileText {
+            tables: Vec::new(),
+            end: Vec::new(),
+        };
+        // lines that are not yet known to be part of a table
+        let mut pending: Vec<String> = Vec::new();
+        let mut open: Option<&'static str> = None;
+        let mut depth = 0;
+        for line in text.split('\n') {
+            if open.is_some() || depth > 0 {
+                // continuation of a multi-line key
+                let table = out.tables.last_mut().unwrap();
+                match table.items.last_mut() {
+                    Some(&mut Item::Key(_, ref mut lines)) => lines.push(line.to_string()),
+                    _ => unreachable!(),
+                }
+                if open.is_some() {
+                    open = open_delim(line, open);
+                } else {
+                    depth = bracket_depth(line, depth);
+                }
+                continue;
+            }
+            if is_other(line) {
+                pending.push(line.to_string());
+                continue;
+            }
+            if let Some(caps) = HEADER_RE.captures(line) {
+                // comments directly above the header belong to it
+                out.tables.push(FileTable {
+                    comments: pending.drain(..).collect(),
+                    header: line.to_string(),
+                    name: strip_quotes(caps.at(1).unwrap()),
+                    items: Vec::new(),
+                });
+                continue;
+            }
+            let key = match KEY_RE.captures(line) {
+                Some(caps) => strip_quotes(caps.at(1).unwrap()),
+                None => return Err(LoadError::new(format!("invalid line: {}", line))),
+            };
+            let table = match out.tables.last_mut() {
+                Some(t) => t,
+                None => {
+                    return Err(LoadError::new(format!("key outside of a table: {}", key)));
+                }
+            };
+            table.items.extend(pending.drain(..).map(|l| Item::Other(l)));
+            let value = &line[line.find('=').unwrap() + 1..];
+            open = open_delim(value, None);
+            if open.is_none() {
+                depth = bracket_depth(value, 0);
+            }
+            table.items.push(Item::Key(key, vec![line.to_string()]));
+        }
+        if open.is_some() || depth > 0 {
+            return Err(LoadError::new("unclosed value at end of file".to_string()));
+        }
+        out.end = pending;
+        Ok(out)
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut lines: Vec<&str> = Vec::new();
+        for table in &self.tables {
+            table.push_lines(&mut lines);
+        }
+        lines.extend(self.end.iter().map(|l| l.as_str()));
+        lines.join("\n")
+    }
+
+    /// get the line number (starting at 0) of the header of every table
+    pub fn header_lines(&self) -> Vec<usize> {
+        let mut out = Vec::new();
+        let mut line = 0;
+        for table in &self.tables {
+            let mut lines: Vec<&str> = Vec::new();
+            table.push_lines(&mut lines);
+            out.push(line + table.comments.len());
+            line += lines.len();
+        }
+        out
+    }
+
+    /// get the index of the artifact table with the given name
+    pub fn find(&self, name: &ArtName) -> Option<usize> {
+        self.tables.iter().position(|t| match ArtName::from_str(&t.name) {
+            Ok(ref n) => n == name,
+            Err(_) => false,
+        })
+    }
+}
+
+impl FileTable {
+    fn push_lines<'a>(&'a self, lines: &mut Vec<&'a str>) {
+        lines.extend(self.comments.iter().map(|l| l.as_str()));
+        lines.push(&self.header);
+        for item in &self.items {
+            match *item {
+                Item::Key(_, ref klines) => lines.extend(klines.iter().map(|l| l.as_str())),
+                Item::Other(ref l) => lines.push(l),
+            }
+        }
+    }
+
+    pub fn is_artifact(&self) -> bool {
+        !SPECIAL_TABLES.contains(&self.name)
+    }
+
+    /// get the raw lines of a key
+    pub fn get_key(&self, key: &str) -> Option<&Vec<String>> {
+        for item in &self.items {
+            if let Item::Key(ref k, ref lines) = *item {
+                if k == key {
+                    return Some(lines);
+                }
+            }
+        }
+        None
+    }
+
+    /// get the comment after the value of a key, if it has one
+    pub fn key_comment(&self, key: &str) -> Option<String> {
+        let lines = match self.get_key(key) {
+            Some(l) => l,
+            None => return None,
+        };
+        let last = lines.last().unwrap();
+        let start = if lines.len() == 1 {
+            last.find('=').unwrap() + 1
+        } else {
+            // the last line of a multi-line string starts inside of it
+            match (last.rfind("'''"), last.rfind("\"\"\"")) {
+                (Some(l), Some(b)) => cmp::max(l, b) + 3,
+                (Some(i), None) | (None, Some(i)) => i + 3,
+                (None, None) => 0,
+            }
+        };
+        comment_start(&last[start..]).map(|i| last[start + i..].trim_right().to_string())
+    }
+
+    /// get the number of lines between the header and the first line of a key
+    pub fn key_offset(&self, key: &str) -> Option<usize> {
+        let mut offset = 1;
+        for item in &self.items {
+            match *item {
+                Item::Key(ref k, _) if k == key => return Some(offset),
+                Item::Key(_, ref klines) => offset += klines.len(),
+                Item::Other(_) => offset += 1,
+            }
+        }
+        None
+    }
+
+    /// get the parsed value of a key
+    pub fn get_value(&self, key: &str) -> LoadResult<Option<Value>> {
+        let lines = match self.get_key(key) {
+            Some(l) => l,
+            None => return Ok(None),
+        };
+        let mut tbl = try!(load::parse_toml(&lines.join("\n")));
+        Ok(tbl.remove(key))
+    }
+
+    /// get the parsed string value of a key
+    pub fn get_str(&self, key: &str) -> LoadResult<Option<String>> {
+        match try!(self.get_value(key)) {
+            Some(Value::String(s)) => Ok(Some(s)),
+            None => Ok(None),
+            Some(_) => Err(LoadError::new(format!("{} has invalid attribute: {}",
+                                                  self.name, key))),
+        }
+    }
+
+    /// set the value of a key, replacing it if it exists or adding it at the
+    /// end of the table if it does not. The value must already be formatted.
+    /// A comment after the old value is kept after the new one.
+    pub fn set_key(&mut self, key: &str, value: &str) {
+        let mut text = format!("{} = {}", key, value);
+        if let Some(comment) = self.key_comment(key) {
+            text = format!("{}  {}", text, comment);
+        }
+        let lines: Vec<String> = text.split('\n')
+            .map(|l| l.to_string())
+            .collect();
+        for item in self.items.iter_mut() {
+            if let Item::Key(ref k, ref mut klines) = *item {
+                if k == key {
+                    *klines = lines;
+                    return;
+                }
+            }
+        }
+        // insert after the last key so trailing blank lines stay at the end
+        let pos = match self.items.iter().rposition(|i| match *i {
+            Item::Key(..) => true,
+            Item::Other(ref l) => l.trim() != "",
+        }) {
+            Some(p) => p + 1,
+            None => 0,
+        };
+        self.items.insert(pos, Item::Key(key.to_string(), lines));
+    }
+
+    /// remove a key from the table
+    pub fn remove_key(&mut self, key: &str) {
+        self.items.retain(|i| match *i {
+            Item::Key(ref k, _) => k != key,
+            Item::Other(_) => true,
+        });
+    }
+
+    /// rename the table, keeping any comment after the header
+    pub fn rename(&mut self, name: &ArtName) {
+        let header = match HEADER_RE.captures(&self.header) {
+            Some(caps) => {
+                let (start, end) = caps.pos(1).unwrap();
+                format!("{}{}{}", &self.header[..start], name.raw, &self.header[end..])
+            }
+            None => format!("[{}]", name.raw),
+        };
+        self.header = header;
+        self.name = name.raw.clone();
+    }
+
+    /// set the partof attribute, removing it if there are no names
+    pub fn set_partof(&mut self, partof: &ArtNames) {
+        if partof.len() == 0 {
+            self.remove_key("partof");
+        } else {
+            self.set_key("partof", &format!("\"{}\"", fmt_names_str(partof)));
+        }
+    }
+
+    /// parse the whole table
+    pub fn get_table(&self) -> LoadResult<Table> {
+        let mut lines: Vec<&str> = Vec::new();
+        for item in &self.items {
+            if let Item::Key(_, ref klines) = *item {
+                lines.extend(klines.iter().map(|l| l.as_str()));
+            }
+        }
+        load::parse_toml(&lines.join("\n"))
+    }
+}
+
+/// format an artifact name in its canonical form: the type in upper case
+/// and the rest in lower case
+pub fn fmt_name(name: &ArtName) -> String {
+    let mut out = name.value[0].clone();
+    for v in name.value.iter().skip(1) {
+        out.push('-');
+        out.push_str(&v.to_lowercase());
+    }
+    out
+}
+
+/// collapse segments into as few names-str items as possible,
+/// segments must be sorted
+fn collapse_segments(names: &[&[String]]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < names.len() {
+        let first = &names[i][0];
+        let mut j = i;
+        while j < names.len() && &names[j][0] == first {
+            j += 1;
+        }
+        let has_self = names[i..j].iter().any(|n| n.len() == 1);
+        let rest: Vec<&[String]> = names[i..j].iter()
+            .filter(|n| n.len() > 1)
+            .map(|n| &n[1..])
+            .collect();
+        if has_self {
+            out.push(first.clone());
+        }
+        if rest.len() > 0 {
+            let sub = collapse_segments(&rest);
+            if sub.len() == 1 {
+                out.push(format!("{}-{}", first, sub[0]));
+            } else {
+                out.push(format!("{}-[{}]", first, sub.join(", ")));
+            }
+        }
+        i = j;
+    }
+    out
+}
+
+/// format names into a canonical names-str, collapsing names into the
+/// bracket form where possible. This is the reverse of `ArtNames::from_str`
+/// partof: #SPC-save-names
+pub fn fmt_names_str(names: &ArtNames) -> String {
+    let mut sorted: Vec<&ArtNameRc> = names.iter().collect();
+    sorted.sort();
+    let segments: Vec<Vec<String>> = sorted.iter()
+        .map(|n| fmt_name(n).split('-').map(|s| s.to_string()).collect())
+        .collect();
+    let refs: Vec<&[String]> = segments.iter().map(|s| s.as_slice()).collect();
+    collapse_segments(&refs).join(", ")
+}
+
+/// format a text value, using a multi-line literal string if the
+/// text has more than one line. A literal string cannot end with a quote,
+/// which would be read as one of its closing quotes
+pub fn fmt_text(text: &str) -> String {
+    let literal_ok = !text.contains("'''") && !text.ends_with('\'') &&
+                     !text.chars().any(|c| c.is_control() && c != '\n' && c != '\t');
+    if text.contains('\n') && literal_ok {
+        format!("'''\n{}'''", text)
+    } else {
+        Value::String(text.to_string()).to_string()
+    }
+}
+
+/// write a formatted key, keeping the comment that was after its value
+fn push_key(out: &mut String, table: &FileTable, key: &str, value: &str) {
+    write!(out, "{} = {}", key, value).unwrap();
+    if let Some(comment) = table.key_comment(key) {
+        write!(out, "  {}", comment).unwrap();
+    }
+    out.push('\n');
+}
+
+/// format a single artifact table with its attributes in canonical order
+fn fmt_artifact_table(table: &FileTable) -> LoadResult<String> {
+    let name = try!(ArtName::from_str(&table.name));
+    let mut out = String::new();
+    let comments: Vec<&str> = table.comments.iter()
+        .map(|c| c.trim_right())
+        .skip_while(|c| *c == "")
+        .collect();
+    for c in comments {
+        out.push_str(c);
+        out.push('\n');
+    }
+    write!(out, "[{}]", fmt_name(&name)).unwrap();
+    if let Some(caps) = HEADER_RE.captures(&table.header) {
+        if let Some(comment) = caps.at(2) {
+            write!(out, " {}", comment).unwrap();
+        }
+    }
+    out.push('\n');
+    // comments inside the table are kept above the attributes
+    for item in &table.items {
+        if let Item::Other(ref l) = *item {
+            if l.trim() != "" {
+                out.push_str(l.trim_right());
+                out.push('\n');
+            }
+        }
+    }
+    if let Some(v) = try!(table.get_value("disabled")) {
+        match v {
+            Value::Boolean(b) => push_key(&mut out, table, "disabled", &b.to_string()),
+            _ => return Err(LoadError::new(format!("{} has invalid attribute: disabled", name))),
+        }
+    }
+    if let Some(st) = try!(table.get_str("status")) {
+        push_key(&mut out, table, "status", &format!("\"{}\"", st));
+    }
+    if let Some(p) = try!(table.get_str("partof")) {
+        let partof = try!(ArtNames::from_str(&p));
+        if partof.len() > 0 {
+            push_key(&mut out, table, "partof", &format!("\"{}\"", fmt_names_str(&partof)));
+        }
+    }
+    if let Some(t) = try!(table.get_str("text")) {
+        if t != "" {
+            push_key(&mut out, table, "text", &fmt_text(&t));
+        }
+    }
+    // unknown attributes are kept as they are
+    for item in &table.items {
+        if let Item::Key(ref k, ref lines) = *item {
+            if !load::ARTIFACT_ATTRS.contains(k) {
+                for l in lines {
+                    out.push_str(l);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// format the text of an artifact file in the canonical format:
+///  - settings and globals are kept as they are at the top of the file
+///  - artifacts are sorted by name
+///  - artifact names and partof are formatted with `fmt_name`
+///    and `fmt_names_str`
+///  - attributes are ordered `disabled`, `status`, `partof`, `text`
+///  - comments are kept with the table they are above, and after the
+///    value they follow
+/// partof: #SPC-save-fmt
+pub fn fmt_file(text: &str) -> LoadResult<String> {
+    let file = try!(FileText::from_str(text));
+    let mut blocks: Vec<String> = Vec::new();
+    let mut artifacts: Vec<(ArtName, &FileTable)> = Vec::new();
+    for table in &file.tables {
+        if table.is_artifact() {
+            artifacts.push((try!(ArtName::from_str(&table.name)), table));
+        } else {
+            let mut lines = Vec::new();
+            table.push_lines(&mut lines);
+            let block = lines.join("\n");
+            blocks.push(block.trim().to_string() + "\n");
+        }
+    }
+    artifacts.sort_by(|a, b| a.0.cmp(&b.0));
+    for &(_, table) in &artifacts {
+        blocks.push(try!(fmt_artifact_table(table)));
+    }
+    let mut out = blocks.join("\n");
+    let end: Vec<&str> = file.end.iter().map(|l| l.trim_right()).collect();
+    let end = end.join("\n");
+    if end.trim() != "" {
+        out.push('\n');
+        out.push_str(end.trim());
+        out.push('\n');
+    }
+    try!(verify_same(text, &out));
+    Ok(out)
+}
+
+/// format a new artifact table
+pub fn fmt_artifact(name: &ArtName, partof: &ArtNames, text: &str) -> String {
+    let mut out = format!("[{}]\n", fmt_name(name));
+    if partof.len() > 0 {
+        write!(out, "partof = \"{}\"\n", fmt_names_str(partof)).unwrap();
+    }
+    if text != "" {
+        write!(out, "text = {}\n", fmt_text(text)).unwrap();
+    }
+    out
+}
+
+/// get the files that artifacts were loaded from, sorted
+pub fn artifact_files(artifacts: &Artifacts) -> Vec<PathBuf> {
+    let parent = PathBuf::from("PARENT");
+    let files: HashSet<&PathBuf> = artifacts.values()
+        .map(|a| &a.path)
+        .filter(|p| **p != parent)
+        .collect();
+    let mut files: Vec<PathBuf> = files.into_iter().cloned().collect();
+    files.sort();
+    files
+}
+
+pub fn read_file(path: &Path) -> LoadResult<String> {
+    let mut text = String::new();
+    match fs::File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+        Ok(_) => Ok(text),
+        Err(e) => Err(LoadError::new(format!("{}: {}", path.display(), e))),
+    }
+}
+
+/// a file that will be changed, with its original and new text
+pub type Change = (PathBuf, String, String);
+
+/// get the text of the files as it will be after the changes
+pub fn changed_texts(files: &[PathBuf], changes: &[Change]) -> LoadResult<Vec<(PathBuf, String)>> {
+    let mut out = Vec::new();
+    for path in files {
+        let text = match changes.iter().find(|c| c.0 == *path) {
+            Some(c) => c.2.clone(),
+            None => try!(read_file(path)),
+        };
+        out.push((path.clone(), text));
+    }
+    Ok(out)
+}
+
+/// write the new text of files given as (path, original, new). If any write
+/// fails the files already written are restored to their original text
+pub fn write_files(files: &[Change]) -> LoadResult<()> {
+    for (i, &(ref path, _, ref new)) in files.iter().enumerate() {
+        let result = fs::File::create(path).and_then(|mut f| f.write_all(new.as_bytes()));
+        if let Err(e) = result {
+            for &(ref path, ref original, _) in &files[..i] {
+                if let Err(e) = fs::File::create(path)
+                        .and_then(|mut f| f.write_all(original.as_bytes())) {
+                    error!("could not restore {}: {}", path.display(), e);
+                }
+            }
+            return Err(LoadError::new(format!("{}: {}", path.display(), e)));
+        }
+    }
+    Ok(())
+}
+
+/// load the artifacts from the text of files given as (path, text) and
+/// do the automatic links, giving the full link structure without
+/// touching the filesystem
+pub fn load_files(files: &[(PathBuf, String)]) -> LoadResult<Artifacts> {
+    let mut artifacts = Artifacts::new();
+    let mut settings: Vec<(PathBuf, Settings)> = Vec::new();
+    let mut variables: Vec<(PathBuf, Globals)> = Vec::new();
+    for &(ref path, ref text) in files {
+        if let Err(e) = load::load_toml(path, text, &mut artifacts, &mut settings,
+                                        &mut variables) {
+            return Err(LoadError::new(format!("{}: {}", path.display(), e)));
+        }
+    }
+    link::link_named_partofs(&mut artifacts);
+    link::create_parents(&mut artifacts);
+    link::link_parents(&mut artifacts);
+    try!(link::validate_partof(&artifacts));
+    Ok(artifacts)
+}
+
+fn load_text(text: &str) -> LoadResult<Artifacts> {
+    let mut artifacts = Artifacts::new();
+    let mut settings: Vec<(PathBuf, Settings)> = Vec::new();
+    let mut variables: Vec<(PathBuf, Globals)> = Vec::new();
+    try!(load::load_toml(&PathBuf::from("fmt"), text, &mut artifacts,
+                         &mut settings, &mut variables));
+    Ok(artifacts)
+}
+
+/// make sure that the formatted text loads the same artifacts as the
+/// original, with every attribute the same
+fn verify_same(original: &str, formatted: &str) -> LoadResult<()> {
+    let original = try!(load_text(original));
+    let formatted = match load_text(formatted) {
+        Ok(a) => a,
+        Err(e) => return Err(LoadError::new(format!("formatted text is invalid: {}", e))),
+    };
+    if original == formatted {
+        Ok(())
+    } else {
+        Err(LoadError::new("formatting changed the loaded artifacts".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEXT: &'static str = "\
+# header comment
+
+[settings]
+artifact_paths = [
+    '{repo}/reqs',  # comment ]
+]
+
+# about foo
+[spc-Foo] # trailing
+text = '''
+[REQ-not-a-table]
+'''
+partof = 'REQ-[foo, bar-[1, 2]]'
+
+[REQ-bar]
+# inner comment
+text = \"single line\"
+";
+
+    #[test]
+    fn test_file_text() {
+        let file = FileText::from_str(TEXT).unwrap();
+        assert_eq!(file.to_string(), TEXT);
+        assert_eq!(file.tables.len(), 3);
+        assert_eq!(file.tables[0].name, "settings");
+        assert_eq!(file.tables[0].comments, vec!["# header comment", ""]);
+        assert_eq!(file.tables[1].name, "spc-Foo");
+        assert_eq!(file.tables[1].comments, vec!["", "# about foo"]);
+        assert_eq!(file.tables[1].get_str("text").unwrap().unwrap(), "[REQ-not-a-table]\n");
+        assert_eq!(file.tables[2].items[0], Item::Other("# inner comment".to_string()));
+        assert_eq!(file.end, vec![""]);
+        assert_eq!(file.find(&ArtName::from_str("SPC-foo").unwrap()), Some(1));
+        assert_eq!(file.header_lines(), vec![2, 8, 14]);
+        assert_eq!(file.tables[1].key_offset("text"), Some(1));
+        assert_eq!(file.tables[1].key_offset("partof"), Some(4));
+        assert_eq!(file.tables[2].key_offset("text"), Some(2));
+        assert_eq!(file.tables[2].key_offset("partof"), None);
+
+        let mut table = file.tables[1].clone();
+        table.rename(&ArtName::from_str("SPC-bar").unwrap());
+        assert_eq!(table.header, "[SPC-bar] # trailing");
+        table.set_partof(&ArtNames::from_str("REQ-a, REQ-b").unwrap());
+        assert_eq!(table.get_str("partof").unwrap().unwrap(), "REQ-[a, b]");
+        table.set_partof(&ArtNames::new());
+        assert_eq!(table.get_key("partof"), None);
+
+        assert!(FileText::from_str("[REQ-foo]\ntext = '''\nnot closed").is_err());
+        assert!(FileText::from_str("text = 'no table'").is_err());
+    }
+
+    #[test]
+    /// partof: #TST-save-fmt
+    fn test_fmt_file() {
+        let expected = "\
+# header comment
+
+[settings]
+artifact_paths = [
+    '{repo}/reqs',  # comment ]
+]
+
+[REQ-bar]
+# inner comment
+text = \"single line\"
+
+# about foo
+[SPC-foo] # trailing
+partof = \"REQ-[bar-[1, 2], foo]\"
+text = '''
+[REQ-not-a-table]
+'''
+";
+        assert_eq!(fmt_file(TEXT).unwrap(), expected);
+        // formatting is idempotent
+        assert_eq!(fmt_file(expected).unwrap(), expected);
+
+        // comments after the values are kept
+        let text = "\
+[REQ-foo]
+text = '''
+multi # not a comment
+'''  # about text
+partof = \"REQ-bar\"  # keep me
+disabled = false # not yet
+status = 'todo # not a comment'
+";
+        let expected = "\
+[REQ-foo]
+disabled = false  # not yet
+status = \"todo # not a comment\"
+partof = \"REQ-bar\"  # keep me
+text = '''
+multi # not a comment
+'''  # about text
+";
+        assert_eq!(fmt_file(text).unwrap(), expected);
+        assert_eq!(fmt_file(expected).unwrap(), expected);
+
+        // a change to any attribute fails the check
+        let original = "[REQ-foo]\nstatus = 'draft'\ntext = 'foo'\n";
+        assert!(verify_same(original, "[REQ-foo]\ntext = 'foo'\nstatus = 'draft'\n").is_ok());
+        assert!(verify_same(original, "[REQ-foo]\ntext = 'foo'\n").is_err());
+        assert!(verify_same(original, "[REQ-foo]\nstatus = 'done'\ntext = 'foo'\n").is_err());
+    }
+
+    #[test]
+    /// partof: #TST-save-names
+    fn test_fmt_names_str() {
+        fn names(s: &str) -> String {
+            fmt_names_str(&ArtNames::from_str(s).unwrap())
+        }
+        assert_eq!(names(""), "");
+        assert_eq!(names("REQ-foo"), "REQ-foo");
+        assert_eq!(names("req-foo, REQ-BAR"), "REQ-[bar, foo]");
+        assert_eq!(names("REQ-2-ui, REQ-2-ui-cmd, REQ-2-ui-help"),
+                   "REQ-2-[ui, ui-[cmd, help]]");
+        assert_eq!(names("REQ-a-b-c, SPC-a"), "REQ-a-b-c, SPC-a");
+        assert_eq!(names("REQ-cmd, REQ-2-ui-link"), "REQ-[2-ui-link, cmd]");
+        // the result parses to the same names
+        let raw = "SPC-[foo-[1, 2, 3], bar], TST-x, TST-x-y";
+        assert_eq!(ArtNames::from_str(&names(raw)).unwrap(),
+                   ArtNames::from_str(raw).unwrap());
+    }
+
+    #[test]
+    fn test_fmt_text() {
+        assert_eq!(fmt_text("one line"), "\"one line\"");
+        assert_eq!(fmt_text("two\nlines\n"), "'''\ntwo\nlines\n'''");
+        assert_eq!(fmt_text("has '''\nquotes"), "\"has '''\\nquotes\"");
+        assert_eq!(fmt_text("ends with\n'quote'"), "\"ends with\\n'quote'\"");
+        // the formatted text loads back the same
+        for text in &["two\nlines\n", "ends with\n'quote'", "has '''\nquotes"] {
+            let value = load::parse_toml(&format!("text = {}", fmt_text(text))).unwrap();
+            assert_eq!(value["text"], Value::String(text.to_string()));
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::ascii::AsciiExt;
 
 use super::*;  // data directory constants
-use super::super::types::*;
+use super::super::prelude::*;
 use super::super::load::*;
 use super::super::link::*;
 
@@ -10,9 +10,9 @@ use super::super::link::*;
 fn test_basic_link() {
     let mut artifacts = Artifacts::new();
     let mut settings: Vec<(PathBuf, Settings)> = Vec::new();
-    let mut variables: Vec<(PathBuf, Variables)> = Vec::new();
+    let mut variables: Vec<(PathBuf, Globals)> = Vec::new();
     let path = PathBuf::from("hi/there");
-    let req_name = Rc::new(ArtNameRc::from_str("REQ-1").unwrap().parent().unwrap());
+    let req_name = Arc::new(ArtNameRc::from_str("REQ-1").unwrap().parent().unwrap());
 
     // get te artifacts
     let num = load_toml(&path, TOML_RST, &mut artifacts, &mut settings, &mut variables).unwrap();
@@ -85,9 +85,9 @@ fn test_basic_link() {
 fn test_link_completed_tested() {
     let mut artifacts = Artifacts::new();
     let mut settings: Vec<(PathBuf, Settings)> = Vec::new();
-    let mut variables: Vec<(PathBuf, Variables)> = Vec::new();
+    let mut variables: Vec<(PathBuf, Globals)> = Vec::new();
     let path = PathBuf::from("hi/there");
-    let req_name = Rc::new(ArtNameRc::from_str("REQ-1").unwrap().parent().unwrap());
+    let req_name = Arc::new(ArtNameRc::from_str("REQ-1").unwrap().parent().unwrap());
 
     let num = load_toml(&path, TOML_LINK, &mut artifacts, &mut settings, &mut variables).unwrap();
     for sname in &["SPC-core-bob-1", "TST-core-bob-1-a", "TST-core-bob-1-b-2",
@@ -196,3 +196,132 @@ fn test_invalid_partof() {
     let artifacts = load_toml_simple("[TST-foo]\npartof = 'REQ-bar'\n");
     assert!(validate_partof(&artifacts).is_err());
 }
+
+#[test]
+/// partof: #TST-artifact-status
+fn test_status() {
+    let mut artifacts = load_toml_simple("\
+        [REQ-foo]\n\
+        [SPC-foo-a]\n\
+        status = 'approved'\n\
+        [SPC-foo-b]\n\
+        status = 'obsolete'\n\
+        [SPC-foo-c]\n");
+    for sname in &["SPC-foo-a", "SPC-foo-c"] {
+        let art = artifacts.get_mut(&ArtNameRc::from_str(sname).unwrap()).unwrap();
+        art.loc = Some(Loc::fake());
+    }
+    do_links(&mut artifacts).unwrap();
+    // the obsolete SPC-foo-b is not done, but it is skipped
+    let spc = &artifacts[&ArtNameRc::from_str("SPC-foo").unwrap()];
+    assert_eq!(spc.completed, 1.);
+    assert!(is_obsolete(&artifacts[&ArtNameRc::from_str("SPC-foo-b").unwrap()]));
+
+    // the default statuses are used when there are none in the settings
+    let mut settings = Settings::new();
+    validate_status(&artifacts, &mut settings).unwrap();
+    assert_eq!(settings.statuses, vec!["draft", "approved", "obsolete"]);
+
+    settings.statuses = vec!["draft".to_string(), "approved".to_string()];
+    settings.transitions = vec![("draft".to_string(), "done".to_string())];
+    let err = validate_status(&artifacts, &mut settings).unwrap_err().desc;
+    assert_eq!(err, "invalid statuses, the statuses are: draft, approved\n\
+                     - [test] SPC-foo-b has unknown status \"obsolete\"\n\
+                     - the transition draft -> done uses the unknown status \"done\"");
+
+    // statuses must be strings and transitions must be `FROM -> TO`
+    assert!(Artifact::from_str("[REQ-foo]\nstatus = 1\n").is_err());
+    let settings = |text: &str| Settings::from_table(&parse_toml(text).unwrap());
+    assert_eq!(settings("transitions = ['a -> b', ' b->c']").unwrap().transitions,
+               vec![("a".to_string(), "b".to_string()), ("b".to_string(), "c".to_string())]);
+    assert!(settings("transitions = ['a b']").is_err());
+}
+
+#[test]
+/// partof: #TST-review
+fn test_suspect() {
+    use super::super::review::*;
+    let name = |n: &str| ArtNameRc::from_str(n).unwrap();
+    // the hash is stable and ignores trailing whitespace
+    assert_eq!(hash_text(""), "cbf29ce484222325");
+    assert_eq!(hash_text("foo\nbar"), hash_text("foo  \nbar\n"));
+    assert!(hash_text("foo bar") != hash_text("foo  bar"));
+    assert!(is_review_file(Path::new("repo/.rst/reviewed.toml")));
+    assert!(!is_review_file(Path::new("repo/reqs/reviewed.toml")));
+
+    let text = "\
+        [REQ-foo]\ntext = 'foo'\n\
+        [REQ-bar]\ntext = 'bar'\n\
+        [SPC-foo]\npartof = 'REQ-bar'\n";
+    let mut artifacts = load_toml_simple(text);
+    do_links(&mut artifacts).unwrap();
+    let mut reviewed = Reviewed::new();
+    review_links(&artifacts, &mut reviewed, &name("SPC-foo"));
+    // the automatic SPC parent is not reviewed
+    let mut parents: Vec<_> = reviewed[&name("SPC-foo")].keys().cloned().collect();
+    parents.sort();
+    assert_eq!(parents, vec![name("REQ-bar"), name("REQ-foo")]);
+    let written = fmt_reviewed(&reviewed);
+    assert_eq!(parse_reviewed(&written).unwrap(), reviewed);
+
+    // only the links whose parent changed are suspect
+    let mut artifacts = load_toml_simple(&text.replace("'bar'", "'bar changed'"));
+    do_links(&mut artifacts).unwrap();
+    set_suspects(&mut artifacts, &reviewed);
+    let suspect: Vec<_> = artifacts[&name("SPC-foo")].suspect.iter().cloned().collect();
+    assert_eq!(suspect, vec![name("REQ-bar")]);
+    assert!(artifacts[&name("REQ-bar")].suspect.is_empty());
+
+    // links that were removed are pruned
+    let mut artifacts = load_toml_simple("[REQ-foo]\n[REQ-bar]\n[SPC-bar]\n");
+    do_links(&mut artifacts).unwrap();
+    prune_reviewed(&artifacts, &mut reviewed);
+    assert!(reviewed.is_empty());
+    assert!(parse_reviewed("[SPC-foo]\nREQ-foo = 1\n").is_err());
+}
+
+#[test]
+/// partof: #TST-approve
+fn test_approvals() {
+    use super::super::review::*;
+    let name = |n: &str| ArtNameRc::from_str(n).unwrap();
+    let text = "[REQ-foo]\ntext = 'foo'\n[REQ-foo-bar]\n";
+    let mut artifacts = load_toml_simple(text);
+    do_links(&mut artifacts).unwrap();
+    let mut approvals = Approvals::new();
+    approve(&artifacts, &mut approvals, &name("REQ-foo"), "Jane \"JD\" Doe",
+            "2017-01-02T03:04:05Z").unwrap();
+    // the automatic REQ parent has no text to approve
+    assert!(approve(&artifacts, &mut approvals, &name("REQ"), "Jane", "").is_err());
+    assert_eq!(approvals[&name("REQ-foo")].by, "Jane \"JD\" Doe");
+    let written = fmt_approvals(&approvals);
+    assert_eq!(parse_approvals(&written).unwrap(), approvals);
+    assert!(parse_approvals("[REQ-foo]\nby = 'Jane'\n").is_err());
+
+    set_approvals(&mut artifacts, &approvals);
+    assert_eq!(approval_state(&artifacts[&name("REQ-foo")]), ApprovalState::Approved);
+    assert_eq!(approval_state(&artifacts[&name("REQ-foo-bar")]), ApprovalState::Missing);
+
+    // any change of the text makes the approval stale
+    let mut artifacts = load_toml_simple(&text.replace("'foo'", "'foo changed'"));
+    do_links(&mut artifacts).unwrap();
+    set_approvals(&mut artifacts, &approvals);
+    assert_eq!(approval_state(&artifacts[&name("REQ-foo")]), ApprovalState::Stale);
+
+    // and so does a change of the partof or the status, but not of formatting
+    let state = |text: &str| {
+        let mut artifacts = load_toml_simple(text);
+        do_links(&mut artifacts).unwrap();
+        set_approvals(&mut artifacts, &approvals);
+        approval_state(&artifacts[&name("REQ-foo")])
+    };
+    assert_eq!(state("[REQ-foo]\ntext = '''\nfoo  \n'''\n[REQ-foo-bar]\n"),
+               ApprovalState::Approved);
+    assert_eq!(state("[REQ-foo]\ntext = 'foo'\npartof = 'REQ-bar'\n[REQ-bar]\n"),
+               ApprovalState::Stale);
+    assert_eq!(state("[REQ-foo]\ntext = 'foo'\nstatus = 'draft'\n"), ApprovalState::Stale);
+
+    let artifacts = load_toml_simple("[REQ-bar]\n");
+    prune_approvals(&artifacts, &mut approvals);
+    assert!(approvals.is_empty());
+}
@@ -4,7 +4,7 @@ use std::ascii::AsciiExt;
 
 use super::*;  // data directory constants
 use super::super::init_logger_test;
-use super::super::types::*;
+use super::super::prelude::*;
 use super::super::load::*;
 use super::super::vars;
 use super::super::locs::*;
@@ -84,11 +84,57 @@ fn test_settings() {
 }
 
 
+#[test]
+/// partof: #TST-settings-views
+fn test_views() {
+    let mut artifacts = Artifacts::new();
+    let mut settings: Vec<(PathBuf, Settings)> = Vec::new();
+    let mut variables: Vec<(PathBuf, Globals)> = Vec::new();
+    let path = PathBuf::from("hi/there");
+    let toml = r#"
+    [settings]
+    artifact_paths = ['hi']
+
+    [views]
+    todo = { completed = "<", fields = "T" }
+    [views.load]
+    search = "load"
+    reverse = true
+    "#;
+    load_toml(&path, toml, &mut artifacts, &mut settings, &mut variables).unwrap();
+    // the views are part of the settings of the file
+    assert_eq!(settings.len(), 1);
+    let views = &settings[0].1.views;
+    assert_eq!(views["todo"], View {
+        completed: Some("<".to_string()),
+        fields: Some("T".to_string()),
+        ..View::default()
+    });
+    assert_eq!(views["load"], View {
+        search: Some("load".to_string()),
+        reverse: true,
+        ..View::default()
+    });
+
+    for invalid in &["[views]\nfoo = 'bar'",
+                     "[views.foo]\ncolor = 'x'",
+                     "[views.foo]\nsearch = 1"] {
+        assert!(load_toml(&path, invalid, &mut artifacts, &mut settings, &mut variables).is_err());
+    }
+
+    // views cannot be defined twice
+    let mut resolved = Settings::new();
+    let mut repo_map = HashMap::new();
+    repo_map.insert(PathBuf::from("hi"), PathBuf::from("hi"));
+    settings.push((path.clone(), settings[0].1.clone()));
+    assert!(resolve_settings(&mut resolved, &mut repo_map, &settings).is_err());
+}
+
 #[test]
 fn test_load_toml() {
     let mut artifacts = Artifacts::new();
     let mut settings: Vec<(PathBuf, Settings)> = Vec::new();
-    let mut variables: Vec<(PathBuf, Variables)> = Vec::new();
+    let mut variables: Vec<(PathBuf, Globals)> = Vec::new();
 
     let path = PathBuf::from("hi/there");
 
@@ -176,7 +222,7 @@ fn test_load_toml() {
 pub fn load_raw_extra(path: &Path)
                       -> LoadResult<(Artifacts, Settings)> {
     let (mut artifacts, settings, loaded_vars, mut repo_map) = try!(load_raw(path));
-    let mut variables = try!(vars::resolve_loaded_vars(loaded_vars, &mut repo_map));
+    let (mut variables, _) = try!(vars::resolve_loaded_vars(loaded_vars, &mut repo_map));
     try!(vars::fill_text_fields(&mut artifacts, &mut variables, &mut repo_map));
     Ok((artifacts, settings))
 }
@@ -225,4 +271,9 @@ fn test_load_raw() {
 
     // #TST-core-load-dir-unit-5
     assert_eq!(spc_lvl1.text, "level one does FOO");
+
+    // the loaded paths are kept, each only once
+    assert_eq!(settings.paths, VecDeque::from_iter(vec![TSIMPLE_DIR.clone(),
+                                                        TSIMPLE_DIR.join("deep/reqs"),
+                                                        TSIMPLE_DIR.join("deep/spcs")]));
 }
@@ -3,10 +3,11 @@
 use std::env;
 
 use super::*;  // data directory constants
-use super::super::types::*;
+use super::super::prelude::*;
 use super::super::vars::*;
 use super::super::locs::*;
 use super::super::utils;
+use super::super::{link, load};
 use super::super::super::init_logger;
 
 #[test]
@@ -23,9 +24,10 @@ fn test_find_repo() {
 fn test_resolve_vars() {
     // we are getting a race condition with variables where sometimes not all
     // variables are resolving. We need to find it and destroy it.
-    let mut loaded_vars: Variables = Variables::new();
+    let mut loaded_vars: Globals = Globals::new();
     let mut variables: Variables = Variables::new();
-    let var_paths: HashMap<String, PathBuf> = HashMap::new();
+    let mut arrays: Arrays = Arrays::new();
+    let mut var_paths: HashMap<String, PathBuf> = HashMap::new();
     let mut repo_map: HashMap<PathBuf, PathBuf> = HashMap::new();
 
     let fpath = TSIMPLE_DIR.join(PathBuf::from("fake.toml"));
@@ -33,15 +35,17 @@ fn test_resolve_vars() {
     for i in 0..3 { // do it a few times
         loaded_vars.clear();
         variables.clear();
-        loaded_vars.insert("foo".to_string(), "{repo}/FOO".to_string());
-        loaded_vars.insert("bar".to_string(), "{foo}/BAR".to_string());
-        loaded_vars.insert("bar-2".to_string(), "{bar}/BAR2".to_string());
+        arrays.clear();
+        var_paths.clear();
+        loaded_vars.insert("foo".to_string(), Global::Str("{repo}/FOO".to_string()));
+        loaded_vars.insert("bar".to_string(), Global::Str("{foo}/BAR".to_string()));
+        loaded_vars.insert("bar-2".to_string(), Global::Str("{bar}/BAR2".to_string()));
 
         // #TST-core-vars-resolve-default
         resolve_default_vars(&loaded_vars, fpath.as_path(), &mut variables,
-                             &mut repo_map).unwrap();
+                             &mut arrays, &mut var_paths, &mut repo_map).unwrap();
         // #TST-core-vars-resolve-user
-        resolve_vars(&mut variables).unwrap();
+        resolve_vars(&mut variables, &mut arrays, &var_paths).unwrap();
         let foo = TSIMPLE_DIR.join("FOO");
         let bar = foo.join("BAR");
         let bar2 = bar.join("BAR2");
@@ -52,6 +56,121 @@ fn test_resolve_vars() {
     }
 }
 
+#[test]
+/// partof: #TST-vars-errors
+fn test_resolve_vars_errors() {
+    let mut repo_map: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let vars = |v: &[(&str, &str)]| -> Globals {
+        v.iter().map(|&(k, v)| (k.to_string(), Global::Str(v.to_string()))).collect()
+    };
+    let first = TSIMPLE_DIR.join("first.toml");
+    let second = TSIMPLE_DIR.join("second.toml");
+
+    // a global defined twice names both files
+    let mut variables = Variables::new();
+    let mut arrays = Arrays::new();
+    let mut var_paths: HashMap<String, PathBuf> = HashMap::new();
+    resolve_default_vars(&vars(&[("a", "{b}")]), &first, &mut variables, &mut arrays,
+                         &mut var_paths, &mut repo_map).unwrap();
+    assert!(resolve_default_vars(&vars(&[("a", "x")]), &second, &mut variables, &mut arrays,
+                                 &mut var_paths, &mut repo_map).is_err());
+    assert_eq!(var_paths["a"], first);
+
+    // cycles are reported in full with the files, dependents are not reported
+    resolve_default_vars(&vars(&[("b", "{c}"), ("c", "{a}"), ("d", "{a}/D"),
+                                 ("e", "{dne}"), ("f", "{fooo}"), ("foo", "FOO"),
+                                 ("g", "{foo}/G")]),
+                         &second, &mut variables, &mut arrays, &mut var_paths,
+                         &mut repo_map).unwrap();
+    let err = resolve_vars(&mut variables, &mut arrays, &var_paths).unwrap_err().desc;
+    let a = format!("a (in {})", first.display());
+    let b = format!("b (in {})", second.display());
+    let c = format!("c (in {})", second.display());
+    let e = format!("e (in {})", second.display());
+    let f = format!("f (in {})", second.display());
+    assert_eq!(err,
+               format!("could not resolve the globals:\n\
+                        - globals refer to each other: {a} -> {b} -> {c} -> {a}\n\
+                        - global {e} uses undefined global \"dne\"\n\
+                        - global {f} uses undefined global \"fooo\" (did you mean \"foo\"?)",
+                       a = a, b = b, c = c, e = e, f = f));
+    assert_eq!(variables["a"], "{b}");
+    assert_eq!(variables["g"], "FOO/G");
+
+    // undefined globals in text name the artifact and its file
+    let mut artifacts = load::load_toml_simple("\
+        [REQ-foo]\n\
+        text = '{foo} and {fooo} but not {{foo}}'\n\
+        [REQ-bar]\n\
+        text = '{foo}'\n");
+    let path = TSIMPLE_DIR.join("fake.toml");
+    for art in artifacts.values_mut() {
+        art.path = path.clone();
+    }
+    let err = fill_text_fields(&mut artifacts, &mut variables, &mut repo_map)
+        .unwrap_err().desc;
+    assert_eq!(err,
+               format!("failure to resolve artifact text fields:\n\
+                        - [{}] REQ-foo uses undefined global \"fooo\" (did you mean \"foo\"?)",
+                       path.display()));
+}
+#[test]
+/// partof: #TST-vars-typed
+fn test_typed_globals() {
+    env::set_var("RST_TEST_BUILD", "out");
+    env::remove_var("RST_TEST_DNE");
+    let text = "\
+        [settings]\n\
+        code_paths = ['{cwd}/{crates}/{build}']\n\
+        exclude_code_paths = ['{cwd}/{{x}}']\n\
+        [globals]\n\
+        crates = ['a', '{name}']\n\
+        name = 'b'\n\
+        debug = true\n\
+        jobs = 4\n\
+        build = '{env:RST_TEST_BUILD|target}/{env:RST_TEST_DNE|debug}'\n\
+        [REQ-foo]\n\
+        text = '{crates} {debug} {jobs} {build} {env:RST_TEST_DNE|none} {{env:X}}'\n";
+    let path = TSIMPLE_DIR.join("fake.toml");
+    let mut artifacts = Artifacts::new();
+    let mut loaded_settings: Vec<(PathBuf, Settings)> = Vec::new();
+    let mut loaded_vars: Vec<(PathBuf, Globals)> = Vec::new();
+    let mut repo_map: HashMap<PathBuf, PathBuf> = HashMap::new();
+    load::load_toml(&path, text, &mut artifacts, &mut loaded_settings, &mut loaded_vars)
+        .unwrap();
+    let (mut variables, arrays) = resolve_loaded_vars(loaded_vars, &mut repo_map).unwrap();
+    assert_eq!(arrays["crates"], vec!["a", "b"]);
+    fill_text_fields(&mut artifacts, &mut variables, &mut repo_map).unwrap();
+    assert_eq!(artifacts[&ArtNameRc::from_str("REQ-foo").unwrap()].text,
+               "a, b true 4 out/debug none {env:X}");
+
+    // all the globals can be used in the code paths, arrays are expanded
+    let mut settings = Settings::new();
+    load::resolve_settings(&mut settings, &mut repo_map, &loaded_settings).unwrap();
+    resolve_code_paths(&mut settings, &variables, &arrays).unwrap();
+    assert_eq!(settings.code_paths,
+               VecDeque::from(vec![TSIMPLE_DIR.join("a/out/debug"),
+                                   TSIMPLE_DIR.join("b/out/debug")]));
+    assert_eq!(settings.exclude_code_paths, VecDeque::from(vec![TSIMPLE_DIR.join("{x}")]));
+    settings.code_paths = VecDeque::from(vec![PathBuf::from("{crate}/src")]);
+    settings.exclude_code_paths.clear();
+    assert_eq!(resolve_code_paths(&mut settings, &variables, &arrays).unwrap_err().desc,
+               "could not resolve the code paths:\n\
+                - code path \"{crate}/src\" uses undefined global \"crate\" \
+                (did you mean \"crates\"?)");
+
+    // environment variables must be set or have a default
+    assert_eq!(fmt_known("{env:RST_TEST_DNE}", &Variables::new()).unwrap_err().desc,
+               "environment variable RST_TEST_DNE is not set and {env:RST_TEST_DNE} \
+                has no default");
+
+    // globals can only be strings, booleans, integers or arrays of them
+    for invalid in &["x = 1.5", "x = [[1]]", "x = {y = 1}", "env = 'x'"] {
+        let text = format!("[globals]\n{}\n", invalid);
+        assert!(load::load_toml(&path, &text, &mut Artifacts::new(), &mut Vec::new(),
+                                &mut Vec::new()).is_err());
+    }
+}
 
 pub const LOC_TEST: &'static str = "\
 $SPC-who
@@ -85,3 +204,111 @@ fn test_resolve_loc_text() {
     assert_eq!(tst_long.line_col,    (4, 15));
     assert_eq!(spc_error.line_col,   (6, 4));
 }
+
+#[test]
+fn test_resolve_loc_text_unicode() {
+    let mut locs: HashMap<ArtName, Loc> = HashMap::new();
+    let path = PathBuf::from("hi/there");
+    assert!(!find_locs_text(&path, "// └── ünïcode\n/// #SPC-after └── \n", &mut locs));
+    let spc_after = locs.get(&ArtName::from_str("SPC-after").unwrap()).unwrap();
+    assert_eq!(spc_after.line_col, (2, 4));
+}
+
+#[test]
+/// partof: #TST-artifact-mentions
+fn test_mentions() {
+    let names = |n: &ArtNames| -> Vec<String> {
+        let mut n: Vec<_> = n.iter().map(|n| n.raw.clone()).collect();
+        n.sort();
+        n
+    };
+    assert_eq!(names(&find_mentions("see SPC-load-dir, partof: #SPC-world and (REQ-foo_1).\n\
+                                      `TST-bar-` is TST-bar")),
+               vec!["REQ-foo_1", "SPC-load-dir", "SPC-world", "TST-bar"]);
+    // names must start with an uppercase type and not be part of a word
+    assert!(find_mentions("spc-foo, XSPC-foo, SPC-, REQ and REQfoo").is_empty());
+    // names in code are examples
+    assert_eq!(names(&find_mentions("`REQ-foo` and\n```\n[SPC-bar]\n```\nbut TST-baz")),
+               vec!["TST-baz"]);
+
+    let mut artifacts = load::load_toml_simple("\
+        [REQ-foo]\ntext = 'see REQ-foo, SPC-foo and SPC-fooo'\n\
+        [SPC-foo]\ntext = 'part of REQ-foo'\n");
+    set_mentions(&mut artifacts);
+    let foo = ArtNameRc::from_str("REQ-foo").unwrap();
+    assert_eq!(names(&artifacts[&foo].mentions), vec!["SPC-foo", "SPC-fooo"]);
+    link::do_links(&mut artifacts).unwrap();
+    assert_eq!(link::warn_mentions(&artifacts), 1);
+}
+
+#[test]
+/// partof: #TST-vars-transclude
+fn test_transclude() {
+    let mut repo_map: HashMap<PathBuf, PathBuf> = HashMap::new();
+    repo_map.insert(PathBuf::from(""), PathBuf::from("/repo"));
+    let load = |text: &str| {
+        let mut artifacts = load::load_toml_simple(text);
+        link::do_links(&mut artifacts).unwrap();
+        artifacts
+    };
+
+    let mut artifacts = load::load_toml_simple(r#"
+[REQ-summary]
+text = '''
+# Summary
+{ART:REQ-foo.text}
+foo is {ART:SPC-foo.completed}% done at {LOC: SPC-foo}, {LOC:SPC-bar}
+parts: {ART:REQ-foo.parts} {{ART:REQ-foo.text}}'''
+[REQ-foo]
+text = "foo {ART:REQ-bar.text}"
+[REQ-bar]
+text = "bar {repo}"
+[SPC-foo]
+[SPC-bar]
+partof = "REQ-foo"
+"#);
+    let spc_foo = ArtNameRc::from_str("SPC-foo").unwrap();
+    artifacts.get_mut(&spc_foo).unwrap().loc = Some(Loc {
+        path: PathBuf::from("/repo/src/foo.rs"),
+        line_col: (3, 0),
+    });
+    link::do_links(&mut artifacts).unwrap();
+    transclude(&mut artifacts, &mut repo_map).unwrap();
+    let summary = ArtNameRc::from_str("REQ-summary").unwrap();
+    assert_eq!(artifacts[&summary].text,
+               "# Summary\nfoo bar {repo}\nfoo is 100% done at src/foo.rs:3, (not implemented)\n\
+                parts: SPC-bar, SPC-foo {{ART:REQ-foo.text}}");
+
+    // the percentages are rounded, and are reported when they are not known
+    let mut artifacts = load("[REQ-foo]\ntext = '{ART:REQ-foo.completed}% {ART:REQ-foo.tested}%'\n\
+                              [SPC-foo-a]\npartof = 'REQ-foo'\n[SPC-foo-b]\npartof = 'REQ-foo'\n\
+                              [SPC-foo-c]\npartof = 'REQ-foo'\n");
+    for n in &["SPC-foo-a", "SPC-foo-b"] {
+        artifacts.get_mut(&ArtNameRc::from_str(n).unwrap()).unwrap().loc = Some(Loc::fake());
+    }
+    link::do_links(&mut artifacts).unwrap();
+    artifacts.get_mut(&ArtNameRc::from_str("REQ-foo").unwrap()).unwrap().tested = -1.;
+    transclude(&mut artifacts, &mut repo_map).unwrap();
+    assert_eq!(artifacts[&ArtNameRc::from_str("REQ-foo").unwrap()].text, "67% (unresolved)%");
+
+    // cycles are detected, including through several artifacts
+    let mut artifacts = load("[REQ-a]\ntext = '{ART:REQ-b.text}'\n\
+                              [REQ-b]\ntext = '{ART:REQ-c.text} {ART:REQ-a.text}'\n\
+                              [REQ-c]\ntext = 'c'\n");
+    let err = transclude(&mut artifacts, &mut repo_map).unwrap_err();
+    assert!(err.desc.contains("transclusion cycle: REQ-a -> REQ-b -> REQ-a"), err.desc);
+    let mut artifacts = load("[REQ-a]\ntext = 'a {ART:REQ-a.text}'\n");
+    let err = transclude(&mut artifacts, &mut repo_map).unwrap_err();
+    assert!(err.desc.contains("transclusion cycle: REQ-a -> REQ-a"), err.desc);
+
+    // invalid transclusions
+    let mut artifacts = load("[REQ-foo]\ntext = '{ART:REQ-fooo.text}'\n");
+    let err = transclude(&mut artifacts, &mut repo_map).unwrap_err();
+    assert!(err.desc.ends_with("transcluded REQ-fooo does not exist (did you mean REQ-foo?)"),
+            err.desc);
+    let mut artifacts = load("[REQ-foo]\ntext = '{ART:REQ-foo.name}'\n");
+    let err = transclude(&mut artifacts, &mut repo_map).unwrap_err();
+    assert!(err.desc.contains("unknown field in {ART:REQ-foo.name}"), err.desc);
+    let mut artifacts = load("[REQ-foo]\ntext = '{ART:REQ-foo}'\n");
+    assert!(transclude(&mut artifacts, &mut repo_map).is_err());
+}
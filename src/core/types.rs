@@ -1,20 +1,16 @@
-// Export these:
-// Traits
-pub use std::io::{Read, Write};
-pub use std::fmt::Write as WriteStr;
-pub use std::iter::FromIterator;
-pub use std::clone::Clone;
-pub use std::convert::AsRef;
-pub use std::str::FromStr;
-
-// stdlib
-pub use std::fs;
-pub use std::path::{Path, PathBuf};
-pub use std::collections::{HashMap, HashSet, VecDeque};
-pub use std::rc::Rc;
+// the std items used throughout core are exported by core::prelude, which is
+// private so that they are not part of the public API
+use std::fmt::Write as WriteStr;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 
 // crates
 use regex::Regex;
+#[cfg(feature = "serde")]
+use serde::{ser, de};
+#[cfg(feature = "serde")]
+use serde::Deserialize;
 
 // for type definitions only
 use std::fmt;
@@ -27,11 +23,57 @@ use std::cmp::{PartialEq, Ord, PartialOrd, Ordering};
 // definition of new types
 pub type LoadResult<T> = Result<T, LoadError>;
 pub type Artifacts = HashMap<ArtNameRc, Artifact>;
-pub type ArtNameRc = Rc<ArtName>;
+/// a shared name, which is `Arc` so that artifacts can be shared between threads
+pub type ArtNameRc = Arc<ArtName>;
 pub type ArtNames = HashSet<ArtNameRc>;
 
+/// the statuses used when none are set in the settings
+pub const DEFAULT_STATUSES: [&'static str; 3] = ["draft", "approved", "obsolete"];
+/// the status of artifacts which are not done being written
+pub const DRAFT: &'static str = "draft";
+/// the status of artifacts which have been accepted
+pub const APPROVED: &'static str = "approved";
+/// the status of artifacts which are no longer used. They are skipped when
+/// calculating the completed and tested ratios of their parents
+pub const OBSOLETE: &'static str = "obsolete";
+
 // #SPC-core-vars-struct
 pub type Variables = HashMap<String, String>;
+/// the resolved elements of the globals that are arrays
+pub type Arrays = HashMap<String, Vec<String>>;
+/// the globals of a file, as they are loaded
+pub type Globals = HashMap<String, Global>;
+/// the hash of the text of each parent as of the last review of a link,
+/// by child and then by parent. See SPC-review
+pub type Reviewed = HashMap<ArtNameRc, HashMap<ArtNameRc, String>>;
+/// the approvals of the artifacts, see SPC-approve
+pub type Approvals = HashMap<ArtNameRc, Approval>;
+
+/// who approved an artifact and when, with the hash of its text, partof and
+/// status at the time. The approval is stale when any of them change
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Approval {
+    pub by: String,
+    pub at: String,
+    pub hash: String,
+}
+
+/// the state of the approval of an artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalState {
+    Missing,
+    Stale,
+    Approved,
+}
+
+/// the value of a global. Booleans and integers are kept as they are
+/// written, since globals are only ever formatted into text and paths
+#[derive(Debug, Clone, PartialEq)]
+pub enum Global {
+    Str(String),
+    Array(Vec<String>),
+}
 
 lazy_static!{
     // must start with artifact type, followed by "-", followed by at least 1 valid character
@@ -41,6 +83,7 @@ lazy_static!{
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// #SPC-core-artifact-types:<valid artifact types>
 pub enum ArtType {
     REQ,
@@ -51,6 +94,7 @@ pub enum ArtType {
 
 /// #SPC-core-artifact-attrs-loc<Location data type>
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Loc {
     pub path: PathBuf,
     pub line_col: (usize, usize),
@@ -130,7 +174,7 @@ impl ArtName {
 
     pub fn parent_rc(&self) -> Option<ArtNameRc> {
         match self.parent() {
-            Some(p) => Some(Rc::new(p)),
+            Some(p) => Some(Arc::new(p)),
             None => None,
         }
     }
@@ -212,6 +256,23 @@ impl PartialOrd for ArtName {
     }
 }
 
+/// names are serialized as their raw string
+#[cfg(feature = "serde")]
+impl ser::Serialize for ArtName {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+/// names are validated (with `ART_VALID`) when deserialized
+#[cfg(feature = "serde")]
+impl<'de> de::Deserialize<'de> for ArtName {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<ArtName, D::Error> {
+        let raw = try!(String::deserialize(deserializer));
+        ArtName::from_str(&raw).map_err(|e| de::Error::custom(e.desc))
+    }
+}
+
 
 
 /// subfunction to parse names from a names-str recusively
@@ -285,7 +346,7 @@ pub trait LoadFromStr: Sized {
 
 impl LoadFromStr for ArtNameRc {
     fn from_str(s: &str) -> LoadResult<ArtNameRc> {
-        Ok(Rc::new(try!(ArtName::from_str(s))))
+        Ok(Arc::new(try!(ArtName::from_str(s))))
     }
 }
 
@@ -296,7 +357,7 @@ impl LoadFromStr for ArtNames {
         let strs = try!(_parse_names(&mut partof_str.chars(), false));
         let mut out = HashSet::new();
         for s in strs {
-            out.insert(Rc::new(try!(ArtName::from_str(&s))));
+            out.insert(Arc::new(try!(ArtName::from_str(&s))));
         }
         Ok(out)
     }
@@ -308,27 +369,40 @@ impl LoadFromStr for ArtNames {
 /// The Artifact type. This encapsulates
 /// REQ, SPC, RSK, and TST artifacts and
 /// contains space to link them
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Artifact {
     // directly loaded types
     pub ty: ArtType,
     pub path: PathBuf,
     pub text: String,
     pub partof: ArtNames,
+    pub status: Option<String>,
     pub parts: ArtNames,
     pub loc: Option<Loc>,
+    pub mentions: ArtNames, // the artifacts mentioned in the text (calculated)
+    pub hash: String, // hash of the text as it is written (calculated)
+    pub suspect: ArtNames, // parents changed since the link was reviewed (calculated)
+    pub approval: Option<Approval>, // loaded from the approvals (calculated)
     pub completed: f32, // completed ratio (calculated)
     pub tested: f32, // tested ratio (calculated)
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// #SPC-core-settings-struct
 pub struct Settings {
     pub disabled: bool,
     pub paths: VecDeque<PathBuf>,
     pub code_paths: VecDeque<PathBuf>,
     pub exclude_code_paths: VecDeque<PathBuf>,
+    pub thresholds: Vec<String>,
     pub color: bool,
+    pub views: HashMap<String, View>,
+    /// the statuses artifacts can have, see SPC-artifact-status
+    pub statuses: Vec<String>,
+    /// the allowed (from, to) status changes. Any change is allowed if empty
+    pub transitions: Vec<(String, String)>,
 }
 
 impl Settings {
@@ -338,11 +412,34 @@ impl Settings {
             paths: VecDeque::new(),
             code_paths: VecDeque::new(),
             exclude_code_paths: VecDeque::new(),
+            thresholds: Vec::new(),
             color: true,
+            views: HashMap::new(),
+            statuses: Vec::new(),
+            transitions: Vec::new(),
         }
     }
 }
 
+/// a saved set of `ls` arguments from the `[views]` table, used
+/// with `rst ls @NAME`
+/// partof: #SPC-settings-views
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct View {
+    pub search: Option<String>,
+    /// the fields searched by the pattern, as in `ls -p`
+    pub pattern: Option<String>,
+    pub completed: Option<String>,
+    pub tested: Option<String>,
+    pub query: Option<String>,
+    /// the display flags, as in `ls -lDPOLT`
+    pub fields: Option<String>,
+    pub sort: Option<String>,
+    pub group_by: Option<String>,
+    pub reverse: bool,
+}
+
 /// Error for parsing files into artifacts
 #[derive(Debug)]
 pub struct LoadError {
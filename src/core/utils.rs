@@ -1,9 +1,9 @@
 use std::env;
-use std::fmt;
+use std::cmp;
 
 use strfmt;
 
-use super::types::*;
+use super::prelude::*;
 use itertools::{Itertools, EitherOrBoth as EoB};
 
 pub fn do_strfmt(s: &str, vars: &HashMap<String, String>, fpath: &PathBuf)
@@ -19,17 +19,6 @@ pub fn do_strfmt(s: &str, vars: &HashMap<String, String>, fpath: &PathBuf)
     }
 }
 
-pub fn strfmt_ignore_missing<T: fmt::Display>(fmtstr: &str, vars: &HashMap<String, T>)
-                                              -> strfmt::Result<String>{
-    let formatter = |mut fmt: strfmt::Formatter| {
-        match vars.get(fmt.key) {
-            Some(v) => fmt.str(v.to_string().as_str()),
-            None => fmt.skip(),
-        }
-    };
-    strfmt::strfmt_map(fmtstr, &formatter)
-}
-
 pub fn get_path_str<'a>(path: &'a Path) -> LoadResult<&'a str> {
     match path.to_str() {
         Some(p) => Ok(p),
@@ -132,6 +121,62 @@ pub fn relative_path(path: &Path, relative_to_dir: &Path) -> PathBuf {
     relative
 }
 
+/// the number of single character insertions, deletions and substitutions
+/// needed to change `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            let d = cmp::min(cmp::min(prev[j + 1] + 1, row[j] + 1), prev[j] + cost);
+            row.push(d);
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+/// the distance between two names. Names with the same number of segments
+/// are compared segment by segment, so a typo can not be "fixed" by
+/// moving a `-`
+fn name_distance(a: &ArtName, b: &ArtName) -> usize {
+    if a.value.len() == b.value.len() {
+        a.value.iter().zip(b.value.iter()).map(|(x, y)| edit_distance(x, y)).sum()
+    } else {
+        edit_distance(&a.value.join("-"), &b.value.join("-"))
+    }
+}
+
+/// the names closest to `name` (which does not exist), closest first.
+/// Only names within a quarter of the length of `name` are returned
+/// partof: #SPC-names-suggest
+pub fn similar_names<'a, I>(name: &ArtName, names: I) -> Vec<ArtNameRc>
+    where I: IntoIterator<Item = &'a ArtNameRc>
+{
+    let max = cmp::max(1, name.value.join("-").len() / 4);
+    let mut similar: Vec<(usize, &ArtNameRc)> = names.into_iter()
+        .map(|n| (name_distance(name, n), n))
+        .filter(|&(d, _)| d > 0 && d <= max)
+        .collect();
+    similar.sort();
+    similar.into_iter().take(3).map(|(_, n)| n.clone()).collect()
+}
+
+/// a suggestion to append to an error about `name` not existing, i.e.
+/// ` (did you mean REQ-foo?)`, or an empty string if no name is similar
+pub fn did_you_mean<'a, I>(name: &ArtName, names: I) -> String
+    where I: IntoIterator<Item = &'a ArtNameRc>
+{
+    let similar: Vec<String> = similar_names(name, names).iter().map(|n| n.raw.clone()).collect();
+    match similar.len() {
+        0 => String::new(),
+        1 => format!(" (did you mean {}?)", similar[0]),
+        l => format!(" (did you mean {} or {}?)", similar[..l - 1].join(", "), similar[l - 1]),
+    }
+}
+
 #[test]
 fn test_relative_path() {
     assert_eq!(relative_path(&PathBuf::from("/foo/bar/txt.t"),
@@ -147,3 +192,38 @@ fn test_relative_path() {
                              &PathBuf::from("/home/user/projects/what/reqs/left/right/a/b/c/")),
                PathBuf::from("../../../../../../src/foo/bar.txt"));
 }
+
+#[test]
+/// partof: #TST-names-suggest
+fn test_similar_names() {
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("abc", "abc"), 0);
+
+    let names: Vec<ArtNameRc> = ["REQ-foo", "SPC-core-artifact-attrs-partof",
+                                 "SPC-core-artifact-attrs-parts", "SPC-core-artifact",
+                                 "TST-core-artifact-attrs-partof"]
+        .iter()
+        .map(|n| ArtNameRc::from_str(n).unwrap())
+        .collect();
+    let similar = |n: &str| -> Vec<String> {
+        similar_names(&ArtName::from_str(n).unwrap(), &names)
+            .iter()
+            .map(|n| n.raw.clone())
+            .collect()
+    };
+    // one character typos, in any case
+    assert_eq!(similar("spc-core-artifact-atrs-partof"),
+               vec!["SPC-core-artifact-attrs-partof", "SPC-core-artifact-attrs-parts",
+                    "TST-core-artifact-attrs-partof"]);
+    assert_eq!(similar("SPC-core-artfact"), vec!["SPC-core-artifact"]);
+    assert_eq!(similar("REQ-fo"), vec!["REQ-foo"]);
+    // short names need to be close
+    assert!(similar("REQ-bar").is_empty());
+    // existing names are not suggested
+    assert!(similar("REQ-foo").is_empty());
+
+    assert_eq!(did_you_mean(&ArtName::from_str("REQ-fooo").unwrap(), &names),
+               " (did you mean REQ-foo?)");
+    assert_eq!(did_you_mean(&ArtName::from_str("REQ-bar").unwrap(), &names), "");
+}
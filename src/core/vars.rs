@@ -2,21 +2,92 @@
 //! used by the load module to resolve and apply loaded variables
 //! also contains settings resolution because it is similar
 
-use super::types::*;
+use super::prelude::*;
 use super::utils;
 
+use std::cmp;
+use std::env;
+
+use regex::Regex;
 use strfmt;
 
 lazy_static!{
     pub static ref DEFAULT_GLOBALS: HashSet<String> = HashSet::from_iter(
-        ["repo", "cwd"].iter().map(|s| s.to_string()));
+        ["repo", "cwd", "env"].iter().map(|s| s.to_string()));
+    /// an artifact name mentioned in text. The type must be uppercase so
+    /// that prose like "req-like" is not mistaken for a name
+    pub static ref MENTION_RE: Regex = Regex::new(
+        r"\b(REQ|SPC|RSK|TST)-[A-Za-z0-9_-]*[A-Za-z0-9_]\b").unwrap();
+    /// a markdown code block or span, where names are examples rather than
+    /// mentions
+    static ref CODE_RE: Regex = Regex::new(r"(?s)```.*?```|`[^`]*`").unwrap();
+    /// a transclusion, i.e. `{ART:REQ-foo.text}` or `{LOC:SPC-foo}`. Escaped
+    /// braces are matched so that `{{ART:REQ-foo.text}}` is left as it is
+    static ref TRANSCLUDE_RE: Regex = Regex::new(
+        r"\{\{|\}\}|\{(ART|LOC):([^{}]*)\}").unwrap();
+    /// a global used in a format string, i.e. `{foo}` or `{foo:>10}`
+    static ref VAR_RE: Regex = Regex::new(
+        r"\{\{|\}\}|\{([^{}:]*)(:[^{}]*)?\}").unwrap();
+}
+
+/// format the globals in the text that are in `vars` and the environment
+/// variables, i.e. `{env:HOME}` or `{env:BUILD_DIR|target}` where `target`
+/// is the default when `BUILD_DIR` is not set. The other globals and escaped
+/// braces are left as they are
+/// partof: #SPC-vars-env
+pub fn fmt_known(text: &str, vars: &Variables) -> LoadResult<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in VAR_RE.captures_iter(text) {
+        let (start, end) = caps.pos(0).unwrap();
+        out.push_str(&text[last..start]);
+        last = end;
+        let whole = &text[start..end];
+        let value = match caps.at(1) {
+            Some("env") => {
+                let spec = caps.at(2).map_or("", |s| &s[1..]);
+                let (name, default) = match spec.find('|') {
+                    Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+                    None => (spec, None),
+                };
+                if name.is_empty() {
+                    return Err(LoadError::new(format!("{} must be {{env:NAME}} or \
+                                                       {{env:NAME|default}}", whole)));
+                }
+                match (env::var(name), default) {
+                    (Ok(v), _) => v,
+                    (Err(_), Some(d)) => d.to_string(),
+                    (Err(_), None) => {
+                        return Err(LoadError::new(format!("environment variable {} is not set \
+                                                           and {} has no default", name, whole)))
+                    }
+                }
+            }
+            Some(k) if vars.contains_key(k) => {
+                match strfmt::strfmt(whole, vars) {
+                    Ok(v) => v,
+                    Err(e) => return Err(LoadError::new(e.to_string())),
+                }
+            }
+            _ => {
+                out.push_str(whole);
+                continue;
+            }
+        };
+        out.push_str(&escape_braces(&value));
+    }
+    out.push_str(&text[last..]);
+    Ok(out)
 }
 
-/// resolves default vars from a file (cwd and repo)
-/// and inserts into variables
+/// resolves default vars from a file (cwd, repo and env)
+/// and inserts into variables, recording the file each one is defined in.
+/// The arrays are put in `arrays`, with an empty placeholder in `variables`
 /// #SPC-core-vars-resolve-default
-pub fn resolve_default_vars(vars: &Variables, fpath: &Path,
+pub fn resolve_default_vars(vars: &Globals, fpath: &Path,
                             variables: &mut Variables,
+                            arrays: &mut Arrays,
+                            var_paths: &mut HashMap<String, PathBuf>,
                             repo_map: &mut HashMap<PathBuf, PathBuf>)
                             -> LoadResult<()> {
     let cwd = fpath.parent().unwrap();
@@ -27,24 +98,40 @@ pub fn resolve_default_vars(vars: &Variables, fpath: &Path,
                      .to_str().unwrap().to_string());
     let mut error = false;
     for (k, v) in vars {
-        // format only the cwd and repo variables
-        let var = match utils::strfmt_ignore_missing(v.as_str(), &fmtvars) {
+        // format only the default variables
+        let var = match *v {
+            Global::Str(ref s) => fmt_known(s, &fmtvars).map(Global::Str),
+            Global::Array(ref a) => {
+                a.iter().map(|s| fmt_known(s, &fmtvars)).collect::<LoadResult<_>>()
+                    .map(Global::Array)
+            }
+        };
+        let var = match var {
             Ok(v) => v,
             Err(e) => {
                 // [#SPC-core-load-error-vars-1]
-                error!("error formatting: {}", e.to_string());
+                error!("error formatting global {:?} in {}: {}", k, fpath.display(), e);
                 error = true;
                 continue;
             }
         };
-        match variables.insert(k.clone(), var) {
-            Some(_) => {
-                // [#SPC-core-load-error-vars-2]
-                error!("global var {:?} exists twice, one at {:?}", k, fpath);
-                error = true;
+        if let Some(first) = var_paths.get(k) {
+            // [#SPC-core-load-error-vars-2]
+            error!("global var {:?} is defined in both {} and {}", k,
+                   first.display(), fpath.display());
+            error = true;
+            continue;
+        }
+        match var {
+            Global::Str(s) => {
+                variables.insert(k.clone(), s);
+            }
+            Global::Array(a) => {
+                variables.insert(k.clone(), String::new());
+                arrays.insert(k.clone(), a);
             }
-            None => {}
         }
+        var_paths.insert(k.clone(), fpath.to_path_buf());
     }
     if error {
         // [#SPC-core-load-error-vars-return-1]
@@ -53,66 +140,125 @@ pub fn resolve_default_vars(vars: &Variables, fpath: &Path,
     Ok(())
 }
 
-/// continues to resolve variables until all are resolved
-/// - done if no vars were resolved in a pass and no errors
-/// - error if no vars were resolved in a pass and there were errors
-/// #SPC-core-vars-resolve-user
-pub fn resolve_vars(variables: &mut Variables) -> LoadResult<()> {
-    // keep resolving variables until all are resolved
-    let mut msg = String::new();
-    let mut keys: Vec<String> = variables.keys().map(|s| s.clone()).collect();
-    let mut errors = Vec::new();
-    let mut num_changed;
-    let mut remove_keys = DEFAULT_GLOBALS.clone();
-    loop {
-        keys = keys.iter().filter(|k| !remove_keys.contains(k.as_str()))
-            .map(|s| s.clone()).collect();
-        num_changed = 0;
-        errors.clear();
-        remove_keys.clear();
-        for k in &keys {
-            let var = variables.remove(k.as_str()).unwrap();
-            match strfmt::strfmt(var.as_str(), &variables) {
-                Ok(s) => {
-                    // TODO: being able to know whether changes were made would remove need
-                    // to compare input to output
-                    if var != s {
-                        // var was changed, but it still might have {} in it
-                        num_changed += 1;
-                    } else {
-                        // no errors, but also didn't change. It is done evaluating
-                        remove_keys.insert(k.clone());
-                    }
-                    variables.insert(k.clone(), s);
-                }
+/// the names of the globals used in a format string, i.e. `foo` in
+/// `{foo}/bar`. Escaped braces are skipped
+fn find_var_refs(text: &str) -> Vec<&str> {
+    VAR_RE.captures_iter(text).filter_map(|caps| caps.at(1)).collect()
+}
 
-                Err(e) => match e {
-                    strfmt::FmtError::Invalid(e) | strfmt::FmtError::TypeError(e) => {
-                        return Err(LoadError::new(e.to_string()));
-                    },
-                    strfmt::FmtError::KeyError(_) => {
-                        // [#SPC-core-load-error-vars-3]
-                        errors.push(k.clone());
-                        // reinsert original value
-                        variables.insert(k.clone(), var);
-                    },
+/// suggest the global that was probably meant by an undefined one
+fn did_you_mean_var(name: &str, variables: &Variables) -> String {
+    let max = cmp::max(1, name.len() / 4);
+    let mut similar: Vec<(usize, &String)> = variables.keys()
+        .map(|k| (utils::edit_distance(name, k), k))
+        .filter(|&(d, _)| d <= max)
+        .collect();
+    similar.sort();
+    match similar.first() {
+        Some(&(_, k)) => format!(" (did you mean {:?}?)", k),
+        None => String::new(),
+    }
+}
+
+/// where a global is defined, for error messages
+fn var_path(name: &str, var_paths: &HashMap<String, PathBuf>) -> String {
+    match var_paths.get(name) {
+        Some(p) => format!("{} (in {})", name, p.display()),
+        None => name.to_string(),
+    }
+}
+
+/// resolve a global after resolving the globals it uses, returning whether
+/// it could be resolved. `stack` is the globals being resolved, which is
+/// used to detect cycles. Each element of an array is resolved, and the
+/// array is joined with ", " when it is used in text
+fn resolve_var(name: &str,
+               variables: &mut Variables,
+               arrays: &mut Arrays,
+               var_paths: &HashMap<String, PathBuf>,
+               done: &mut HashMap<String, bool>,
+               stack: &mut Vec<String>,
+               errors: &mut Vec<String>)
+               -> bool {
+    if let Some(ok) = done.get(name) {
+        return *ok;
+    }
+    if let Some(i) = stack.iter().position(|n| n == name) {
+        // [#SPC-core-load-error-vars-3]
+        let cycle: Vec<String> = stack[i..].iter()
+            .map(|n| n.as_str())
+            .chain(Some(name))
+            .map(|n| var_path(n, var_paths))
+            .collect();
+        errors.push(format!("globals refer to each other: {}", cycle.join(" -> ")));
+        for n in &stack[i..] {
+            done.insert(n.clone(), false);
+        }
+        return false;
+    }
+    stack.push(name.to_string());
+    let values: Vec<String> = match arrays.get(name) {
+        Some(a) => a.clone(),
+        None => vec![variables[name].clone()],
+    };
+    let mut ok = true;
+    for dep in values.iter().flat_map(|v| find_var_refs(v)) {
+        if DEFAULT_GLOBALS.contains(dep) {
+            continue;
+        }
+        if !variables.contains_key(dep) {
+            errors.push(format!("global {} uses undefined global {:?}{}",
+                                var_path(name, var_paths), dep,
+                                did_you_mean_var(dep, variables)));
+            ok = false;
+        } else if !resolve_var(dep, variables, arrays, var_paths, done, stack, errors) {
+            ok = false;
+        }
+    }
+    stack.pop();
+    if ok {
+        match values.iter().map(|v| strfmt::strfmt(v, variables)).collect() {
+            Ok(values) => {
+                let values: Vec<String> = values;
+                variables.insert(name.to_string(), values.join(", "));
+                if arrays.contains_key(name) {
+                    arrays.insert(name.to_string(), values);
                 }
             }
-        }
-        if num_changed == 0 {  // no items changed, we are either done or failed
-            if errors.len() == 0 {
-                break;
-            } else {
-                // unresolved errors
-                // [#SPC-core-load-error-vars-return-2]
-                keys = keys.iter().filter(|k| !remove_keys.contains(k.as_str()))
-                    .map(|s| s.clone()).collect();
-                write!(msg, "Could not resolve some globals: {:?}\ngot related errors: {:?}",
-                       keys, errors).unwrap();
-                return Err(LoadError::new(msg));
+            Err(e) => {
+                let e: strfmt::FmtError = e;
+                errors.push(format!("global {}: {}", var_path(name, var_paths), e));
+                ok = false;
             }
         }
     }
+    done.insert(name.to_string(), ok);
+    ok
+}
+
+/// resolve the globals that use other globals, resolving each one after the
+/// globals it uses. Cycles are reported with every global in them and where
+/// it is defined, i.e. `a (in x.toml) -> b (in y.toml) -> a (in x.toml)`
+/// #SPC-core-vars-resolve-user
+pub fn resolve_vars(variables: &mut Variables,
+                    arrays: &mut Arrays,
+                    var_paths: &HashMap<String, PathBuf>)
+                    -> LoadResult<()> {
+    let mut names: Vec<String> = variables.keys()
+        .filter(|k| !DEFAULT_GLOBALS.contains(k.as_str()))
+        .cloned()
+        .collect();
+    names.sort();
+    let mut done: HashMap<String, bool> = HashMap::new();
+    let mut errors: Vec<String> = Vec::new();
+    for name in &names {
+        resolve_var(name, variables, arrays, var_paths, &mut done, &mut Vec::new(), &mut errors);
+    }
+    if !errors.is_empty() {
+        // [#SPC-core-load-error-vars-return-2]
+        return Err(LoadError::new(format!("could not resolve the globals:\n- {}",
+                                          errors.join("\n- "))));
+    }
     Ok(())
 }
 
@@ -123,51 +269,287 @@ pub fn fill_text_fields(artifacts: &mut Artifacts,
                         repo_map: &mut HashMap<PathBuf, PathBuf>)
                         -> LoadResult<()> {
     // resolve all text blocks in artifacts
-    let mut error = false;
-    let mut errors: Vec<(&str, strfmt::FmtError)> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
     for (name, art) in artifacts.iter_mut() {
         trace!("filling in {}", name);
-        errors.clear();
         let cwd = art.path.parent().expect("no-path-parent").to_path_buf();
         try!(utils::find_and_insert_repo(&cwd, repo_map));
         variables.insert("cwd".to_string(), cwd.to_str().expect("utf-path").to_string());
         variables.insert("repo".to_string(), repo_map.get(&cwd).expect("repo_map")
                             .to_str().expect("utf-path").to_string());
 
-        // evaluate text
-        match strfmt::strfmt(art.text.as_str(), &variables) {
-            Ok(t) => art.text = t,
-            Err(err) => errors.push(("text field", err)),
+        let text = match fmt_known(&art.text, variables) {
+            Ok(t) => t,
+            Err(e) => {
+                errors.push(format!("[{}] {}: {}", art.path.display(), name, e.desc));
+                continue;
+            }
         };
-        if errors.len() > 0 {
+        let undefined = find_var_refs(&text);
+        if !undefined.is_empty() {
             // [#SPC-core-load-error-text-3]
-            error!(" resolving variables on [{:?}] {} failed: {:?}", art.path, name, errors);
-            error = true;
+            for k in undefined {
+                errors.push(format!("[{}] {} uses undefined global {:?}{}",
+                                    art.path.display(), name, k,
+                                    did_you_mean_var(k, variables)));
+            }
+            continue;
         }
+        // unescape the braces
+        match strfmt::strfmt(&text, &Variables::new()) {
+            Ok(t) => art.text = t,
+            Err(e) => errors.push(format!("[{}] {}: {}", art.path.display(), name, e)),
+        };
     }
 
-    if error {
+    if !errors.is_empty() {
         // [#SPC-core-load-error-text-return]
-        return Err(LoadError::new("failure to resolve artifact text fields".to_string()));
+        errors.sort();
+        for e in &errors {
+            error!("{}", e);
+        }
+        return Err(LoadError::new(format!("failure to resolve artifact text fields:\n- {}",
+                                          errors.join("\n- "))));
     }
     trace!("Done filling");
     Ok(())
 }
 
+/// escape the braces in a value so that it is not formatted
+fn escape_braces(s: &str) -> String {
+    s.replace("{", "{{").replace("}", "}}")
+}
+
+/// get the value of a field of another artifact for a transclusion
+fn transclude_field(artifact: &Artifact, field: &str) -> Option<String> {
+    let names = |names: &ArtNames| {
+        let mut names: Vec<&str> = names.iter().map(|n| n.raw.as_str()).collect();
+        names.sort();
+        names.join(", ")
+    };
+    // the percentages are negative when they could not be calculated
+    let percent = |value: f32| if value < 0. {
+        "(unresolved)".to_string()
+    } else {
+        ((value * 100.).round() as i64).to_string()
+    };
+    Some(match field {
+        "completed" => percent(artifact.completed),
+        "tested" => percent(artifact.tested),
+        "parts" => names(&artifact.parts),
+        "partof" => names(&artifact.partof),
+        "path" => artifact.path.display().to_string(),
+        _ => return None,
+    })
+}
+
+/// expand the transclusions in the text of an artifact, first expanding the
+/// text of the artifacts it transcludes. `stack` is the artifacts whose
+/// text is being expanded, which is used to detect cycles
+fn expand_text(name: &ArtNameRc,
+               artifacts: &Artifacts,
+               repo_map: &mut HashMap<PathBuf, PathBuf>,
+               expanded: &mut HashMap<ArtNameRc, String>,
+               stack: &mut Vec<ArtNameRc>)
+               -> LoadResult<()> {
+    if expanded.contains_key(name) {
+        return Ok(());
+    }
+    if let Some(i) = stack.iter().position(|n| n == name) {
+        let cycle: Vec<&str> = stack[i..].iter()
+            .chain(Some(name))
+            .map(|n| n.raw.as_str())
+            .collect();
+        return Err(LoadError::new(format!("transclusion cycle: {}", cycle.join(" -> "))));
+    }
+    let artifact = &artifacts[name];
+    let error = |msg: String| {
+        LoadError::new(format!("[{}] {}: {}", artifact.path.display(), name, msg))
+    };
+    stack.push(name.clone());
+    let text = &artifact.text;
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in TRANSCLUDE_RE.captures_iter(text) {
+        let (start, end) = caps.pos(0).unwrap();
+        out.push_str(&text[last..start]);
+        last = end;
+        let (kind, spec) = match (caps.at(1), caps.at(2)) {
+            (Some(k), Some(s)) => (k, s.trim()),
+            _ => {
+                // escaped braces are unescaped when the globals are filled in
+                out.push_str(&text[start..end]);
+                continue;
+            }
+        };
+        let (target, field) = match kind {
+            "ART" => match spec.rfind('.') {
+                Some(i) => (&spec[..i], &spec[i + 1..]),
+                None => return Err(error(format!("{{ART:{}}} must be {{ART:NAME.FIELD}}", spec))),
+            },
+            _ => (spec, ""),
+        };
+        let target = match ArtNameRc::from_str(target) {
+            Ok(t) => t,
+            Err(e) => return Err(error(e.desc)),
+        };
+        let tartifact = match artifacts.get(&target) {
+            Some(a) => a,
+            None => {
+                return Err(error(format!("transcluded {} does not exist{}",
+                                         target, utils::did_you_mean(&target, artifacts.keys()))))
+            }
+        };
+        if kind == "LOC" {
+            let value = match tartifact.loc {
+                Some(ref loc) => {
+                    let cwd = artifact.path.parent().expect("no-path-parent");
+                    try!(utils::find_and_insert_repo(cwd, repo_map));
+                    let path = utils::relative_path(&loc.path, &repo_map[cwd]);
+                    format!("{}:{}", path.display(), loc.line_col.0)
+                }
+                None => "(not implemented)".to_string(),
+            };
+            out.push_str(&escape_braces(&value));
+        } else if field == "text" {
+            try!(expand_text(&target, artifacts, repo_map, expanded, stack));
+            out.push_str(&expanded[&target]);
+        } else {
+            match transclude_field(tartifact, field) {
+                Some(v) => out.push_str(&escape_braces(&v)),
+                None => {
+                    return Err(error(format!("unknown field in {{ART:{}}}, the fields are: \
+                                              text, completed, tested, parts, partof, path",
+                                             spec)))
+                }
+            }
+        }
+    }
+    out.push_str(&text[last..]);
+    stack.pop();
+    expanded.insert(name.clone(), out);
+    Ok(())
+}
+
+/// replace the transclusions in the text of the artifacts with the fields
+/// of other artifacts:
+/// - `{ART:NAME.text}`: the text of the artifact (after its own transclusions)
+/// - `{ART:NAME.completed}` and `{ART:NAME.tested}`: the rounded percentage
+///   (`(unresolved)` if it could not be calculated)
+/// - `{ART:NAME.parts}` and `{ART:NAME.partof}`: the sorted names
+/// - `{ART:NAME.path}`: the file it is defined in
+/// - `{LOC:NAME}`: where it is implemented, as `path:line` in the repo
+///
+/// This must be done after linking and before the globals are filled in, so
+/// the transcluded text uses the globals of the artifact it is included in
+/// partof: #SPC-vars-transclude
+pub fn transclude(artifacts: &mut Artifacts, repo_map: &mut HashMap<PathBuf, PathBuf>)
+                  -> LoadResult<()> {
+    let mut names: Vec<ArtNameRc> = artifacts.keys().cloned().collect();
+    names.sort();
+    let mut expanded: HashMap<ArtNameRc, String> = HashMap::new();
+    for name in &names {
+        try!(expand_text(name, artifacts, repo_map, &mut expanded, &mut Vec::new()));
+    }
+    for (name, text) in expanded.drain() {
+        artifacts.get_mut(&name).unwrap().text = text;
+    }
+    Ok(())
+}
+
+/// find the names of the artifacts mentioned in text, i.e. "see SPC-foo".
+/// Names in code, i.e. "`REQ-foo`", are not mentions
+pub fn find_mentions(text: &str) -> ArtNames {
+    let code: Vec<(usize, usize)> = CODE_RE.find_iter(text).collect();
+    MENTION_RE.find_iter(text)
+        .filter(|&(start, _)| !code.iter().any(|&(s, e)| s <= start && start < e))
+        .filter_map(|(start, end)| ArtNameRc::from_str(&text[start..end]).ok())
+        .collect()
+}
+
+/// set the artifacts mentioned in the (filled) text of every artifact
+/// partof: #SPC-artifact-mentions
+pub fn set_mentions(artifacts: &mut Artifacts) {
+    for (name, art) in artifacts.iter_mut() {
+        art.mentions = find_mentions(&art.text);
+        art.mentions.remove(name);
+    }
+}
+
+/// expand the globals in a code path, with a path for each element of the
+/// arrays it uses
+fn expand_code_path(path: &str, variables: &Variables, arrays: &Arrays)
+                    -> Result<Vec<String>, String> {
+    let caps = match VAR_RE.captures_iter(path).find(|c| c.at(1).is_some()) {
+        Some(c) => c,
+        None => {
+            // unescape the braces
+            return strfmt::strfmt(path, &Variables::new())
+                .map(|p| vec![p])
+                .map_err(|e| format!("code path {:?}: {}", path, e));
+        }
+    };
+    let key = caps.at(1).unwrap();
+    let (start, end) = caps.pos(0).unwrap();
+    let values = match (arrays.get(key), variables.get(key)) {
+        (Some(a), _) => a.clone(),
+        (None, Some(v)) => vec![v.clone()],
+        (None, None) => {
+            return Err(format!("code path {:?} uses undefined global {:?}{}",
+                               path, key, did_you_mean_var(key, variables)))
+        }
+    };
+    let mut paths = Vec::new();
+    for v in values {
+        let p = format!("{}{}{}", &path[..start], escape_braces(&v), &path[end..]);
+        paths.extend(try!(expand_code_path(&p, variables, arrays)));
+    }
+    Ok(paths)
+}
+
+/// resolve the globals in the `code_paths` and `exclude_code_paths` of the
+/// settings, which must be done after the globals are resolved. A path that
+/// uses an array is expanded into a path for each element, i.e.
+/// `{repo}/{crates}/src` with `crates = ['a', 'b']`
+/// partof: #SPC-vars-code-paths
+pub fn resolve_code_paths(settings: &mut Settings, variables: &Variables, arrays: &Arrays)
+                          -> LoadResult<()> {
+    let mut errors: Vec<String> = Vec::new();
+    for paths in &mut [&mut settings.code_paths, &mut settings.exclude_code_paths] {
+        let mut resolved = VecDeque::new();
+        for p in paths.iter() {
+            match expand_code_path(p.to_str().expect("utf-path"), variables, arrays) {
+                Ok(e) => resolved.extend(e.into_iter().map(PathBuf::from)),
+                Err(e) => errors.push(e),
+            }
+        }
+        **paths = resolved;
+    }
+    if !errors.is_empty() {
+        return Err(LoadError::new(format!("could not resolve the code paths:\n- {}",
+                                          errors.join("\n- "))));
+    }
+    Ok(())
+}
+
 /// resolve raw loaded variables, replacing default and user-defined globals
-/// recursively
+/// recursively. Returns the globals, which includes the arrays joined with
+/// ", ", and the elements of the arrays
 /// partof: #SPC-vars
-pub fn resolve_loaded_vars(mut loaded_vars: Vec<(PathBuf, Variables)>,
+pub fn resolve_loaded_vars(mut loaded_vars: Vec<(PathBuf, Globals)>,
                            repo_map: &mut HashMap<PathBuf, PathBuf>)
-                           -> LoadResult<Variables> {
+                           -> LoadResult<(Variables, Arrays)> {
     let mut variables = Variables::new();
+    let mut arrays = Arrays::new();
+    let mut var_paths: HashMap<String, PathBuf> = HashMap::new();
     debug!("Resolving default globals in variables, see SPC-vars.1");
     for pv in loaded_vars.drain(0..) {
         let p = pv.0;
         let v = pv.1;
-        try!(resolve_default_vars(&v, p.as_path(), &mut variables, repo_map));
+        try!(resolve_default_vars(&v, p.as_path(), &mut variables, &mut arrays,
+                                   &mut var_paths, repo_map));
     }
     debug!("Resolving variables, see SPC-vars.2");
-    try!(resolve_vars(&mut variables));
-    Ok(variables)
+    try!(resolve_vars(&mut variables, &mut arrays, &var_paths));
+    Ok((variables, arrays))
 }
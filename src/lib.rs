@@ -17,10 +17,26 @@ extern crate toml;
 // # ui-cmdline crates
 extern crate clap;
 extern crate ansi_term;
+extern crate atty;
+extern crate libc;
+
+// # lsp crates
+extern crate rustc_serialize;
+
+// # serialization crates
+#[cfg(feature = "serde")]
+#[macro_use] extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 pub mod core;
 pub mod ui;
 pub mod cmd;
+pub mod lsp;
+pub mod project;
+
+pub use project::{Project, ProjectError, Stats};
+pub use core::{Artifact, Artifacts, ArtType, ArtName, ArtNameRc, ArtNames, Loc, Settings};
 
 
 pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,16 @@
+//! lsp: a language server for artifacts
+//!
+//! implements the parts of the language server protocol that help when
+//! editing artifacts and the code that implements them: hover, definition,
+//! references and completion of artifact names, and diagnostics for
+//! artifact files. Messages are read from stdin and written to stdout.
+
+mod types;
+pub mod rpc;
+pub mod text;
+pub mod server;
+
+#[cfg(test)]
+mod tests;
+
+pub use lsp::server::{Server, serve};
@@ -0,0 +1,72 @@
+//! reading and writing json-rpc messages with the headers used by the
+//! language server protocol
+
+use super::types::*;
+
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// read the next message, returning None at the end of the input
+pub fn read_message<R: BufRead>(r: &mut R) -> io::Result<Option<Json>> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if try!(r.read_line(&mut line)) == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line == "" {
+            break;
+        }
+        let mut split = line.splitn(2, ':');
+        let key = split.next().unwrap().trim().to_lowercase();
+        if key == "content-length" {
+            length = split.next().and_then(|v| v.trim().parse().ok());
+        }
+    }
+    let length = match length {
+        Some(l) => l,
+        None => return Err(invalid_data("missing Content-Length header".to_string())),
+    };
+    let mut buf = vec![0; length];
+    try!(r.read_exact(&mut buf));
+    let text = try!(String::from_utf8(buf).map_err(|e| invalid_data(e.to_string())));
+    match Json::from_str(&text) {
+        Ok(j) => Ok(Some(j)),
+        Err(e) => Err(invalid_data(e.to_string())),
+    }
+}
+
+/// write a message with its header
+pub fn write_message<W: Write>(w: &mut W, msg: &Json) -> io::Result<()> {
+    let text = msg.to_string();
+    try!(write!(w, "Content-Length: {}\r\n\r\n{}", text.len(), text));
+    w.flush()
+}
+
+/// create a json object from its fields
+pub fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(BTreeMap::from_iter(fields.into_iter().map(|(k, v)| (k.to_string(), v))))
+}
+
+pub fn response(id: &Json, result: Json) -> Json {
+    object(vec![("jsonrpc", "2.0".to_json()),
+                ("id", id.clone()),
+                ("result", result)])
+}
+
+pub fn error_response(id: &Json, code: i64, message: &str) -> Json {
+    object(vec![("jsonrpc", "2.0".to_json()),
+                ("id", id.clone()),
+                ("error", object(vec![("code", code.to_json()),
+                                      ("message", message.to_json())]))])
+}
+
+pub fn notification(method: &str, params: Json) -> Json {
+    object(vec![("jsonrpc", "2.0".to_json()),
+                ("method", method.to_json()),
+                ("params", params)])
+}
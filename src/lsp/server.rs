@@ -0,0 +1,448 @@
+//! the state of the language server and the handlers for every message
+
+use toml::Parser;
+
+use super::types::*;
+use super::rpc;
+use super::rpc::object;
+use super::text;
+use super::super::core::save::{self, FileText};
+use super::super::VERSION;
+
+/// diagnostic severities
+const ERROR: u64 = 1;
+const WARNING: u64 = 2;
+
+/// completion item kind for references
+const REFERENCE: u64 = 18;
+
+pub struct Server {
+    cfg: PathBuf,
+    pub artifacts: Artifacts,
+    pub settings: Settings,
+    /// the error from the last time the artifacts were loaded, if any
+    pub load_error: Option<String>,
+    /// the text of the open documents by their uri
+    docs: HashMap<String, String>,
+}
+
+fn get_str<'a>(params: &'a Json, keys: &[&str]) -> Option<&'a str> {
+    params.find_path(keys).and_then(|j| j.as_string())
+}
+
+/// get the uri, line and character of a text document position
+fn get_position(params: &Json) -> Option<(String, usize, usize)> {
+    let uri = match get_str(params, &["textDocument", "uri"]) {
+        Some(u) => u.to_string(),
+        None => return None,
+    };
+    let line = params.find_path(&["position", "line"]).and_then(|j| j.as_u64());
+    let character = params.find_path(&["position", "character"]).and_then(|j| j.as_u64());
+    match (line, character) {
+        (Some(l), Some(c)) => Some((uri, l as usize, c as usize)),
+        _ => None,
+    }
+}
+
+fn diagnostic(range: Json, severity: u64, message: &str) -> Json {
+    object(vec![("range", range),
+                ("severity", severity.to_json()),
+                ("source", "rst".to_json()),
+                ("message", message.to_json())])
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().map(|e| e == "toml").unwrap_or(false)
+}
+
+impl Server {
+    /// create the server, loading the artifacts from the settings directory
+    pub fn new(cfg: &Path) -> Server {
+        let mut server = Server {
+            cfg: cfg.to_path_buf(),
+            artifacts: Artifacts::new(),
+            settings: Settings::new(),
+            load_error: None,
+            docs: HashMap::new(),
+        };
+        server.reload();
+        server
+    }
+
+    /// reload the artifacts. If they cannot be loaded the previously
+    /// loaded artifacts are kept
+    pub fn reload(&mut self) {
+        match core::load_path(&self.cfg) {
+            Ok((artifacts, settings)) => {
+                self.artifacts = artifacts;
+                self.settings = settings;
+                self.load_error = None;
+            }
+            Err(e) => self.load_error = Some(e.to_string()),
+        }
+    }
+
+    /// the text of a file, from the open document if there is one
+    fn get_text(&self, path: &Path) -> Option<String> {
+        match self.docs.get(&text::path_to_uri(path)) {
+            Some(t) => Some(t.clone()),
+            None => save::read_file(path).ok(),
+        }
+    }
+
+    /// handle a message, returning the messages to send back
+    pub fn handle(&mut self, msg: &Json) -> Vec<Json> {
+        let method = msg.find("method").and_then(|m| m.as_string()).unwrap_or("");
+        let null = Json::Null;
+        let params = msg.find("params").unwrap_or(&null);
+        if let Some(id) = msg.find("id") {
+            let result = match method {
+                "initialize" => self.initialize(),
+                "shutdown" => Json::Null,
+                "textDocument/hover" => self.hover(params),
+                "textDocument/definition" => self.definition(params),
+                "textDocument/references" => self.references(params),
+                "textDocument/completion" => self.completion(params),
+                // responses to requests from the server have no method
+                "" => return vec![],
+                _ => {
+                    return vec![rpc::error_response(id, rpc::METHOD_NOT_FOUND,
+                                                    &format!("unknown method: {}", method))]
+                }
+            };
+            return vec![rpc::response(id, result)];
+        }
+
+        let uri = get_str(params, &["textDocument", "uri"]).unwrap_or("").to_string();
+        match method {
+            "initialized" => match self.load_error {
+                Some(ref e) => vec![self.show_error(e)],
+                None => vec![],
+            },
+            "textDocument/didOpen" => {
+                let t = get_str(params, &["textDocument", "text"]).unwrap_or("");
+                self.docs.insert(uri.clone(), t.to_string());
+                self.diagnose(&uri).into_iter().collect()
+            }
+            "textDocument/didChange" => {
+                // the full text is sent on every change
+                let changes = params.find("contentChanges").and_then(|c| c.as_array());
+                let t = changes.and_then(|c| c.last())
+                    .and_then(|c| c.find("text"))
+                    .and_then(|t| t.as_string());
+                if let Some(t) = t {
+                    self.docs.insert(uri.clone(), t.to_string());
+                }
+                self.diagnose(&uri).into_iter().collect()
+            }
+            "textDocument/didSave" => {
+                // percentages and valid names can change with any file
+                self.reload();
+                let mut out = Vec::new();
+                if let Some(ref e) = self.load_error {
+                    out.push(self.show_error(e));
+                }
+                let mut uris: Vec<&String> = self.docs.keys().collect();
+                uris.sort();
+                out.extend(uris.iter().filter_map(|u| self.diagnose(u)));
+                out
+            }
+            "textDocument/didClose" => {
+                self.docs.remove(&uri);
+                match text::uri_to_path(&uri) {
+                    Some(ref p) if is_toml(p) => {
+                        vec![self.publish(&uri, vec![])]
+                    }
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        }
+    }
+
+    fn initialize(&self) -> Json {
+        let sync = object(vec![("openClose", true.to_json()),
+                               // the full text is sent on every change
+                               ("change", 1u64.to_json()),
+                               ("save", object(vec![("includeText", false.to_json())]))]);
+        let capabilities = object(vec![
+            ("textDocumentSync", sync),
+            ("hoverProvider", true.to_json()),
+            ("definitionProvider", true.to_json()),
+            ("referencesProvider", true.to_json()),
+            ("completionProvider", object(vec![("triggerCharacters", vec!["#".to_string()].to_json())]))]);
+        object(vec![("capabilities", capabilities),
+                    ("serverInfo", object(vec![("name", "rst".to_json()),
+                                               ("version", VERSION.to_json())]))])
+    }
+
+    fn show_error(&self, msg: &str) -> Json {
+        rpc::notification("window/showMessage",
+                          object(vec![("type", ERROR.to_json()),
+                                      ("message", format!("rst: {}", msg).to_json())]))
+    }
+
+    fn publish(&self, uri: &str, diagnostics: Vec<Json>) -> Json {
+        rpc::notification("textDocument/publishDiagnostics",
+                          object(vec![("uri", uri.to_json()),
+                                      ("diagnostics", Json::Array(diagnostics))]))
+    }
+
+    /// get the artifact name at the position of a request
+    fn name_at(&self, params: &Json) -> Option<(ArtName, String, usize, usize, usize)> {
+        let (uri, line, character) = match get_position(params) {
+            Some(p) => p,
+            None => return None,
+        };
+        let t = match text::uri_to_path(&uri).and_then(|p| self.get_text(&p)) {
+            Some(t) => t,
+            None => return None,
+        };
+        text::name_at(&t, line, character).map(|(n, start, end)| (n, uri, line, start, end))
+    }
+
+    /// show the status and text of the artifact under the cursor
+    /// partof: #SPC-lsp-hover
+    fn hover(&self, params: &Json) -> Json {
+        let (name, _, line, start, end) = match self.name_at(params) {
+            Some(n) => n,
+            None => return Json::Null,
+        };
+        // the name is displayed the way it was defined
+        let (name, artifact) = match self.artifacts.iter().find(|&(n, _)| n.as_ref() == &name) {
+            Some(a) => a,
+            None => return Json::Null,
+        };
+        let repo = self.cfg.parent().unwrap_or(&self.cfg);
+        let mut value = format!("**{}** {}% done {}% tested",
+                                name.raw,
+                                (artifact.completed * 100.) as i64,
+                                (artifact.tested * 100.) as i64);
+        if artifact.text != "" {
+            value.push_str("\n\n");
+            value.push_str(&artifact.text);
+        }
+        if artifact.path != PathBuf::from("PARENT") {
+            value.push_str(&format!("\n\n*defined at* `{}`",
+                                    core::utils::relative_path(&artifact.path, repo)
+                                        .display()));
+        }
+        if let Some(ref loc) = artifact.loc {
+            value.push_str(&format!("\n\n*implemented at* `{}:{}`",
+                                    core::utils::relative_path(&loc.path, repo).display(),
+                                    loc.line_col.0));
+        }
+        object(vec![("contents", object(vec![("kind", "markdown".to_json()),
+                                             ("value", value.to_json())])),
+                    ("range", text::range(line, start, end))])
+    }
+
+    /// get the location of the header of an artifact
+    fn header_location(&self, name: &ArtName) -> Option<Json> {
+        let artifact = match self.artifacts.get(name) {
+            Some(a) if a.path != PathBuf::from("PARENT") => a,
+            _ => return None,
+        };
+        let t = match self.get_text(&artifact.path) {
+            Some(t) => t,
+            None => return None,
+        };
+        let line = match FileText::from_str(&t) {
+            Ok(file) => match file.find(name) {
+                Some(i) => file.header_lines()[i],
+                None => 0,
+            },
+            Err(_) => 0,
+        };
+        Some(text::location(&artifact.path, text::line_range(&t, line)))
+    }
+
+    /// go to where the artifact under the cursor is defined
+    /// partof: #SPC-lsp-definition
+    fn definition(&self, params: &Json) -> Json {
+        match self.name_at(params).and_then(|n| self.header_location(&n.0)) {
+            Some(l) => l,
+            None => Json::Null,
+        }
+    }
+
+    /// find every reference to the artifact under the cursor in the code
+    /// and the partof of other artifacts
+    /// partof: #SPC-lsp-references
+    fn references(&self, params: &Json) -> Json {
+        let name = match self.name_at(params) {
+            Some(n) => n.0,
+            None => return Json::Null,
+        };
+        let mut out: Vec<Json> = Vec::new();
+        let declaration = params.find_path(&["context", "includeDeclaration"])
+            .and_then(|d| d.as_boolean())
+            .unwrap_or(true);
+        if declaration {
+            out.extend(self.header_location(&name));
+        }
+        for path in save::artifact_files(&self.artifacts) {
+            let t = match self.get_text(&path) {
+                Some(t) => t,
+                None => continue,
+            };
+            let file = match FileText::from_str(&t) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+            for (table, header) in file.tables.iter().zip(file.header_lines()) {
+                let partof = match table.get_str("partof") {
+                    Ok(Some(p)) => p,
+                    _ => continue,
+                };
+                match ArtNames::from_str(&partof) {
+                    Ok(ref names) if names.contains(&name) => {
+                        let line = header + table.key_offset("partof").unwrap();
+                        out.push(text::location(&path, text::line_range(&t, line)));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for path in core::locs::find_code_files(&self.settings).unwrap_or(vec![]) {
+            let t = match self.get_text(&path) {
+                Some(t) => t,
+                None => continue,
+            };
+            for (line, start, end) in text::find_code_refs(&t, &name) {
+                out.push(text::location(&path, text::range(line, start, end)));
+            }
+        }
+        Json::Array(out)
+    }
+
+    /// complete the names of artifacts after `#` in code or anywhere in
+    /// artifact files
+    /// partof: #SPC-lsp-completion
+    fn completion(&self, params: &Json) -> Json {
+        let (uri, line, character) = match get_position(params) {
+            Some(p) => p,
+            None => return Json::Null,
+        };
+        let path = match text::uri_to_path(&uri) {
+            Some(p) => p,
+            None => return Json::Null,
+        };
+        let t = self.get_text(&path).unwrap_or(String::new());
+        let l = text::get_line(&t, line).unwrap_or("");
+        let (prefix, start) = text::name_prefix(l, character);
+        let after_hash = l[..text::byte_index(l, start)].ends_with('#');
+        if !(after_hash || is_toml(&path) && prefix != "") {
+            return Json::Array(vec![]);
+        }
+        let prefix = prefix.to_uppercase();
+        let mut names: Vec<_> = self.artifacts.keys()
+            .filter(|n| n.raw.to_uppercase().starts_with(&prefix))
+            .collect();
+        names.sort();
+        let items = names.iter()
+            .map(|n| {
+                let a = &self.artifacts[*n];
+                object(vec![("label", n.raw.to_json()),
+                            ("kind", REFERENCE.to_json()),
+                            ("detail", format!("{}% done {}% tested",
+                                               (a.completed * 100.) as i64,
+                                               (a.tested * 100.) as i64).to_json()),
+                            ("documentation", a.text.to_json()),
+                            ("textEdit", object(vec![("range", text::range(line, start,
+                                                                           character)),
+                                                     ("newText", n.raw.to_json())]))])
+            })
+            .collect();
+        Json::Array(items)
+    }
+
+    /// publish the problems in an open artifact file. Files that
+    /// are not toml files are not diagnosed
+    /// partof: #SPC-lsp-diagnostics
+    fn diagnose(&self, uri: &str) -> Option<Json> {
+        let path = match text::uri_to_path(uri) {
+            Some(ref p) if is_toml(p) => p.clone(),
+            _ => return None,
+        };
+        let t = match self.docs.get(uri) {
+            Some(t) => t,
+            None => return None,
+        };
+        let mut out: Vec<Json> = Vec::new();
+
+        // errors from the toml parser have a position
+        let mut parser = Parser::new(t);
+        if parser.parse().is_none() {
+            for e in &parser.errors {
+                let (line, col) = parser.to_linecol(e.lo);
+                let l = text::get_line(t, line).unwrap_or("");
+                let start = if l.is_char_boundary(col) { text::utf16_len(&l[..col]) } else { 0 };
+                out.push(diagnostic(text::range(line, start, text::utf16_len(l)),
+                                    ERROR, &e.desc));
+            }
+            return Some(self.publish(uri, out));
+        }
+        let file = match FileText::from_str(t) {
+            Ok(f) => f,
+            Err(e) => {
+                out.push(diagnostic(text::line_range(t, 0), ERROR, &e.to_string()));
+                return Some(self.publish(uri, out));
+            }
+        };
+
+        // errors from the loader do not have a position
+        let mut artifacts = Artifacts::new();
+        if let Err(e) = core::load::load_toml(&path, t, &mut artifacts,
+                                              &mut Vec::new(), &mut Vec::new()) {
+            out.push(diagnostic(text::line_range(t, 0), ERROR, &e.to_string()));
+        }
+
+        for (table, header) in file.tables.iter().zip(file.header_lines()) {
+            if !table.is_artifact() {
+                continue;
+            }
+            if let Err(e) = ArtName::from_str(&table.name) {
+                out.push(diagnostic(text::line_range(t, header), ERROR, &e.to_string()));
+            }
+            let line = match table.key_offset("partof") {
+                Some(o) => header + o,
+                None => continue,
+            };
+            let names = match table.get_str("partof")
+                .and_then(|p| ArtNames::from_str(&p.unwrap())) {
+                Ok(n) => n,
+                Err(e) => {
+                    out.push(diagnostic(text::line_range(t, line), ERROR, &e.to_string()));
+                    continue;
+                }
+            };
+            let mut names: Vec<_> = names.iter().collect();
+            names.sort();
+            for n in names {
+                if !self.artifacts.contains_key(n) && file.find(n).is_none() {
+                    let msg = format!("partof {} does not exist{}",
+                                      n.raw,
+                                      core::utils::did_you_mean(n, self.artifacts.keys()));
+                    out.push(diagnostic(text::line_range(t, line), WARNING, &msg));
+                }
+            }
+        }
+        Some(self.publish(uri, out))
+    }
+}
+
+/// serve requests until the input ends or the exit notification is received
+/// partof: #SPC-lsp
+pub fn serve<R: BufRead, W: Write>(r: &mut R, w: &mut W, cfg: &Path) -> io::Result<()> {
+    let mut server = Server::new(cfg);
+    while let Some(msg) = try!(rpc::read_message(r)) {
+        if msg.find("method").and_then(|m| m.as_string()) == Some("exit") {
+            break;
+        }
+        for out in server.handle(&msg) {
+            try!(rpc::write_message(w, &out));
+        }
+    }
+    Ok(())
+}
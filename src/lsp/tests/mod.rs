@@ -0,0 +1,3 @@
+/// test the language server
+
+mod test_server;
@@ -0,0 +1,152 @@
+use std::fs;
+use std::io::Cursor;
+
+use core::TempDir;
+
+use super::super::types::*;
+use super::super::rpc;
+use super::super::text;
+use super::super::server::*;
+
+// `\x23` is used instead of `#` so that the references in the tests
+// are not found as locations when rst is run on itself
+
+/// create a project with a requirement, its specification and code
+fn create_project(dir: &Path) {
+    for d in &[".rst", "reqs", "src"] {
+        fs::create_dir_all(dir.join(d)).unwrap();
+    }
+    let files = [(".rst/settings.toml", "[settings]\n\
+                                         artifact_paths = ['{repo}/reqs']\n\
+                                         code_paths = ['{repo}/src']\n"),
+                 ("reqs/foo.toml", "[REQ-foo]\ntext = 'the foo'\n\n\
+                                    [SPC-bar]\npartof = 'REQ-[foo]'\n"),
+                 ("src/foo.rs", "// \x23SPC-foo\nfn foo() {} // \x23SPC-bar\n")];
+    for &(f, t) in &files {
+        fs::File::create(dir.join(f)).unwrap().write_all(t.as_bytes()).unwrap();
+    }
+}
+
+fn request(server: &mut Server, method: &str, params: &str) -> Json {
+    let msg = Json::from_str(&format!(r#"{{"jsonrpc": "2.0", "id": 1, "method": "{}",
+                                          "params": {}}}"#, method, params)).unwrap();
+    let mut out = server.handle(&msg);
+    assert_eq!(out.len(), 1);
+    out.pop().unwrap().find("result").unwrap().clone()
+}
+
+fn notify(server: &mut Server, method: &str, params: &str) -> Vec<Json> {
+    let msg = Json::from_str(&format!(r#"{{"jsonrpc": "2.0", "method": "{}",
+                                          "params": {}}}"#, method, params)).unwrap();
+    server.handle(&msg)
+}
+
+fn position(uri: &str, line: usize, character: usize) -> String {
+    format!(r#"{{"textDocument": {{"uri": "{}"}},
+                "position": {{"line": {}, "character": {}}},
+                "context": {{"includeDeclaration": true}}}}"#, uri, line, character)
+}
+
+/// get the (file name, line) of locations
+fn locations(result: &Json) -> Vec<(String, u64)> {
+    result.as_array().unwrap().iter()
+        .map(|l| {
+            let path = text::uri_to_path(l.find("uri").unwrap().as_string().unwrap()).unwrap();
+            (path.file_name().unwrap().to_string_lossy().to_string(),
+             l.find_path(&["range", "start", "line"]).unwrap().as_u64().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn test_text() {
+    let path = PathBuf::from("/a dir/ü.rs");
+    assert_eq!(text::path_to_uri(&path), "file:///a%20dir/%C3%BC.rs");
+    assert_eq!(text::uri_to_path(&text::path_to_uri(&path)).unwrap(), path);
+    // an escape at the end is decoded, and one that is cut off is kept
+    assert_eq!(text::uri_to_path("file:///a%20").unwrap(), PathBuf::from("/a "));
+    assert_eq!(text::uri_to_path("file:///a%2").unwrap(), PathBuf::from("/a%2"));
+    assert_eq!(text::uri_to_path("file:///a%zz").unwrap(), PathBuf::from("/a%zz"));
+
+    // positions are in utf-16 code units
+    let t = "\n// ü \x23SPC-foo-bar.";
+    assert_eq!(text::name_at(t, 1, 8).unwrap(), (ArtName::from_str("SPC-foo-bar").unwrap(), 6, 17));
+    assert_eq!(text::name_at(t, 1, 2), None);
+    assert_eq!(text::name_prefix("// ü \x23SPC-fo", 12), ("SPC-fo", 6));
+    assert_eq!(text::find_code_refs(t, &ArtName::from_str("spc-foo-bar").unwrap()),
+               vec![(1, 5, 17)]);
+    assert_eq!(text::find_code_refs(t, &ArtName::from_str("spc-foo").unwrap()), vec![]);
+
+    // messages are framed by their length
+    let msg = Json::from_str(r#"{"id": 1, "text": "ü"}"#).unwrap();
+    let mut w: Vec<u8> = Vec::new();
+    rpc::write_message(&mut w, &msg).unwrap();
+    let header = format!("Content-Length: {}\r\n\r\n", msg.to_string().len());
+    assert!(w.starts_with(header.as_bytes()));
+    let mut r = Cursor::new(w);
+    assert_eq!(rpc::read_message(&mut r).unwrap(), Some(msg));
+    assert_eq!(rpc::read_message(&mut r).unwrap(), None);
+}
+
+#[test]
+/// partof: #TST-lsp
+fn test_server() {
+    let dir = TempDir::new("rst-test-lsp");
+    create_project(&dir);
+    let mut server = Server::new(&dir.join(".rst"));
+    assert_eq!(server.load_error, None);
+    let code = text::path_to_uri(&dir.join("src/foo.rs"));
+    let reqs = text::path_to_uri(&dir.join("reqs/foo.toml"));
+
+    let result = request(&mut server, "initialize", "{}");
+    assert!(result.find_path(&["capabilities", "hoverProvider"]).is_some());
+
+    let result = request(&mut server, "textDocument/hover", &position(&code, 1, 18));
+    let hover = result.find_path(&["contents", "value"]).unwrap().as_string().unwrap();
+    assert!(hover.starts_with("**SPC-bar** 100% done 0% tested"), hover.to_string());
+    assert!(hover.contains("*implemented at* `src/foo.rs:2`"), hover.to_string());
+    assert_eq!(request(&mut server, "textDocument/hover", &position(&code, 1, 3)), Json::Null);
+
+    let result = request(&mut server, "textDocument/definition", &position(&code, 1, 18));
+    assert_eq!(locations(&Json::Array(vec![result])), vec![("foo.toml".to_string(), 3)]);
+
+    // the declaration, the partof of SPC-bar (which is compressed) and the code
+    let result = request(&mut server, "textDocument/references", &position(&reqs, 0, 3));
+    assert_eq!(locations(&result), vec![("foo.toml".to_string(), 0),
+                                        ("foo.toml".to_string(), 4)]);
+    let result = request(&mut server, "textDocument/references", &position(&code, 1, 18));
+    assert_eq!(locations(&result), vec![("foo.toml".to_string(), 3),
+                                        ("foo.rs".to_string(), 1)]);
+
+    // completion after `#` in code
+    let result = request(&mut server, "textDocument/completion", &position(&code, 0, 7));
+    let labels: Vec<&str> = result.as_array().unwrap().iter()
+        .map(|i| i.find("label").unwrap().as_string().unwrap())
+        .collect();
+    assert_eq!(labels, vec!["SPC", "SPC-bar"]);
+    let result = request(&mut server, "textDocument/completion", &position(&code, 1, 3));
+    assert_eq!(result, Json::Array(vec![]));
+
+    // diagnostics for open artifact files
+    let out = notify(&mut server, "textDocument/didOpen",
+                     &format!(r#"{{"textDocument": {{"uri": "{}", "text": "[SPC-baz]\npartof = 'REQ-[foo, dne]'\n"}}}}"#,
+                              reqs));
+    assert_eq!(out.len(), 1);
+    let diagnostics = out[0].find_path(&["params", "diagnostics"]).unwrap().as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].find("message").unwrap().as_string().unwrap(),
+               "partof REQ-dne does not exist");
+    assert_eq!(diagnostics[0].find_path(&["range", "start", "line"]).unwrap().as_u64(), Some(1));
+
+    let out = notify(&mut server, "textDocument/didChange",
+                     &format!(r#"{{"textDocument": {{"uri": "{}"}},
+                                   "contentChanges": [{{"text": "[SPC-baz\n"}}]}}"#, reqs));
+    let diagnostics = out[0].find_path(&["params", "diagnostics"]).unwrap().as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].find("severity").unwrap().as_u64(), Some(1));
+
+    // code files are not diagnosed
+    let out = notify(&mut server, "textDocument/didOpen",
+                     &format!(r#"{{"textDocument": {{"uri": "{}", "text": ""}}}}"#, code));
+    assert_eq!(out.len(), 0);
+}
@@ -0,0 +1,150 @@
+//! positions, uris and finding artifact names in text
+//!
+//! the protocol counts characters in utf-16 code units, so positions are
+//! converted to byte indexes before the text is sliced
+
+use super::types::*;
+use super::rpc::object;
+
+lazy_static!{
+    /// anything that could be an artifact name
+    static ref NAME_RE: Regex = Regex::new(r"[A-Za-z]{3}-[A-Za-z0-9_-]*[A-Za-z0-9_]").unwrap();
+}
+
+fn is_name_char(c: char) -> bool {
+    match c {
+        'a'...'z' | 'A'...'Z' | '0'...'9' | '-' | '_' => true,
+        _ => false,
+    }
+}
+
+/// convert a `file://` uri to a path
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    if !uri.starts_with("file://") {
+        return None;
+    }
+    let bytes = uri[7..].as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // an escape is the `%` and the two hex digits after it, which may end the uri
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            let hex = String::from_utf8_lossy(&bytes[i + 1..i + 3]).to_string();
+            if let Ok(b) = u8::from_str_radix(&hex, 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).ok().map(PathBuf::from)
+}
+
+/// convert a path to a `file://` uri
+pub fn path_to_uri(path: &Path) -> String {
+    let mut out = "file://".to_string();
+    for b in path.to_string_lossy().bytes() {
+        match b {
+            b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// the length of the text in utf-16 code units
+pub fn utf16_len(s: &str) -> usize {
+    s.chars().map(|c| c.len_utf16()).fold(0, |s, l| s + l)
+}
+
+/// the byte index in the line of a utf-16 character position
+pub fn byte_index(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+/// get a line of the text, without its line ending
+pub fn get_line(text: &str, line: usize) -> Option<&str> {
+    text.split('\n').nth(line).map(|l| l.trim_right_matches('\r'))
+}
+
+pub fn position(line: usize, character: usize) -> Json {
+    object(vec![("line", (line as u64).to_json()),
+                ("character", (character as u64).to_json())])
+}
+
+/// a range within a single line
+pub fn range(line: usize, start: usize, end: usize) -> Json {
+    object(vec![("start", position(line, start)),
+                ("end", position(line, end))])
+}
+
+/// the range of a whole line
+pub fn line_range(text: &str, line: usize) -> Json {
+    range(line, 0, utf16_len(get_line(text, line).unwrap_or("")))
+}
+
+pub fn location(path: &Path, range: Json) -> Json {
+    object(vec![("uri", path_to_uri(path).to_json()),
+                ("range", range)])
+}
+
+/// get the artifact name at a position along with its range
+/// (as the start and end character)
+pub fn name_at(text: &str, line: usize, character: usize) -> Option<(ArtName, usize, usize)> {
+    let l = match get_line(text, line) {
+        Some(l) => l,
+        None => return None,
+    };
+    let index = byte_index(l, character);
+    for (start, end) in NAME_RE.find_iter(l) {
+        if start <= index && index <= end {
+            return match ArtName::from_str(&l[start..end]) {
+                Ok(n) => Some((n, utf16_len(&l[..start]), utf16_len(&l[..end]))),
+                Err(_) => None,
+            };
+        }
+    }
+    None
+}
+
+/// get the part of an artifact name that is before a position, along
+/// with the character where it starts
+pub fn name_prefix(line: &str, character: usize) -> (&str, usize) {
+    let index = byte_index(line, character);
+    let start = match line[..index].rfind(|c: char| !is_name_char(c)) {
+        Some(i) => i + line[i..].chars().next().unwrap().len_utf8(),
+        None => 0,
+    };
+    (&line[start..index], utf16_len(&line[..start]))
+}
+
+/// find every reference to the name in code, which is `#` followed by the name,
+/// returned as (line, start, end)
+pub fn find_code_refs(text: &str, name: &ArtName) -> Vec<(usize, usize, usize)> {
+    let mut out = Vec::new();
+    for (i, l) in text.split('\n').enumerate() {
+        for (start, end) in NAME_RE.find_iter(l) {
+            if start == 0 || &l[start - 1..start] != "#" {
+                continue;
+            }
+            match ArtName::from_str(&l[start..end]) {
+                Ok(ref n) if n == name => {
+                    out.push((i, utf16_len(&l[..start - 1]), utf16_len(&l[..end])))
+                }
+                _ => {}
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,16 @@
+// Traits
+pub use std::io::{self, BufRead, Write};
+pub use std::iter::FromIterator;
+
+pub use std::collections::{BTreeMap, HashMap};
+pub use std::path::{Path, PathBuf};
+
+// modules
+pub use regex::Regex;
+pub use rustc_serialize::json::{Json, ToJson};
+
+pub use super::super::core;
+pub use super::super::core::{
+    Settings, Artifacts,
+    ArtName, ArtNames,
+    LoadFromStr};
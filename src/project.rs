@@ -0,0 +1,461 @@
+//! the library API: load a project and query its artifacts
+//!
+//! ```
+//! use std::path::Path;
+//! use rst_app::{Project, ArtType};
+//!
+//! let project = Project::from_strs(Path::new("/repo"), &[
+//!     ("reqs/foo.toml", "[REQ-foo]\n[SPC-foo]\n[SPC-foo-bar]\npartof = 'REQ-foo'\n"),
+//! ]).unwrap();
+//! assert!(project.get("spc-foo").is_some());
+//! assert_eq!(project.parts_of("REQ-foo").unwrap().len(), 2);
+//! // the `SPC` parent is created automatically
+//! assert_eq!(project.stats_of(ArtType::SPC).count, 3);
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use time;
+#[cfg(feature = "serde")]
+use serde::{ser, de, Serialize, Deserialize};
+
+use core::{load, vars, locs, link, review};
+use core::types::{Globals, Reviewed, Approvals};
+use core::{Artifact, Artifacts, ArtName, ArtNameRc, ArtType, Settings, LoadError};
+use ui::Query;
+
+/// the errors that can happen while loading a project or searching it
+#[derive(Debug)]
+pub enum ProjectError {
+    /// the path to load does not exist
+    NotFound(PathBuf),
+    /// an artifact or settings file could not be read or is invalid
+    Load(LoadError),
+    /// the globals or the text of an artifact could not be resolved
+    Vars(LoadError),
+    /// the code could not be searched for artifact locations
+    Locs(LoadError),
+    /// the artifacts could not be linked, i.e. because of an invalid partof
+    Link(LoadError),
+    /// the query given to `Project::search` is invalid
+    Query(String),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProjectError::NotFound(ref p) => write!(f, "{} does not exist", p.display()),
+            ProjectError::Load(ref e) |
+            ProjectError::Vars(ref e) |
+            ProjectError::Locs(ref e) |
+            ProjectError::Link(ref e) => write!(f, "{}", e),
+            ProjectError::Query(ref e) => write!(f, "invalid query: {}", e),
+        }
+    }
+}
+
+impl error::Error for ProjectError {
+    fn description(&self) -> &str {
+        match *self {
+            ProjectError::NotFound(_) => "project path does not exist",
+            ProjectError::Load(_) => "error loading rst files",
+            ProjectError::Vars(_) => "error resolving variables",
+            ProjectError::Locs(_) => "error finding artifact locations",
+            ProjectError::Link(_) => "error linking artifacts",
+            ProjectError::Query(_) => "invalid query",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ProjectError::Load(ref e) |
+            ProjectError::Vars(ref e) |
+            ProjectError::Locs(ref e) |
+            ProjectError::Link(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProjectError> for LoadError {
+    fn from(err: ProjectError) -> LoadError {
+        match err {
+            ProjectError::Load(e) |
+            ProjectError::Vars(e) |
+            ProjectError::Locs(e) |
+            ProjectError::Link(e) => e,
+            e => LoadError::new(e.to_string()),
+        }
+    }
+}
+
+/// the number of artifacts and their average completed and tested ratios
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub completed: f32,
+    pub tested: f32,
+}
+
+/// the loaded and linked artifacts of a project and its settings
+/// partof: #SPC-project
+#[derive(Debug)]
+pub struct Project {
+    artifacts: Artifacts,
+    settings: Settings,
+}
+
+impl Project {
+    /// load the project from a path, which is a directory or a settings
+    /// file in a repo (a directory containing a `.rst` directory)
+    pub fn from_path(path: &Path) -> Result<Project, ProjectError> {
+        let start = time::get_time();
+        info!("loading path: {}", path.to_string_lossy().as_ref());
+        if !path.exists() {
+            return Err(ProjectError::NotFound(path.to_path_buf()));
+        }
+        let (artifacts, settings, loaded_vars, repo_map) =
+            try!(load::load_raw(path).map_err(ProjectError::Load));
+        // the reviews and approvals are kept in the settings directory,
+        // see SPC-review and SPC-approve
+        let cfg = if path.is_dir() { path } else { path.parent().unwrap() };
+        let reviewed = try!(review::load_reviewed(cfg).map_err(ProjectError::Load));
+        let approvals = try!(review::load_approvals(cfg).map_err(ProjectError::Load));
+        let project = try!(Project::resolve(artifacts, settings, loaded_vars, repo_map,
+                                            &reviewed, &approvals));
+        let total = time::get_time() - start;
+        info!("Done loading: {} artifacts loaded successfullly in {:.3} seconds",
+              project.artifacts.len(), total.num_milliseconds() as f64 * 1e-3);
+        Ok(project)
+    }
+
+    /// load the project from the text of its files, given as
+    /// `(path, text)` pairs with paths relative to the `repo` directory.
+    ///
+    /// Nothing is read from disk: the `paths` in the settings are not
+    /// loaded, the `code_paths` are not searched for locations, no links
+    /// are suspect and no artifacts are approved
+    pub fn from_strs(repo: &Path, files: &[(&str, &str)]) -> Result<Project, ProjectError> {
+        let mut artifacts = Artifacts::new();
+        let mut settings = Settings::new();
+        let mut loaded_settings: Vec<(PathBuf, Settings)> = Vec::new();
+        let mut loaded_vars: Vec<(PathBuf, Globals)> = Vec::new();
+        // every file is known to be in the repo, so it is not searched for
+        let mut repo_map: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for &(path, text) in files {
+            let path = repo.join(path);
+            repo_map.insert(path.parent().unwrap().to_path_buf(), repo.to_path_buf());
+            try!(load::load_toml(&path, text, &mut artifacts, &mut loaded_settings,
+                                 &mut loaded_vars)
+                .map_err(ProjectError::Load));
+        }
+        try!(load::resolve_settings(&mut settings, &mut repo_map, &loaded_settings)
+            .map_err(ProjectError::Load));
+        settings.paths = VecDeque::new();
+        settings.code_paths = VecDeque::new();
+        Project::resolve(artifacts, settings, loaded_vars, repo_map, &Reviewed::new(),
+                         &Approvals::new())
+    }
+
+    /// resolve the variables, find the locations, link the artifacts and
+    /// then fill in their text, since it can include the linked values
+    fn resolve(mut artifacts: Artifacts,
+               mut settings: Settings,
+               loaded_vars: Vec<(PathBuf, Globals)>,
+               mut repo_map: HashMap<PathBuf, PathBuf>,
+               reviewed: &Reviewed,
+               approvals: &Approvals)
+               -> Result<Project, ProjectError> {
+        info!("resolving variables");
+        let (mut variables, arrays) = try!(vars::resolve_loaded_vars(loaded_vars, &mut repo_map)
+            .map_err(ProjectError::Vars));
+        try!(vars::resolve_code_paths(&mut settings, &variables, &arrays)
+            .map_err(ProjectError::Vars));
+
+        info!("finding and attaching locations");
+        let locs = try!(locs::find_locs(&settings).map_err(ProjectError::Locs));
+        locs::attach_locs(&mut artifacts, &locs);
+
+        try!(link::do_links(&mut artifacts).map_err(ProjectError::Link));
+        try!(link::validate_status(&artifacts, &mut settings).map_err(ProjectError::Link));
+        review::set_suspects(&mut artifacts, reviewed);
+        review::set_approvals(&mut artifacts, approvals);
+
+        info!("filling text fields");
+        try!(vars::transclude(&mut artifacts, &mut repo_map).map_err(ProjectError::Vars));
+        try!(vars::fill_text_fields(&mut artifacts, &mut variables, &mut repo_map)
+            .map_err(ProjectError::Vars));
+        vars::set_mentions(&mut artifacts);
+        link::warn_mentions(&artifacts);
+        Ok(Project {
+            artifacts: artifacts,
+            settings: settings,
+        })
+    }
+
+    pub fn artifacts(&self) -> &Artifacts {
+        &self.artifacts
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// the artifacts and settings, as used by the commands
+    pub fn into_parts(self) -> (Artifacts, Settings) {
+        (self.artifacts, self.settings)
+    }
+
+    /// get an artifact by its name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&Artifact> {
+        match ArtName::from_str(name) {
+            Ok(n) => self.artifacts.get(&n),
+            Err(_) => None,
+        }
+    }
+
+    /// iterate over the artifacts, sorted by name
+    pub fn iter(&self) -> vec::IntoIter<(&ArtNameRc, &Artifact)> {
+        let mut all: Vec<_> = self.artifacts.iter().collect();
+        all.sort_by(|a, b| a.0.cmp(b.0));
+        all.into_iter()
+    }
+
+    /// iterate over the artifacts of a type, sorted by name
+    pub fn iter_type(&self, ty: ArtType) -> vec::IntoIter<(&ArtNameRc, &Artifact)> {
+        let all: Vec<_> = self.iter().filter(|&(_, a)| a.ty == ty).collect();
+        all.into_iter()
+    }
+
+    /// the sorted `parts` of an artifact, or None if it does not exist
+    pub fn parts_of(&self, name: &str) -> Option<Vec<&ArtNameRc>> {
+        self.get(name).map(|a| {
+            let mut parts: Vec<_> = a.parts.iter().collect();
+            parts.sort();
+            parts
+        })
+    }
+
+    /// the sorted `partof` of an artifact, or None if it does not exist
+    pub fn partof_of(&self, name: &str) -> Option<Vec<&ArtNameRc>> {
+        self.get(name).map(|a| {
+            let mut partof: Vec<_> = a.partof.iter().collect();
+            partof.sort();
+            partof
+        })
+    }
+
+    /// the artifacts matching a query (as used by `rst ls -Q`), sorted by name
+    pub fn search(&self, query: &str) -> Result<Vec<(&ArtNameRc, &Artifact)>, ProjectError> {
+        let query = try!(Query::from_str(query).map_err(ProjectError::Query));
+        Ok(self.iter().filter(|&(n, a)| query.matches(n, a)).collect())
+    }
+
+    /// the stats of all the artifacts
+    pub fn stats(&self) -> Stats {
+        get_stats(self.artifacts.values())
+    }
+
+    /// the stats of the artifacts of a type
+    pub fn stats_of(&self, ty: ArtType) -> Stats {
+        get_stats(self.artifacts.values().filter(|a| a.ty == ty))
+    }
+}
+
+/// the version of the format that projects are serialized in. It is
+/// incremented whenever the format changes incompatibly
+pub const FORMAT_VERSION: u32 = 1;
+
+/// a serialized project, i.e. `{"version": 1, "artifacts": {...}, "settings": {...}}`
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct ProjectRef<'a> {
+    version: u32,
+    artifacts: &'a Artifacts,
+    settings: &'a Settings,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct ProjectData {
+    version: u32,
+    artifacts: Artifacts,
+    settings: Settings,
+}
+
+/// projects are serialized with the `FORMAT_VERSION` and their linked
+/// artifacts, so they do not need to be linked again when deserialized
+/// partof: #SPC-project-serde
+#[cfg(feature = "serde")]
+impl ser::Serialize for Project {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ProjectRef {
+                version: FORMAT_VERSION,
+                artifacts: &self.artifacts,
+                settings: &self.settings,
+            }
+            .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> de::Deserialize<'de> for Project {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Project, D::Error> {
+        let data = try!(ProjectData::deserialize(deserializer));
+        if data.version != FORMAT_VERSION {
+            return Err(de::Error::custom(format!("unsupported project format version {}, \
+                                                  expected {}",
+                                                 data.version,
+                                                 FORMAT_VERSION)));
+        }
+        Ok(Project {
+            artifacts: data.artifacts,
+            settings: data.settings,
+        })
+    }
+}
+
+fn get_stats<'a, I: Iterator<Item = &'a Artifact>>(artifacts: I) -> Stats {
+    let mut stats = Stats {
+        count: 0,
+        completed: 0.,
+        tested: 0.,
+    };
+    for a in artifacts {
+        stats.count += 1;
+        stats.completed += a.completed;
+        stats.tested += a.tested;
+    }
+    if stats.count > 0 {
+        stats.completed /= stats.count as f32;
+        stats.tested /= stats.count as f32;
+    }
+    stats
+}
+
+#[test]
+/// partof: #TST-project
+fn test_project() {
+    let repo = Path::new("/repo");
+    let project = Project::from_strs(repo, &[
+        ("reqs/foo.toml", "[globals]\nname = 'foo'\n\
+                           [REQ-foo]\ntext = 'the {name} requirement'\n\
+                           [SPC-foo]\n[TST-foo]\n"),
+        ("reqs/bar/bar.toml", "[REQ-bar]\npartof = 'REQ-foo'\n"),
+    ]).unwrap();
+    assert_eq!(project.get("req-FOO").unwrap().text, "the foo requirement");
+    assert!(project.get("REQ-dne").is_none());
+    assert!(project.get("not a name").is_none());
+    assert_eq!(project.get("REQ-bar").unwrap().path, repo.join("reqs/bar/bar.toml"));
+
+    let names = |v: Vec<&ArtNameRc>| -> Vec<String> { v.iter().map(|n| n.raw.clone()).collect() };
+    assert_eq!(names(project.iter().map(|(n, _)| n).collect()),
+               vec!["REQ", "REQ-bar", "REQ-foo", "SPC", "SPC-foo", "TST", "TST-foo"]);
+    assert_eq!(names(project.iter_type(ArtType::REQ).map(|(n, _)| n).collect()),
+               vec!["REQ", "REQ-bar", "REQ-foo"]);
+    assert_eq!(names(project.parts_of("REQ-foo").unwrap()), vec!["REQ-bar", "SPC-foo"]);
+    assert_eq!(names(project.partof_of("TST-foo").unwrap()), vec!["SPC-foo", "TST"]);
+    assert!(project.parts_of("REQ-dne").is_none());
+
+    let found = project.search("type:REQ and partof:REQ-foo").unwrap();
+    assert_eq!(names(found.iter().map(|&(n, _)| n).collect()), vec!["REQ-bar"]);
+    match project.search("type:") {
+        Err(ProjectError::Query(_)) => {}
+        r => panic!("{:?}", r),
+    }
+
+    assert_eq!(project.stats().count, 7);
+    // nothing is implemented without code
+    assert_eq!(project.stats_of(ArtType::REQ),
+               Stats { count: 3, completed: 0., tested: 0. });
+
+    // each stage has its own error
+    match Project::from_path(Path::new("/dne/project")) {
+        Err(ProjectError::NotFound(p)) => assert_eq!(p, PathBuf::from("/dne/project")),
+        r => panic!("{:?}", r),
+    }
+    match Project::from_strs(repo, &[("foo.toml", "[REQ-foo\n")]) {
+        Err(ProjectError::Load(_)) => {}
+        r => panic!("{:?}", r),
+    }
+    match Project::from_strs(repo, &[("foo.toml", "[REQ-foo]\ntext = '{dne}'\n")]) {
+        Err(ProjectError::Vars(_)) => {}
+        r => panic!("{:?}", r),
+    }
+    match Project::from_strs(repo, &[("foo.toml", "[REQ-foo]\n[SPC-foo]\npartof = 'TST-foo'\n\
+                                                  [TST-foo]\n")]) {
+        Err(ProjectError::Link(_)) => {}
+        r => panic!("{:?}", r),
+    }
+}
+
+#[test]
+/// partof: #TST-project-threads
+fn test_send_sync() {
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Artifacts>();
+    assert_send_sync::<Settings>();
+    assert_send_sync::<Project>();
+
+    let project = Arc::new(Project::from_strs(Path::new("/repo"), &[
+        ("reqs/foo.toml", "[REQ-foo]\n[SPC-foo]\n[SPC-bar]\npartof = 'REQ-foo'\n"),
+    ]).unwrap());
+    let threads: Vec<_> = ["REQ-foo", "SPC-foo", "SPC-bar"]
+        .iter()
+        .map(|name| {
+            let project = project.clone();
+            thread::spawn(move || project.partof_of(name).unwrap().len())
+        })
+        .collect();
+    let partof: Vec<usize> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert_eq!(partof, vec![1, 2, 2]);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+/// partof: #TST-project-serde
+fn test_serde() {
+    use serde_json;
+
+    // names round trip through their raw string and are validated
+    let name = ArtName::from_str("REQ-Foo-bar").unwrap();
+    let json = serde_json::to_string(&name).unwrap();
+    assert_eq!(json, r#""REQ-Foo-bar""#);
+    let loaded: ArtName = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.raw, "REQ-Foo-bar");
+    assert_eq!(loaded, name);
+    assert!(serde_json::from_str::<ArtName>(r#""FOO-bar""#).is_err());
+    assert!(serde_json::from_str::<ArtName>(r#""REQ-foo bar!""#).is_err());
+
+    let project = Project::from_strs(Path::new("/repo"), &[
+        ("reqs/foo.toml", "[settings]\nthresholds = ['REQ completed > 50']\n\
+                           [views.todo]\ncompleted = '<100'\n\
+                           [REQ-foo]\ntext = 'foo'\n[SPC-foo]\n"),
+    ]).unwrap();
+    let json = serde_json::to_string(&project).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"], FORMAT_VERSION);
+    assert_eq!(value["artifacts"]["REQ-foo"]["ty"], "REQ");
+    assert_eq!(value["artifacts"]["REQ-foo"]["parts"][0], "SPC-foo");
+
+    let loaded: Project = serde_json::from_str(&json).unwrap();
+    let names = |p: &Project| -> Vec<String> { p.iter().map(|(n, _)| n.raw.clone()).collect() };
+    assert_eq!(names(&loaded), names(&project));
+    assert_eq!(loaded.get("REQ-foo").unwrap().text, "foo");
+    assert_eq!(loaded.parts_of("REQ-foo"), project.parts_of("REQ-foo"));
+    assert_eq!(loaded.settings().thresholds, project.settings().thresholds);
+    assert_eq!(loaded.settings().views, project.settings().views);
+
+    // other versions of the format are rejected
+    let mut value = value;
+    value["version"] = serde_json::Value::from(FORMAT_VERSION + 1);
+    let err = serde_json::from_value::<Project>(value).unwrap_err();
+    assert!(err.to_string().starts_with("unsupported project format version"));
+}
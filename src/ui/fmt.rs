@@ -1,4 +1,6 @@
 use super::types::*;
+use core::review;
+use core::types::ApprovalState;
 
 /// format ArtNames in a reasonable way
 pub fn fmt_names(names: &Vec<ArtNameRc>) -> String {
@@ -22,36 +24,50 @@ pub fn fmt_artifact(name: &ArtNameRc, artifacts: &Artifacts, fmtset: &FmtSetting
     let artifact = artifacts.get(name).unwrap();
     let mut out = FmtArtifact::default();
     out.long = fmtset.long;
+    out.name_width = fmtset.name_width;
+    out.width = fmtset.width;
     if fmtset.path {
         out.path = Some(artifact.path.clone());
     }
     if fmtset.parts {
         let mut parts: Vec<FmtArtifact> = Vec::new();
-        for p in &artifact.parts {
+        // sorted so the same artifacts are always the ones expanded
+        let mut names: Vec<&ArtNameRc> = artifact.parts.iter().collect();
+        names.sort();
+        for p in names {
             let mut part;
             if recurse == 0 || displayed.contains(p) {
                 part = FmtArtifact::default();
                 part.name = p.clone();
             } else {
-                part = fmt_artifact(&p, artifacts, fmtset, recurse - 1, displayed);
+                // mark as displayed first so that cycles are not followed
                 displayed.insert(p.clone());
+                part = fmt_artifact(p, artifacts, fmtset, recurse - 1, displayed);
             }
             parts.push(part);
         }
-        parts.sort_by_key(|p| p.name.clone());  // TODO: get around clone here
         out.parts = Some(parts);
     }
     if fmtset.partof {
         let mut partof = artifact.partof.iter().map(|p| p.clone()).collect::<Vec<ArtNameRc>>();
         partof.sort();
         let partof = partof.drain(0..)
-            .map(|n| FmtArtifact{name: n, ..FmtArtifact::default()})
+            .map(|n| FmtArtifact{suspect: artifact.suspect.contains(&n),
+                                 name: n, ..FmtArtifact::default()})
             .collect();
         out.partof = Some(partof);
     }
     if fmtset.loc_path {
         out.loc = artifact.loc.clone();
     }
+    if fmtset.approval {
+        let by = artifact.approval.as_ref().map(|a| format!("by {} at {}", a.by, a.at));
+        out.approval = Some(match (review::approval_state(artifact), by) {
+            (ApprovalState::Approved, Some(by)) => format!("approved {}", by),
+            (ApprovalState::Stale, Some(by)) => format!("stale, approved {}", by),
+            _ => "not approved".to_string(),
+        });
+    }
     if fmtset.text {
         if fmtset.long {
             out.text = Some(artifact.text.clone());
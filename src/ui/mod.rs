@@ -5,7 +5,10 @@
 mod types;
 pub mod fmt;
 mod search;
+pub mod query;
 
-pub use ui::types::{FmtSettings, FmtArtifact, PercentSearch, SearchSettings};
-pub use ui::search::show_artifact;
+pub use ui::types::{FmtSettings, FmtArtifact, PercentSearch, SearchSettings, SortBy, GroupBy,
+                    OutputFormat};
+pub use ui::query::Query;
+pub use ui::search::{show_artifact, VALID_SEARCH_FIELDS};
 pub use ui::fmt::{fmt_artifact, fmt_names};
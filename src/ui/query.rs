@@ -0,0 +1,413 @@
+//! a query language for filtering artifacts, i.e.
+//! `type:SPC and completed<100 and (text~"cache" or path~"core/") and not has:loc`
+//!
+//! a query is a boolean expression of `FIELD OP VALUE` comparisons joined
+//! by `and`, `or` and `not`, where `and` binds tighter than `or`
+
+use super::types::*;
+use core::review;
+use core::types::ApprovalState;
+
+/// the fields of an artifact that can be queried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Type,
+    Path,
+    Text,
+    Loc,
+    Parts,
+    Partof,
+    Completed,
+    Tested,
+    Status,
+    Suspect,
+    Approval,
+}
+
+const FIELDS: [(&'static str, Field); 12] = [("name", Field::Name),
+                                            ("type", Field::Type),
+                                            ("path", Field::Path),
+                                            ("text", Field::Text),
+                                            ("loc", Field::Loc),
+                                            ("parts", Field::Parts),
+                                            ("partof", Field::Partof),
+                                            ("completed", Field::Completed),
+                                            ("tested", Field::Tested),
+                                            ("status", Field::Status),
+                                            ("suspect", Field::Suspect),
+                                            ("approval", Field::Approval)];
+
+/// the operators used to compare percents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// a parsed query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    /// `has:FIELD`, the field is not empty
+    Has(Field),
+    /// `FIELD:VALUE` or `FIELD=VALUE`, any value of the field is equal
+    /// ignoring case
+    Equals(Field, String),
+    /// `FIELD~REGEX`, any value of the field matches ignoring case
+    Matches(Field, Regex),
+    /// compare the completed or tested percent
+    Percent(Field, Op, i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Pred(Query),
+}
+
+const OPS: [&'static str; 8] = ["!=", "<=", ">=", ":", "=", "~", "<", ">"];
+
+fn get_field(name: &str) -> Result<Field, String> {
+    match FIELDS.iter().find(|f| f.0 == name.to_lowercase()) {
+        Some(f) => Ok(f.1),
+        None => {
+            let names: Vec<&str> = FIELDS.iter().map(|f| f.0).collect();
+            Err(format!("unknown field `{}`, the fields are: has, {}", name, names.join(", ")))
+        }
+    }
+}
+
+/// build the query for a single comparison
+fn get_predicate(field: &str, op: &str, value: &str) -> Result<Query, String> {
+    if field.to_lowercase() == "has" {
+        if op != ":" && op != "=" {
+            return Err(format!("`has` must be used as `has:FIELD`, got `has{}`", op));
+        }
+        return match try!(get_field(value)) {
+            f @ Field::Loc | f @ Field::Text | f @ Field::Parts | f @ Field::Partof |
+            f @ Field::Status | f @ Field::Suspect | f @ Field::Approval => Ok(Query::Has(f)),
+            _ => Err(format!("`has:` is only valid for loc, text, parts, partof, status, \
+                              suspect and approval. Got `{}`", value)),
+        };
+    }
+    let f = try!(get_field(field));
+    if f == Field::Completed || f == Field::Tested {
+        let perc = try!(value.trim_right_matches('%').parse::<i64>()
+            .map_err(|_| format!("`{}` must be compared to a percent, got `{}`", field, value)));
+        return Ok(match op {
+            ":" | "=" => Query::Percent(f, Op::Eq, perc),
+            "!=" => Query::Not(Box::new(Query::Percent(f, Op::Eq, perc))),
+            "<" => Query::Percent(f, Op::Lt, perc),
+            "<=" => Query::Percent(f, Op::Le, perc),
+            ">" => Query::Percent(f, Op::Gt, perc),
+            ">=" => Query::Percent(f, Op::Ge, perc),
+            _ => return Err(format!("`{}` cannot be used with percents", op)),
+        });
+    }
+    match op {
+        ":" | "=" => Ok(Query::Equals(f, value.to_string())),
+        "!=" => Ok(Query::Not(Box::new(Query::Equals(f, value.to_string())))),
+        "~" => {
+            let re = try!(RegexBuilder::new(value)
+                .case_insensitive(true)
+                .compile()
+                .map_err(|e| format!("invalid regex `{}`: {}", value, e)));
+            Ok(Query::Matches(f, re))
+        }
+        _ => Err(format!("`{}` can only be used with completed and tested", op)),
+    }
+}
+
+/// split the query into tokens, parsing each comparison
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let skip_space = |i: &mut usize| {
+        while *i < chars.len() && chars[*i].is_whitespace() {
+            *i += 1;
+        }
+    };
+    loop {
+        skip_space(&mut i);
+        if i >= chars.len() {
+            break;
+        }
+        match chars[i] {
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+                continue;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let start = i;
+        while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().cloned().collect();
+        if word.is_empty() {
+            return Err(format!("unexpected `{}` at {}", chars[i], i));
+        }
+        skip_space(&mut i);
+        let rest: String = chars[i..].iter().cloned().collect();
+        let op = match OPS.iter().find(|op| rest.starts_with(*op)) {
+            Some(op) => *op,
+            None => {
+                tokens.push(match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => return Err(format!("expected an operator after `{}`", word)),
+                });
+                continue;
+            }
+        };
+        i += op.len();
+        skip_space(&mut i);
+
+        let mut value = String::new();
+        if i < chars.len() && chars[i] == '"' {
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    Some(&'"') => break,
+                    Some(&'\\') if i + 1 < chars.len() => {
+                        value.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(c) => {
+                        value.push(*c);
+                        i += 1;
+                    }
+                    None => return Err(format!("unclosed quote in `{}{}`", word, op)),
+                }
+            }
+            i += 1;
+        } else {
+            while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' &&
+                  chars[i] != ')' {
+                value.push(chars[i]);
+                i += 1;
+            }
+            if value.is_empty() {
+                return Err(format!("expected a value after `{}{}`", word, op));
+            }
+        }
+        tokens.push(Token::Pred(try!(get_predicate(&word, op, &value))));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next_is(&self, token: &Token) -> bool {
+        self.tokens.get(self.pos) == Some(token)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, String> {
+        let mut query = try!(self.parse_and());
+        while self.next_is(&Token::Or) {
+            self.pos += 1;
+            query = Query::Or(Box::new(query), Box::new(try!(self.parse_and())));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, String> {
+        let mut query = try!(self.parse_not());
+        while self.next_is(&Token::And) {
+            self.pos += 1;
+            query = Query::And(Box::new(query), Box::new(try!(self.parse_not())));
+        }
+        Ok(query)
+    }
+
+    fn parse_not(&mut self) -> Result<Query, String> {
+        if self.next_is(&Token::Not) {
+            self.pos += 1;
+            return Ok(Query::Not(Box::new(try!(self.parse_not()))));
+        }
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Pred(q)) => Ok(q),
+            Some(Token::Open) => {
+                let query = try!(self.parse_or());
+                if !self.next_is(&Token::Close) {
+                    return Err("missing `)`".to_string());
+                }
+                self.pos += 1;
+                Ok(query)
+            }
+            Some(t) => {
+                let t = match t {
+                    Token::Close => ")",
+                    Token::And => "and",
+                    _ => "or",
+                };
+                Err(format!("expected a comparison, got `{}`", t))
+            }
+            None => Err("the query ended early".to_string()),
+        }
+    }
+}
+
+/// get the values of a field which are compared as text
+fn text_values(field: Field, name: &ArtName, artifact: &Artifact) -> Vec<String> {
+    let names = |names: &ArtNames| -> Vec<String> { names.iter().map(|n| n.raw.clone()).collect() };
+    match field {
+        Field::Name => vec![name.raw.clone()],
+        Field::Type => vec![format!("{:?}", artifact.ty)],
+        Field::Path => vec![artifact.path.display().to_string()],
+        Field::Text => vec![artifact.text.clone()],
+        Field::Loc => artifact.loc.iter().map(|l| l.path.display().to_string()).collect(),
+        Field::Parts => names(&artifact.parts),
+        Field::Partof => names(&artifact.partof),
+        Field::Completed => vec![((artifact.completed * 100.).round() as i64).to_string()],
+        Field::Tested => vec![((artifact.tested * 100.).round() as i64).to_string()],
+        Field::Status => artifact.status.iter().cloned().collect(),
+        Field::Suspect => names(&artifact.suspect),
+        Field::Approval => vec![match review::approval_state(artifact) {
+            ApprovalState::Approved => "approved",
+            ApprovalState::Stale => "stale",
+            ApprovalState::Missing => "missing",
+        }.to_string()],
+    }
+}
+
+impl Query {
+    /// parse a query
+    /// partof: #SPC-ui-query
+    pub fn from_str(s: &str) -> Result<Query, String> {
+        let mut parser = Parser {
+            tokens: try!(tokenize(s)),
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err("the query is empty".to_string());
+        }
+        let query = try!(parser.parse_or());
+        match parser.tokens.get(parser.pos) {
+            Some(&Token::Close) => Err("unmatched `)`".to_string()),
+            Some(_) => Err("expected `and` or `or` between comparisons".to_string()),
+            None => Ok(query),
+        }
+    }
+
+    /// return whether the artifact matches the query
+    pub fn matches(&self, name: &ArtName, artifact: &Artifact) -> bool {
+        match *self {
+            Query::And(ref a, ref b) => a.matches(name, artifact) && b.matches(name, artifact),
+            Query::Or(ref a, ref b) => a.matches(name, artifact) || b.matches(name, artifact),
+            Query::Not(ref q) => !q.matches(name, artifact),
+            Query::Has(field) => match field {
+                Field::Loc => artifact.loc.is_some(),
+                Field::Text => !artifact.text.trim().is_empty(),
+                Field::Parts => !artifact.parts.is_empty(),
+                Field::Partof => !artifact.partof.is_empty(),
+                Field::Status => artifact.status.is_some(),
+                Field::Suspect => !artifact.suspect.is_empty(),
+                Field::Approval => artifact.approval.is_some(),
+                _ => true,
+            },
+            Query::Equals(field, ref value) => {
+                let value = value.to_lowercase();
+                text_values(field, name, artifact).iter().any(|v| v.to_lowercase() == value)
+            }
+            Query::Matches(field, ref re) => {
+                text_values(field, name, artifact).iter().any(|v| re.is_match(v))
+            }
+            Query::Percent(field, op, perc) => {
+                let value = if field == Field::Completed {
+                    artifact.completed
+                } else {
+                    artifact.tested
+                };
+                let value = (value * 100.).round() as i64;
+                match op {
+                    Op::Eq => value == perc,
+                    Op::Lt => value < perc,
+                    Op::Le => value <= perc,
+                    Op::Gt => value > perc,
+                    Op::Ge => value >= perc,
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_query() {
+    let q = Query::from_str("type:SPC and completed<100 or not has:loc").unwrap();
+    assert_eq!(q,
+               Query::Or(Box::new(Query::And(Box::new(Query::Equals(Field::Type,
+                                                                     "SPC".to_string())),
+                                             Box::new(Query::Percent(Field::Completed,
+                                                                     Op::Lt,
+                                                                     100)))),
+                         Box::new(Query::Not(Box::new(Query::Has(Field::Loc))))));
+    let q = Query::from_str("(text ~ \"a \\\"b\\\"\")").unwrap();
+    let re = RegexBuilder::new("a \"b\"").case_insensitive(true).compile().unwrap();
+    assert_eq!(q, Query::Matches(Field::Text, re));
+
+    for invalid in &["", "foo:bar", "type<3", "completed~1", "has:name", "name:",
+                     "(name:a", "name:a)", "name:a name:b", "text~\"a", "text~(", "and"] {
+        assert!(Query::from_str(invalid).is_err(), "{} should be invalid", invalid);
+    }
+}
+
+#[test]
+/// partof: #TST-ui-query
+fn test_query_matches() {
+    let (name, mut spc) = Artifact::from_str("[SPC-cache]
+            partof = 'REQ-cache'
+            text = 'the Cache is fast'").unwrap();
+    spc.path = PathBuf::from("reqs/core/cache.toml");
+    spc.completed = 0.5;
+    let matches = |q: &str, artifact: &Artifact| {
+        Query::from_str(q).unwrap().matches(&name, artifact)
+    };
+    assert!(matches("type:spc", &spc));
+    assert!(matches("name=SPC-CACHE and partof:req-cache", &spc));
+    assert!(matches("type:SPC and completed<100 and (text~\"cache\" or path~\"core/\") \
+                     and not has:loc",
+                    &spc));
+    assert!(matches("completed=50 and tested<=0 and completed>=50%", &spc));
+    assert!(matches("text~^the and not text~^cache", &spc));
+    assert!(!matches("type!=SPC or has:parts or completed>50", &spc));
+    spc.loc = Some(Loc::fake());
+    assert!(matches("has:loc and loc~.", &spc));
+    assert!(!matches("has:status or status:draft", &spc));
+    spc.status = Some("draft".to_string());
+    assert!(matches("has:status and status:DRAFT and status!=approved", &spc));
+    assert!(matches("not has:suspect and not has:approval and approval:missing", &spc));
+    spc.approval = Some(::core::types::Approval {
+        by: "Jane".into(),
+        at: "now".into(),
+        hash: review::hash_approval(&spc),
+    });
+    assert!(matches("has:approval and approval:approved", &spc));
+    spc.text = "changed".to_string();
+    spc.hash = review::hash_text(&spc.text);
+    assert!(matches("approval:stale", &spc));
+}
@@ -18,6 +18,7 @@ impl SearchSettings {
             text: false,
             completed: PercentSearch{lt: false, perc: 0},
             tested: PercentSearch{lt: false, perc: 0},
+            query: None,
         }
     }
 
@@ -42,6 +43,7 @@ impl SearchSettings {
             text: pattern.contains(&'T'),
             completed: PercentSearch{lt: false, perc: 0},
             tested: PercentSearch{lt: false, perc: 0},
+            query: None,
         };
         if pattern.contains(&'A') {
             set.name = !set.name;
@@ -73,6 +75,11 @@ pub fn show_artifact(name: &ArtName,
     let ss = search_settings;
     let completed = (art.completed * 100.0).round() as u8;
     let tested = (art.tested * 100.0).round() as u8;
+    if let Some(ref q) = ss.query {
+        if !q.matches(name, art) {
+            return false;
+        }
+    }
     if (ss.completed.lt && completed > ss.completed.perc)
         || (!ss.completed.lt && completed < ss.completed.perc)
         || (ss.tested.lt && tested > ss.tested.perc)
@@ -1,5 +1,5 @@
 // Traits
-pub use std::rc::Rc;
+pub use std::sync::Arc;
 pub use std::io::Write;
 pub use std::fmt::Write as FmtWrite;
 pub use std::iter::FromIterator;
@@ -15,6 +15,56 @@ pub use super::super::core::{
     ArtType, Loc,
     ArtName, ArtNameRc, ArtNames,
     LoadFromStr};
+pub use super::query::Query;
+
+/// the field that artifacts are sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Name,
+    Completed,
+    Tested,
+    Path,
+    Loc,
+}
+
+impl Default for SortBy {
+    fn default() -> SortBy {
+        SortBy::Name
+    }
+}
+
+/// how artifacts are grouped when they are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Nothing,
+    Type,
+    File,
+    Parent,
+}
+
+impl Default for GroupBy {
+    fn default() -> GroupBy {
+        GroupBy::Nothing
+    }
+}
+
+/// how the artifacts are written by `ls`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// the table for humans
+    Table,
+    Json,
+    Csv,
+    Tsv,
+    /// a strfmt template written once for every artifact
+    Template(String),
+}
+
+impl Default for OutputFormat {
+    fn default() -> OutputFormat {
+        OutputFormat::Table
+    }
+}
 
 /// settings for what to format
 /// [#SPC-core-fmt-settings]
@@ -27,14 +77,23 @@ pub struct FmtSettings {
     pub partof: bool,
     pub loc_path: bool,
     pub text: bool,
+    pub approval: bool,
     pub color: bool,
+    pub sort: SortBy,
+    pub reverse: bool,
+    pub group_by: GroupBy,
+    pub output: OutputFormat,
+    /// the width of the name column in the table
+    pub name_width: usize,
+    /// the width of the terminal, None if the output is not limited
+    pub width: Option<usize>,
 }
 
 impl FmtSettings {
     pub fn is_empty(&self) -> bool {
         !self.long && !self.path && !self.parts
             && !self.partof && !self.loc_path
-            && !self.text
+            && !self.text && !self.approval
     }
 }
 
@@ -44,6 +103,7 @@ impl FmtSettings {
 /// such as completed or tested
 /// [#SPC-core-fmt-artifact]
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FmtArtifact {
     pub long: bool,
     pub path: Option<PathBuf>,
@@ -54,7 +114,13 @@ pub struct FmtArtifact {
     // pub loc_line_col: (usize, usize),
     // pub loc_valid: Option<bool>,
     pub text: Option<String>,
+    /// who approved the artifact and when, see SPC-approve
+    pub approval: Option<String>,
     pub name: ArtNameRc,
+    pub name_width: usize,
+    pub width: Option<usize>,
+    /// the artifact is a parent whose text changed since the link was reviewed
+    pub suspect: bool,
 }
 
 
@@ -75,4 +141,6 @@ pub struct SearchSettings {
     pub text: bool,
     pub completed: PercentSearch,
     pub tested: PercentSearch,
+    /// only artifacts matching the query are shown
+    pub query: Option<Query>,
 }